@@ -41,23 +41,32 @@ fn main() {
     let soldier_handle = scene.add_mesh(rt::Mesh::load_obj("./examples/assets/soldier.obj").unwrap());
 
     let floor=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,-1.1,0.0))*rt::scale(100.0,100.0,100.0), 
-        floor_material);
+        floor_material).unwrap();
     let floor_shiny=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,-1.0,0.0))
         *rt::rotation(&UVec3f::new_normalize(Vec3f::x()), f32::consts::PI)*rt::scale(100.0,100.0,100.0), 
-        glass_material);
+        glass_material).unwrap();
 
-    const SOLDER_GRID_WIDTH : u32 = 20;
-    const SOLDER_GRID_HEIGHT : u32 = 20;
-    
-    for i in 1..SOLDER_GRID_WIDTH+1 {
-        for j in 1..SOLDER_GRID_HEIGHT+1 {
-            let x = i as f32 - SOLDER_GRID_WIDTH as f32 / 2.0;
-            let z = j as f32 * 1.2;
+    const SQUAD_WIDTH : u32 = 5;
+    const SQUAD_DEPTH : u32 = 5;
+    const SQUAD_COUNT_W : u32 = 4;
+    const SQUAD_COUNT_D : u32 = 4;
+
+    let mut squad = rt::Group::default();
+    for i in 0..SQUAD_WIDTH {
+        for j in 0..SQUAD_DEPTH {
+            let soldier: Object=  rt::Object::new(soldier_handle, rt::translate(&Vec3f::new(i as f32, 0.0, j as f32 * 1.2))
+                *rt::rotation(&UVec3f::new_normalize(Vec3f::y()), f32::consts::PI), 
+                *materials.choose(&mut rand::thread_rng()).unwrap()).unwrap();
+            squad.add_object(soldier);
+        }
+    }
+    scene.define_group("squad", squad);
 
-            let soldier: Object=  rt::Object::new(soldier_handle, rt::translate(&Vec3f::new(x,-1.0,z))
-                *rt::rotation(&UVec3f::new_normalize(Vec3f::y()), f32::consts::PI)*rt::scale(1.0,1.0,1.0), 
-                *materials.choose(&mut rand::thread_rng()).unwrap());
-            scene.add_object(soldier);
+    for i in 0..SQUAD_COUNT_W {
+        for j in 0..SQUAD_COUNT_D {
+            let x = (i*SQUAD_WIDTH + 1) as f32 - (SQUAD_WIDTH*SQUAD_COUNT_W) as f32 / 2.0;
+            let z = (j*SQUAD_DEPTH + 1) as f32 * 1.2;
+            scene.instantiate("squad", rt::translate(&Vec3f::new(x,-1.0,z)));
         }
     }
      
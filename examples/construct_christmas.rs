@@ -43,31 +43,21 @@ fn main() {
     let support = scene.add_mesh(rt::Mesh::load_obj("./examples/assets/tree/support.obj").unwrap());
     let trunk = scene.add_mesh(rt::Mesh::load_obj("./examples/assets/tree/trunk.obj").unwrap());
 
-    let background =  rt::Object::new(background, Mat4f::identity(), background_material);
-    let base =  rt::Object::new(base, Mat4f::identity(), base_material);
-    let big_star =  rt::Object::new(big_star, Mat4f::identity(), big_star_material);
-    let inner_shell =  rt::Object::new(inner_shell, Mat4f::identity(), inner_shell_material);
-    let leafs =  rt::Object::new(leafs, Mat4f::identity(), leafs_material);
-    let outer_shell =  rt::Object::new(outer_shell, Mat4f::identity(), outer_shell_material);
-    let spheres =  rt::Object::new(spheres, Mat4f::identity(), spheres_material);
-    let spirale =  rt::Object::new(spirale, Mat4f::identity(), spirale_material);
-    let stars =  rt::Object::new(stars, Mat4f::identity(), stars_material);
-    let sugar_canes =  rt::Object::new(sugar_canes, Mat4f::identity(), sugar_canes_material);
-    let support =  rt::Object::new(support, Mat4f::identity(), support_material);
-    let trunk =  rt::Object::new(trunk, Mat4f::identity(), trunk_material);
+    let mut snow_globe = rt::Group::new(Mat4f::identity());
+    snow_globe.add_object(rt::Object::new(background, Mat4f::identity(), background_material).unwrap());
+    snow_globe.add_object(rt::Object::new(base, Mat4f::identity(), base_material).unwrap());
+    snow_globe.add_object(rt::Object::new(big_star, Mat4f::identity(), big_star_material).unwrap());
+    snow_globe.add_object(rt::Object::new(inner_shell, Mat4f::identity(), inner_shell_material).unwrap());
+    snow_globe.add_object(rt::Object::new(leafs, Mat4f::identity(), leafs_material).unwrap());
+    snow_globe.add_object(rt::Object::new(outer_shell, Mat4f::identity(), outer_shell_material).unwrap());
+    snow_globe.add_object(rt::Object::new(spheres, Mat4f::identity(), spheres_material).unwrap());
+    snow_globe.add_object(rt::Object::new(spirale, Mat4f::identity(), spirale_material).unwrap());
+    snow_globe.add_object(rt::Object::new(stars, Mat4f::identity(), stars_material).unwrap());
+    snow_globe.add_object(rt::Object::new(sugar_canes, Mat4f::identity(), sugar_canes_material).unwrap());
+    snow_globe.add_object(rt::Object::new(support, Mat4f::identity(), support_material).unwrap());
+    snow_globe.add_object(rt::Object::new(trunk, Mat4f::identity(), trunk_material).unwrap());
 
-    scene.add_object(background);
-    scene.add_object(base);
-    scene.add_object(big_star);
-    scene.add_object(inner_shell);
-    scene.add_object(leafs);
-    scene.add_object(outer_shell);
-    scene.add_object(spheres);
-    scene.add_object(spirale);
-    scene.add_object(stars);
-    scene.add_object(sugar_canes);
-    scene.add_object(support);
-    scene.add_object(trunk);
+    scene.add_node(rt::Node::Group(snow_globe));
 
 
     println!("Saving the scene to: ./christmas.json ...");
@@ -9,7 +9,7 @@ fn main() {
     const HEIGHT: u32 = 1500;
     
     let camera = rt::Camera::new(Vec3f::new(0.0,0.0,-3.0), Vec3f::z(), Vec3f::y(), 45.0 / 180. * f32::consts::PI);
-    let renderer = rt::RayTracer;
+    let renderer = rt::RayTracer::new();
     
     
     let mut scene = rt::Scene::new(camera, Box::new(rt::ConstantEnvironment {color: Vec3f::new(0.1,0.1,0.1)}) );
@@ -38,35 +38,35 @@ fn main() {
     let cube_handle = scene.add_mesh(rt::Mesh::load_obj("./examples/assets/cube.obj").unwrap());
 
     let floor=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,-1.0,0.0)), 
-                white_material);
+                white_material).unwrap();
 
     let ceiling=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,1.0,0.0)), 
-                white_material);
+                white_material).unwrap();
 
     let front_wall: Object=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,0.0,1.0))*rt::rotation(&UVec3f::new_normalize(Vec3f::x()), f32::consts::FRAC_PI_2), 
-        pink_material);
+        pink_material).unwrap();
 
     let left_wall: Object=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(1.0,0.0,0.0))*rt::rotation(&UVec3f::new_normalize(Vec3f::z()), f32::consts::FRAC_PI_2), 
         bleu_material
-    );
+    ).unwrap();
 
     let right_wall: Object=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(-1.0,0.0,0.0))*rt::rotation(&UVec3f::new_normalize(Vec3f::z()), f32::consts::FRAC_PI_2), 
         white_material
-    );
+    ).unwrap();
 
     let lamp: Object=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,0.99,0.0))*rt::scale(0.2, 0.2, 0.2), 
         glowing_material
-    );
+    ).unwrap();
 
     let bunny: Object=  rt::Object::new(bunny_handle, rt::translate(&Vec3f::new(0.5,-0.85,-0.2))*rt::rotation(&UVec3f::new_normalize(Vec3f::y()), -3.0*f32::consts::PI/4.0)*rt::scale(0.15, 0.15, 0.15), 
-        glass_material);
+        glass_material).unwrap();
 
     let dragon: Object=  rt::Object::new(dragon_handle, rt::translate(&Vec3f::new(-0.3,-1.0,0.0))
         *rt::rotation(&UVec3f::new_normalize(Vec3f::y()), -f32::consts::PI/4.0)*rt::scale(1.0,1.0,1.0), 
-        gold_material);
+        gold_material).unwrap();
     let cube: Object=  rt::Object::new(cube_handle, rt::translate(&Vec3f::new(0.3,-0.2,1.0))
         *rt::rotation(&UVec3f::new_normalize(Vec3f::y()), f32::consts::PI/6.0)*rt::scale(0.3,0.8,0.3), 
-        mirror_material);
+        mirror_material).unwrap();
     
     scene.add_object(floor);
     scene.add_object(ceiling);
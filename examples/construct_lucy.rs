@@ -30,30 +30,30 @@ fn main() {
     let lucy_flame = scene.add_mesh(rt::Mesh::load_obj("./examples/assets/lucy_flame.obj").unwrap());
     let lucy_shell = scene.add_mesh(rt::Mesh::load_obj("./examples/assets/lucy_shell_outer.obj").unwrap());
 
-    let floor = rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,-1.0,0.0))*rt::scale(3.5,3.5,3.5), faint_material);
+    let floor = rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,-1.0,0.0))*rt::scale(3.5,3.5,3.5), faint_material).unwrap();
 
-    let ceiling =  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,1.0,0.0))*rt::scale(3.5,3.5,3.5), bleu_material);
+    let ceiling =  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,1.0,0.0))*rt::scale(3.5,3.5,3.5), bleu_material).unwrap();
 
     let front_wall: Object=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,0.0,1.0))*rt::rotation(&UVec3f::new_normalize(Vec3f::x()), f32::consts::FRAC_PI_2)*rt::scale(3.5,3.5,3.5), 
-    mirror_material);
+    mirror_material).unwrap();
 
     let back_wall: Object=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(0.0,0.0,-3.5))*rt::rotation(&UVec3f::new_normalize(Vec3f::x()), f32::consts::FRAC_PI_2)*rt::scale(3.5,3.5,3.5), 
-    mirror_material);
+    mirror_material).unwrap();
 
     let left_wall: Object=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(1.0,0.0,0.0))*rt::rotation(&UVec3f::new_normalize(Vec3f::z()), f32::consts::FRAC_PI_2)*rt::scale(3.5,3.5,3.5), 
         mirror_material
-    );
+    ).unwrap();
 
     let right_wall: Object=  rt::Object::new(plane_handle, rt::translate(&Vec3f::new(-1.0,0.0,0.0))*rt::rotation(&UVec3f::new_normalize(Vec3f::z()), f32::consts::FRAC_PI_2)*rt::scale(3.5,3.5,3.5), 
         mirror_material
-    );
+    ).unwrap();
 
     let lucy: Object=  rt::Object::new(lucy, rt::translate(&Vec3f::new(0.0,-0.2,0.0))*rt::rotation(&UVec3f::new_normalize(Vec3f::y()), f32::consts::FRAC_PI_2)*rt::scale(0.8,0.8,0.8), 
         white_material
-    );
+    ).unwrap();
     let lucy_flame: Object=  rt::Object::new(lucy_flame, rt::translate(&Vec3f::new(0.0,-0.2,0.0))*rt::rotation(&UVec3f::new_normalize(Vec3f::y()), f32::consts::FRAC_PI_2)*rt::scale(0.8,0.8,0.8), 
         glowing_material
-    );
+    ).unwrap();
     
     scene.add_object(floor);
     scene.add_object(ceiling);
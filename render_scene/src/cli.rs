@@ -0,0 +1,404 @@
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ray_tracer as rt;
+
+pub const USAGE: &str = "\
+Usage:
+    render_scene <scene> [options]
+    render_scene info <scene>
+    render_scene validate <scene>...
+    render_scene convert <input> <output> [--embed-meshes]
+    render_scene bench <scene> [options]
+    render_scene upgrade <scene>...
+    render_scene worker [--listen ADDRESS] [--threads N]
+    render_scene serve [--listen ADDRESS] [--threads N]
+
+Commands:
+    info                    print the size of the scene, its meshes and their BVH
+    validate                check the handles, transforms and meshes of scenes
+    convert                 write a scene in another format: JSON (.json), OBJ (.obj), glTF (.gltf, .glb)
+                            or a binary mesh (.rtmesh) for scenes of a single mesh. With --embed-meshes,
+                            the geometry is written into JSON scenes instead of referring to mesh files
+    bench                   time a render, 256x256 at 16 samples per pixel with the seed 0 unless
+                            the options say otherwise, and report the rays per second
+    worker                  render for coordinators started with --workers, one after the other, listening
+                            on 127.0.0.1:7878 unless --listen says otherwise
+    serve                   render the jobs posted to an HTTP API one after the other, listening on
+                            127.0.0.1:8080 unless --listen says otherwise:
+                                POST /jobs                  {\"scene\": {...}, \"width\": 640, \"height\": 480, \"spp\": 256, ...}
+                                GET  /jobs, /jobs/ID        status and progress of the jobs
+                                GET  /jobs/ID/image.png     image rendered so far, or .exr, .hdr...
+                                POST /jobs/ID/cancel        stop a job
+
+Options:
+    --resolution WxH        size of the image (default 700x700)
+    --spp N                 samples per pixel, the most a pixel gets with a time limit or a noise target (default 3000)
+    --max-depth N           maximum number of bounces (default 10)
+    --threads N             worker threads (default one per CPU)
+    --tile-order ORDER      scanline, spiral or hilbert, the order tiles are rendered in (default scanline)
+    -o, --output PATH       image to write (default render.png)
+    --format FORMAT         png, jpeg, bmp, tga, exr or hdr (default from the output extension)
+    --seed N                seed of the random numbers, for reproducible renders
+    --tone-mapping MODE     clamp, reinhard or aces (default clamp)
+    --exposure STOPS        brightness adjustment before tone mapping (default 0)
+    --crop X,Y,W,H          only render this region of the image
+    --time-limit DURATION   render in passes until this long has passed, such as 90, 30s, 5m or 1h
+    --noise-target NOISE    render in passes until the estimated noise is below this, such as 0.01 for 1%
+    --frames A..B           render the frames A to B - 1 of the animation, or A to B with A..=B
+    --fps N                 frames per second of the animation (default 24)
+    --workers ADDRESSES     render on the workers at these comma separated addresses, such as
+                            192.168.1.20:7878,192.168.1.21:7878, instead of this machine
+    --no-mesh-cache         parse the meshes the scene refers to instead of using, or writing, the
+                            binary cache next to them
+    --dry-run               print the scene statistics and the settings without rendering
+    -h, --help              print this message";
+
+pub enum Command {
+    Render(RenderArgs),
+    Info(String),
+    Validate(Vec<String>),
+    Convert { input: String, output: PathBuf, embed_meshes: bool },
+    Bench(RenderArgs),
+    Upgrade(Vec<String>),
+    Worker { listen: String, threads: Option<usize> },
+    Serve { listen: String, threads: Option<usize> },
+    Help,
+}
+
+#[derive(Debug)]
+pub struct RenderArgs {
+    pub scene: String,
+    pub width: u32,
+    pub height: u32,
+    pub options: rt::RenderOptions,
+    // Worker threads, one per CPU when `None`.
+    pub threads: Option<usize>,
+    pub output: PathBuf,
+    pub save_options: rt::ImageSaveOptions,
+    pub load_options: rt::LoadOptions,
+    pub frames: Option<RangeInclusive<u32>>,
+    pub fps: f32,
+    // Addresses of `render_scene worker` processes to render on, this machine when empty.
+    pub workers: Vec<String>,
+    pub dry_run: bool,
+}
+
+impl RenderArgs {
+    fn new(scene: String) -> RenderArgs {
+        RenderArgs {
+            scene,
+            width: 700,
+            height: 700,
+            options: rt::RenderOptions { rays_per_pixel: 3000, ..Default::default() },
+            threads: None,
+            output: PathBuf::from("render.png"),
+            save_options: rt::ImageSaveOptions::default(),
+            load_options: rt::LoadOptions::default(),
+            frames: None,
+            fps: 24.0,
+            workers: Vec::new(),
+            dry_run: false,
+        }
+    }
+
+    // Frames of an animation are written next to each other: render_0001.png, render_0002.png...
+    pub fn frame_output(&self, frame: u32) -> PathBuf {
+        let stem = self.output.file_stem().and_then(|stem| stem.to_str()).unwrap_or("frame");
+        let name = match self.output.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => format!("{stem}_{frame:04}.{extension}"),
+            None => format!("{stem}_{frame:04}"),
+        };
+        self.output.with_file_name(name)
+    }
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let Some(first) = args.first() else {
+        return Err("no scene provided".to_string());
+    };
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(Command::Help);
+    }
+    let scene = |command: &str| args.get(1).cloned().ok_or(format!("{command} expects a scene"));
+    match first.as_str() {
+        "upgrade" => Ok(Command::Upgrade(args[1..].to_vec())),
+        "info" => {
+            if args.len() > 2 {
+                return Err("info expects a single scene".to_string());
+            }
+            Ok(Command::Info(scene("info")?))
+        }
+        "validate" => {
+            if args.len() < 2 {
+                return Err("validate expects at least one scene".to_string());
+            }
+            Ok(Command::Validate(args[1..].to_vec()))
+        }
+        "convert" => parse_convert(&args[1..]),
+        "worker" => {
+            let (listen, threads) = parse_server(&args[1..], "127.0.0.1:7878")?;
+            Ok(Command::Worker { listen, threads })
+        }
+        "serve" => {
+            let (listen, threads) = parse_server(&args[1..], "127.0.0.1:8080")?;
+            Ok(Command::Serve { listen, threads })
+        }
+        "bench" => {
+            let defaults = RenderArgs {
+                width: 256,
+                height: 256,
+                options: rt::RenderOptions { rays_per_pixel: 16, seed: Some(0), ..Default::default() },
+                ..RenderArgs::new(scene("bench")?)
+            };
+            Ok(Command::Bench(parse_render(&args[2..], defaults)?))
+        }
+        _ => Ok(Command::Render(parse_render(&args[1..], RenderArgs::new(first.clone()))?)),
+    }
+}
+
+fn parse_render(args: &[String], mut render: RenderArgs) -> Result<RenderArgs, String> {
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        let mut value = || options.next().map(String::as_str).ok_or(format!("{option} expects a value"));
+        match option.as_str() {
+            "--resolution" => (render.width, render.height) = parse_resolution(value()?)?,
+            "--spp" => render.options.rays_per_pixel = parse_number(option, value()?)?,
+            "--max-depth" => render.options.max_depth = parse_number(option, value()?)?,
+            "--threads" => render.threads = Some(parse_number(option, value()?)?),
+            "--tile-order" => render.options.tile_order = parse_tile_order(value()?)?,
+            "-o" | "--output" => render.output = PathBuf::from(value()?),
+            "--format" => render.save_options.format = Some(parse_format(value()?)?),
+            "--seed" => render.options.seed = Some(parse_number(option, value()?)?),
+            "--tone-mapping" => render.save_options.tone_mapping = parse_tone_mapping(value()?)?,
+            "--exposure" => render.save_options.exposure = parse_number(option, value()?)?,
+            "--crop" => render.options.crop = Some(parse_crop(value()?)?),
+            "--time-limit" => render.options.time_limit = Some(parse_duration(value()?)?),
+            "--noise-target" => render.options.noise_target = Some(parse_number(option, value()?)?),
+            "--frames" => render.frames = Some(parse_frames(value()?)?),
+            "--fps" => render.fps = parse_number(option, value()?)?,
+            "--workers" => render.workers = value()?.split(',').map(str::to_string).collect(),
+            "--no-mesh-cache" => render.load_options.no_mesh_cache = true,
+            "--dry-run" => render.dry_run = true,
+            _ => return Err(format!("unknown option {option}")),
+        }
+    }
+
+    if render.width == 0 || render.height == 0 {
+        return Err("the resolution must not be empty".to_string());
+    }
+    if render.threads == Some(0) {
+        return Err("--threads must be at least 1".to_string());
+    }
+    if !render.workers.is_empty() && (render.options.time_limit.is_some() || render.options.noise_target.is_some()) {
+        return Err("--time-limit and --noise-target can not be used with --workers".to_string());
+    }
+    if let Some(crop) = &render.options.crop {
+        let inside = |start: u32, size: u32, end: u32| start.checked_add(size).is_some_and(|stop| stop <= end);
+        if !inside(crop.x, crop.width, render.width) || !inside(crop.y, crop.height, render.height) {
+            return Err(format!("the crop region does not fit in a {}x{} image", render.width, render.height));
+        }
+    }
+    // Checked now rather than after a long render.
+    if render.save_options.format.is_none() && rt::ImageFormat::from_path(&render.output).is_err() {
+        return Err(format!("unknown image format for {}, use --format", render.output.display()));
+    }
+    Ok(render)
+}
+
+fn parse_convert(args: &[String]) -> Result<Command, String> {
+    let mut paths = Vec::new();
+    let mut embed_meshes = false;
+    for arg in args {
+        match arg.as_str() {
+            "--embed-meshes" => embed_meshes = true,
+            option if option.starts_with("--") => return Err(format!("unknown option {option}")),
+            path => paths.push(path),
+        }
+    }
+    let [input, output] = paths[..] else {
+        return Err("convert expects an input and an output file".to_string());
+    };
+    Ok(Command::Convert { input: input.to_string(), output: PathBuf::from(output), embed_meshes })
+}
+
+// The address to listen on and the number of threads of the worker and serve commands.
+fn parse_server(args: &[String], default_listen: &str) -> Result<(String, Option<usize>), String> {
+    let mut listen = default_listen.to_string();
+    let mut threads = None;
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        let mut value = || options.next().map(String::as_str).ok_or(format!("{option} expects a value"));
+        match option.as_str() {
+            "--listen" => listen = value()?.to_string(),
+            "--threads" => threads = Some(parse_number(option, value()?)?),
+            _ => return Err(format!("unknown option {option}")),
+        }
+    }
+    if threads == Some(0) {
+        return Err("--threads must be at least 1".to_string());
+    }
+    Ok((listen, threads))
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{option} expects a number, got \"{value}\""))
+}
+
+fn parse_resolution(value: &str) -> Result<(u32, u32), String> {
+    let error = || format!("--resolution expects a size such as 1920x1080, got \"{value}\"");
+    let (width, height) = value.split_once('x').ok_or_else(error)?;
+    Ok((width.parse().map_err(|_| error())?, height.parse().map_err(|_| error())?))
+}
+
+fn parse_crop(value: &str) -> Result<rt::Crop, String> {
+    let error = || format!("--crop expects a region such as 100,100,200,150, got \"{value}\"");
+    let numbers = value.split(',').map(str::parse).collect::<Result<Vec<u32>, _>>().map_err(|_| error())?;
+    let [x, y, width, height] = numbers[..] else { return Err(error()) };
+    Ok(rt::Crop { x, y, width, height })
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, unit) = match value.char_indices().last() {
+        Some((i, 's')) => (&value[..i], 1.0),
+        Some((i, 'm')) => (&value[..i], 60.0),
+        Some((i, 'h')) => (&value[..i], 3600.0),
+        _ => (value, 1.0),
+    };
+    let seconds: f64 = number.parse().map_err(|_| format!("--time-limit expects a duration such as 90, 30s, 5m or 1h, got \"{value}\""))?;
+    Duration::try_from_secs_f64(seconds * unit).map_err(|_| format!("invalid time limit \"{value}\""))
+}
+
+// Ranges are written as in Rust, `0..48` stops before frame 48 and `0..=48` includes it.
+fn parse_frames(value: &str) -> Result<RangeInclusive<u32>, String> {
+    let error = || format!("--frames expects a range such as 0..48 or 0..=47, got \"{value}\"");
+    let (first, end) = value.split_once("..").ok_or_else(error)?;
+    let first: u32 = first.parse().map_err(|_| error())?;
+    let last = match end.strip_prefix('=') {
+        Some(last) => last.parse().map_err(|_| error())?,
+        None => end.parse::<u32>().map_err(|_| error())?.checked_sub(1).ok_or_else(|| format!("--frames {value} has no frame"))?,
+    };
+    if last < first {
+        return Err(format!("--frames {value} has no frame"));
+    }
+    Ok(first..=last)
+}
+
+fn parse_format(value: &str) -> Result<rt::ImageFormat, String> {
+    rt::ImageFormat::from_extension(value).ok_or(format!("unknown image format \"{value}\""))
+}
+
+fn parse_tone_mapping(value: &str) -> Result<rt::ToneMapping, String> {
+    match value {
+        "clamp" => Ok(rt::ToneMapping::Clamp),
+        "reinhard" => Ok(rt::ToneMapping::Reinhard),
+        "aces" => Ok(rt::ToneMapping::Aces),
+        _ => Err(format!("unknown tone mapping \"{value}\", expected clamp, reinhard or aces")),
+    }
+}
+
+pub fn parse_tile_order(value: &str) -> Result<rt::TileOrder, String> {
+    match value {
+        "scanline" => Ok(rt::TileOrder::Scanline),
+        "spiral" => Ok(rt::TileOrder::Spiral),
+        "hilbert" => Ok(rt::TileOrder::Hilbert),
+        _ => Err(format!("unknown tile order \"{value}\", expected scanline, spiral or hilbert")),
+    }
+}
+
+// What a render would do, for --dry-run.
+pub fn print_settings(args: &RenderArgs) {
+    println!("resolution:          {}x{}", args.width, args.height);
+    println!("samples per pixel:   {}", args.options.rays_per_pixel);
+    println!("max depth:           {}", args.options.max_depth);
+    match (&args.workers[..], args.threads) {
+        ([], Some(threads)) => println!("threads:             {threads}"),
+        ([], None) => println!("threads:             {} (one per CPU)", num_threads()),
+        (workers, _) => println!("workers:             {}", workers.join(", ")),
+    }
+    println!("tile order:          {:?}", args.options.tile_order);
+    if let Some(seed) = args.options.seed {
+        println!("seed:                {seed}");
+    }
+    if let Some(crop) = &args.options.crop {
+        println!("crop:                {}x{} at {},{}", crop.width, crop.height, crop.x, crop.y);
+    }
+    if let Some(time_limit) = args.options.time_limit {
+        println!("time limit:          {:.1}s", time_limit.as_secs_f32());
+    }
+    if let Some(noise_target) = args.options.noise_target {
+        println!("noise target:        {:.2}%", noise_target * 100.0);
+    }
+    println!("tone mapping:        {:?}, exposure {:+}", args.save_options.tone_mapping, args.save_options.exposure);
+    match &args.frames {
+        Some(frames) => println!("output:              {} to {} at {} fps", args.frame_output(*frames.start()).display(), args.frame_output(*frames.end()).display(), args.fps),
+        None => println!("output:              {}", args.output.display()),
+    }
+}
+
+fn num_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(args: &[&str]) -> Result<RenderArgs, String> {
+        parse_render(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>(), RenderArgs::new("scene.json".to_string()))
+    }
+
+    #[test]
+    fn test_parse_render() {
+        let args = render(&["--resolution", "320x240", "--spp", "64", "--crop", "10,20,100,50", "--seed", "7", "-o", "out.exr",
+                            "--tile-order", "hilbert", "--time-limit", "5m", "--frames", "0..=3"]).unwrap();
+        assert_eq!((args.width, args.height, args.options.rays_per_pixel, args.options.seed), (320, 240, 64, Some(7)));
+        assert_eq!(args.options.crop, Some(rt::Crop { x: 10, y: 20, width: 100, height: 50 }));
+        assert_eq!(args.options.tile_order, rt::TileOrder::Hilbert);
+        assert_eq!(args.options.time_limit, Some(Duration::from_secs(300)));
+        assert_eq!(args.output, PathBuf::from("out.exr"));
+        assert_eq!(args.frames, Some(0..=3));
+
+        // Defaults of a render from the command line.
+        let args = render(&[]).unwrap();
+        assert_eq!((args.width, args.height, args.options.rays_per_pixel, args.options.max_depth), (700, 700, 3000, 10));
+
+        for invalid in [
+            &["--resolution", "0x10"][..],
+            &["--spp"],
+            &["--spp", "many"],
+            &["--threads", "0"],
+            &["--crop", "600,0,200,10"],
+            &["--crop", "1,0,4294967295,10"],
+            &["--workers", "a:1", "--time-limit", "10"],
+            &["-o", "render.unknown"],
+            &["--unknown"],
+        ] {
+            assert!(render(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_duration("-1").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("").is_err());
+
+        assert_eq!(parse_crop("1,2,3,4"), Ok(rt::Crop { x: 1, y: 2, width: 3, height: 4 }));
+        assert!(parse_crop("1,2,3").is_err());
+        assert!(parse_crop("1,2,3,-4").is_err());
+
+        assert_eq!(parse_frames("0..48"), Ok(0..=47));
+        assert_eq!(parse_frames("0..=48"), Ok(0..=48));
+        assert_eq!(parse_frames("5..6"), Ok(5..=5));
+        assert!(parse_frames("5..5").is_err());
+        assert!(parse_frames("0..0").is_err());
+        assert!(parse_frames("48").is_err());
+        assert!(parse_frames("a..b").is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use ray_tracer as rt;
+
+use std::net::TcpListener;
+use std::path::Path;
+use std::time::Instant;
+use rt::Vec3f;
+
+use crate::{cli, load_scene, render_image};
+
+pub fn info(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let scene = load_scene(path, &rt::LoadOptions::default())?;
+    println!("{}", scene.statistics());
+
+    for (handle, name, mesh) in scene.meshes().iter() {
+        let name = name.map_or_else(|| format!("#{}", rt::Handle::raw(&handle).index), str::to_string);
+        let statistics = mesh.statistics();
+        println!("\nmesh {name}:");
+        for line in statistics.to_string().lines() {
+            println!("    {line}");
+        }
+    }
+    Ok(())
+}
+
+// Prints the problems of every scene, returns whether they can all be rendered.
+pub fn validate(paths: &[String]) -> bool {
+    let mut all_valid = true;
+    for path in paths {
+        // Loading already refuses scenes with errors and lists them.
+        let scene = match load_scene(path, &rt::LoadOptions::default()) {
+            Ok(scene) => scene,
+            Err(error) => {
+                println!("{path}: {error}");
+                all_valid = false;
+                continue;
+            }
+        };
+
+        let issues = scene.validate();
+        for issue in &issues {
+            println!("{path}: {issue}");
+        }
+        if issues.iter().any(|issue| !issue.is_warning()) {
+            all_valid = false;
+        } else {
+            println!("{path}: ok ({} warning(s))", issues.len());
+        }
+    }
+    all_valid
+}
+
+// Returns whether every scene could be upgraded, the others are listed on the standard error.
+pub fn upgrade(paths: &[String]) -> bool {
+    let mut all_upgraded = true;
+    for path in paths {
+        match rt::Scene::upgrade_file(path) {
+            Ok(true) => println!("{path}: upgraded to version {}", rt::Scene::VERSION),
+            Ok(false) => println!("{path}: already up to date"),
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                all_upgraded = false;
+            }
+        }
+    }
+    all_upgraded
+}
+
+pub fn convert(input: &str, output: &Path, embed_meshes: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let scene = load_scene(input, &rt::LoadOptions::default())?;
+    let extension = output.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("json") => scene.save_with_options(output, &rt::SaveOptions { embed_meshes })?,
+        Some("obj") => scene.export_obj(output)?,
+        Some("gltf" | "glb") => scene.export_gltf(output)?,
+        Some("rtmesh") => {
+            let mut meshes = scene.meshes().values();
+            let (Some(mesh), None) = (meshes.next(), meshes.next()) else {
+                return Err(format!("a binary mesh holds a single mesh but the scene has {}", scene.meshes().len()).into());
+            };
+            mesh.save_binary(output)?;
+        }
+        _ => return Err(format!("can not convert to {}, expected a .json, .obj, .gltf, .glb or .rtmesh file", output.display()).into()),
+    }
+    println!("{input} -> {}", output.display());
+    Ok(())
+}
+
+pub fn bench(args: &cli::RenderArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    let scene = load_scene(&args.scene, &args.load_options)?;
+    println!("Scene loaded in {:.2}s", start.elapsed().as_secs_f32());
+
+    let mut target = rt::Image::new(Vec3f::zeros(), args.width, args.height);
+    let renderer = args.threads.map_or_else(rt::RayTracer::new, rt::RayTracer::with_threads);
+    let report = render_image(args, &renderer, &scene, &mut target, &args.options, &())?;
+
+    let (width, height) = match &args.options.crop {
+        Some(crop) => (crop.width, crop.height),
+        None => (args.width, args.height),
+    };
+    let elapsed = report.elapsed.as_secs_f64();
+    let on = match (&args.workers[..], args.threads) {
+        ([], threads) => format!("{} threads", threads.unwrap_or_else(|| rt::RayTracer::new().threads())),
+        (workers, _) => format!("{} workers", workers.len()),
+    };
+    println!("Rendered {width}x{height} at {} spp in {elapsed:.3}s on {on}", report.samples_per_pixel);
+    println!("rays:                {} ({} primary, {} secondary)", report.rays(), report.primary_rays, report.secondary_rays);
+    println!("rays per second:     {:.0}", report.rays_per_second());
+    println!("AABB tests per ray:  {:.1}", report.aabb_tests as f64 / report.rays() as f64);
+    println!("triangle tests/ray:  {:.1}", report.triangle_tests as f64 / report.rays() as f64);
+
+    let paths: u64 = report.path_lengths.iter().sum();
+    println!("path lengths:");
+    for (length, &count) in report.path_lengths.iter().enumerate().filter(|(_, &count)| count > 0) {
+        println!("    {length:>3} rays: {:>5.1}%", count as f64 * 100.0 / paths as f64);
+    }
+
+    if let Some((tile, time)) = report.tile_times.iter().max_by_key(|(_, time)| *time) {
+        let average = report.tile_times.iter().map(|(_, time)| time.as_secs_f64()).sum::<f64>() / report.tile_times.len() as f64;
+        println!("tile times:          {:.1}ms on average, {:.1}ms at most for the tile at {},{}",
+                 average * 1000.0, time.as_secs_f64() * 1000.0, tile.x, tile.y);
+    }
+    Ok(())
+}
+
+// Renders for one coordinator after the other, until the process is stopped.
+pub fn worker(listen: &str, threads: Option<usize>) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(listen)?;
+    let renderer = threads.map_or_else(rt::RayTracer::new, rt::RayTracer::with_threads);
+    println!("Waiting for coordinators on {} with {} threads", listener.local_addr()?, renderer.threads());
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("error: {error}");
+                continue;
+            }
+        };
+        let peer = stream.peer_addr().map_or_else(|_| "a coordinator".to_string(), |address| address.to_string());
+        println!("Rendering for {peer}");
+        match rt::serve_coordinator(stream, &renderer) {
+            Ok(()) => println!("Done with {peer}"),
+            Err(error) => eprintln!("error: {peer}: {error}"),
+        }
+    }
+    Ok(())
+}
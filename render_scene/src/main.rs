@@ -3,28 +3,136 @@ use ray_tracer as rt;
 use std::time::Instant;
 use rt::Vec3f;
 
+mod cli;
+mod commands;
+mod serve;
+
 fn main() {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let command = match cli::parse(&args) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("error: {error}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        cli::Command::Help => println!("{}", cli::USAGE),
+        cli::Command::Upgrade(paths) => {
+            if !commands::upgrade(&paths) {
+                std::process::exit(1);
+            }
+        }
+        cli::Command::Render(args) => exit_on_error(render(&args)),
+        cli::Command::Info(path) => exit_on_error(commands::info(&path)),
+        cli::Command::Validate(paths) => {
+            if !commands::validate(&paths) {
+                std::process::exit(1);
+            }
+        }
+        cli::Command::Convert { input, output, embed_meshes } => exit_on_error(commands::convert(&input, &output, embed_meshes)),
+        cli::Command::Bench(args) => exit_on_error(commands::bench(&args)),
+        cli::Command::Worker { listen, threads } => exit_on_error(commands::worker(&listen, threads)),
+        cli::Command::Serve { listen, threads } => exit_on_error(serve::serve(&listen, threads)),
+    }
+}
+
+fn exit_on_error(result: Result<(), Box<dyn std::error::Error>>) {
+    if let Err(error) = result {
+        eprintln!("error: {error}");
+        std::process::exit(1);
+    }
+}
+
+fn render(args: &cli::RenderArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut scene = load_scene(&args.scene, &args.load_options).map_err(|error| format!("could not load scene: {error}"))?;
+
+    if args.dry_run {
+        println!("{}", scene.statistics());
+        cli::print_settings(args);
+        return Ok(());
+    }
+
+    // The first Ctrl-C stops the render and saves what it has, a second one quits right away.
+    let control = rt::RenderControl::new();
+    let handler_control = control.clone();
+    ctrlc::set_handler(move || {
+        if handler_control.is_cancelled() {
+            std::process::exit(130);
+        }
+        eprintln!("\nStopping, press Ctrl-C again to quit without saving");
+        handler_control.cancel();
+    })?;
+    let options = rt::RenderOptions { control: Some(control), ..args.options.clone() };
+
+    let mut target = rt::Image::new(Vec3f::zeros(), args.width, args.height);
+    let renderer = args.threads.map_or_else(rt::RayTracer::new, rt::RayTracer::with_threads);
+
+    let Some(frames) = args.frames.clone() else {
+        let start = Instant::now();
+        let report = render_image(args, &renderer, &scene, &mut target, &options, &rt::ProgressBar)?;
+        let status = if report.cancelled {"cancelled"} else {"finished"};
+        println!("\nRendering {status} after {:.2}s, {} spp{}, {:.0} rays/s", start.elapsed().as_secs_f32(), report.samples_per_pixel, noise(&report), report.rays_per_second());
+
+        target.save_with_options(&args.output, &args.save_options)?;
+        return Ok(());
+    };
+
+    for frame in frames {
+        scene.set_time(frame as f32 / args.fps)?;
+        target.fill(Vec3f::zeros());
+
+        let start = Instant::now();
+        let report = render_image(args, &renderer, &scene, &mut target, &options, &rt::ProgressBar)?;
+        let status = if report.cancelled {"cancelled"} else {"finished"};
+        println!("\nFrame {frame} {status} after {:.2}s, {} spp{}", start.elapsed().as_secs_f32(), report.samples_per_pixel, noise(&report));
+
+        target.save_with_options(args.frame_output(frame), &args.save_options)?;
+        if report.cancelled {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// The noise is only estimated with --noise-target.
+fn noise(report: &rt::RenderReport) -> String {
+    report.noise.map_or_else(String::new, |noise| format!(", {:.2}% noise", noise * 100.0))
+}
+
+// Renders on this machine with `renderer`, or on the workers given with --workers.
+pub fn render_image(args: &cli::RenderArgs, renderer: &rt::RayTracer, scene: &rt::Scene, target: &mut rt::Image, options: &rt::RenderOptions,
+                    observer: &dyn rt::RenderObserver) -> Result<rt::RenderReport, Box<dyn std::error::Error>> {
+    if args.workers.is_empty() {
+        Ok(renderer.render(scene, target, options, observer)?)
+    } else {
+        Ok(rt::Coordinator::new(args.workers.clone()).render(scene, target, options, observer)?)
+    }
+}
 
-    let args: Vec<_> = std::env::args().collect();
+// Scenes can also be OBJ, glTF, pbrt or Mitsuba files, or single PLY, STL and binary meshes in a light grey,
+// rendered under a white sky unless they bring their own lights.
+pub fn load_scene(path: &str, options: &rt::LoadOptions) -> Result<rt::Scene, Box<dyn std::error::Error>> {
+    let extension = std::path::Path::new(path).extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase);
+    let import = match extension.as_deref() {
+        Some("obj" | "gltf" | "glb" | "pbrt" | "xml") => rt::Import::load(path)?,
+        Some("ply" | "stl" | "rtmesh") => rt::Import {
+            meshes: vec![("mesh".to_string(), rt::Mesh::load(path)?)],
+            materials: vec![("default".to_string(), Box::new(rt::Lambertian { color: Vec3f::repeat(0.8), emission: Vec3f::zeros() }))],
+            objects: vec![rt::ImportedObject { name: "mesh".to_string(), mesh: 0, material: 0, transform: rt::Mat4f::identity() }],
+            camera: None,
+            environment: None,
+            warnings: Vec::new(),
+        },
+        _ => return Ok(rt::Scene::load_with_options(path, options)?),
+    };
 
-    if args.len() < 2 {
-        println!("No scene provided");
-        return;
+    for warning in &import.warnings {
+        eprintln!("warning: {warning}");
     }
-    
-    const WIDTH: u32 = 700;
-    const HEIGHT: u32 = 700;
-    
-    let scene = rt::Scene::load(&args[1]).expect("Counln not load scene");
-    let renderer = rt::RayTracer;
-    
-    let mut target = rt::Image::new(Vec3f::zeros(), WIDTH, HEIGHT);
-
-    let options = rt::RenderOptions { max_depth: 10,  rays_per_pixel: 3000};
-
-    let start = Instant::now();
-    renderer.render_with_print(&scene, &mut target, &options);
-    println!("\nRendering finished after {:.2}s", start.elapsed().as_secs_f32());
-
-    target.save("./lucy.png").expect("Failed to save render result");
+    let camera = rt::Camera::new(Vec3f::new(0.0, 0.0, 5.0), -Vec3f::z(), Vec3f::y(), 1.0);
+    let mut scene = rt::Scene::new(camera, Box::new(rt::ConstantEnvironment { color: Vec3f::repeat(1.0) }));
+    scene.add_import(import, &rt::Mat4f::identity())?;
+    Ok(scene)
 }
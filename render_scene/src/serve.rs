@@ -0,0 +1,689 @@
+use ray_tracer as rt;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use rt::Vec3f;
+use serde::{Deserialize, Serialize};
+
+use crate::cli;
+
+// Scenes with embedded meshes can get large, but not that large.
+const MAX_BODY_SIZE: usize = 1 << 30;
+// The request line and the headers.
+const MAX_HEADER_SIZE: u64 = 64 << 10;
+// Each connection has a thread of its own, more clients are answered with 503 Service Unavailable right away.
+const MAX_CONNECTIONS: usize = 64;
+// Clients that neither send nor read anything for that long are disconnected.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+// Up to 4096x4096, an image of that size takes 200 MB.
+const MAX_PIXELS: u64 = 1 << 24;
+// Jobs posted while that many are waiting are turned away, each of them keeps its scene until it starts.
+const MAX_QUEUED_JOBS: usize = 16;
+// Jobs that are done keep their image, the oldest ones are forgotten once they take more memory than that.
+const MAX_DONE_BYTES: usize = 1 << 30;
+// Paths are traced recursively, deeper ones could overflow the stack of the render threads.
+const MAX_DEPTH: u32 = 64;
+
+// Renders the jobs posted to a small HTTP API one after the other, until the process is stopped:
+//     POST /jobs                  a scene and its options, see `JobRequest`, answers with the status of the new job
+//     GET  /jobs                  the status of every job
+//     GET  /jobs/{id}             the status of a job, with its progress
+//     GET  /jobs/{id}/image.png   the image as rendered so far, or .exr, .hdr, .jpeg...
+//     POST /jobs/{id}/cancel      stops the job, its image keeps what was rendered
+//     DELETE /jobs/{id}           stops the job and forgets it
+// Posting a job while `MAX_QUEUED_JOBS` are waiting answers with 503 Service Unavailable. Finished, cancelled and
+// failed jobs are kept, newest first, as long as their images fit in `MAX_DONE_BYTES`.
+pub fn serve(listen: &str, threads: Option<usize>) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(listen)?;
+    let renderer = threads.map_or_else(rt::RayTracer::new, rt::RayTracer::with_threads);
+    let server = Server::new(".")?;
+    println!("Serving on http://{} with {} threads", listener.local_addr()?, renderer.threads());
+
+    let connections = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        scope.spawn(|| server.run(&renderer));
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS => {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    let response = Response::error(503, format!("the server already has {MAX_CONNECTIONS} connections"));
+                    if let Err(error) = stream.set_write_timeout(Some(CONNECTION_TIMEOUT)).and_then(|_| response.write_to(stream)) {
+                        eprintln!("error: {error}");
+                    }
+                }
+                Ok(stream) => {
+                    let (server, connections) = (&server, &connections);
+                    scope.spawn(move || {
+                        if let Err(error) = server.handle(stream) {
+                            eprintln!("error: {error}");
+                        }
+                        connections.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+                Err(error) => eprintln!("error: {error}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+// Body of POST /jobs, with the same defaults as a render from the command line.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct JobRequest {
+    // A scene as saved by `Scene::save`, meshes and textures it refers to are loaded relative to the directory of the
+    // server and must be inside of it.
+    scene: serde_json::Value,
+    width: u32,
+    height: u32,
+    spp: u32,
+    max_depth: u32,
+    seed: Option<u64>,
+    // scanline, spiral or hilbert.
+    tile_order: Option<String>,
+    // x, y, width and height.
+    crop: Option<[u32; 4]>,
+    // In seconds.
+    time_limit: Option<f64>,
+    noise_target: Option<f32>,
+}
+
+impl Default for JobRequest {
+    fn default() -> Self {
+        JobRequest { scene: serde_json::Value::Null, width: 700, height: 700, spp: 3000, max_depth: 10, seed: None, tile_order: None, crop: None, time_limit: None, noise_target: None }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Queued,
+    Rendering,
+    Finished,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+struct JobStatus {
+    id: u64,
+    status: Status,
+    width: u32,
+    height: u32,
+    // Between 0 and 1.
+    progress: f32,
+    // In seconds, since the job started rendering.
+    elapsed: f64,
+    eta: Option<f64>,
+    // Once the job is done.
+    samples_per_pixel: Option<u32>,
+    noise: Option<f32>,
+    error: Option<String>,
+}
+
+struct Server {
+    // Canonical, scenes can only refer to files inside of it.
+    root: PathBuf,
+    jobs: Mutex<Jobs>,
+    queued: Condvar,
+}
+
+#[derive(Default)]
+struct Jobs {
+    next_id: u64,
+    all: BTreeMap<u64, Arc<Job>>,
+    queue: VecDeque<Arc<Job>>,
+}
+
+struct Job {
+    id: u64,
+    width: u32,
+    height: u32,
+    options: rt::RenderOptions,
+    control: rt::RenderControl,
+    // Parsed when the job starts, scenes can't be sent to another thread.
+    scene: Mutex<Option<serde_json::Value>>,
+    state: Mutex<JobState>,
+}
+
+struct JobState {
+    status: Status,
+    started: Option<Instant>,
+    progress: Option<rt::Progress>,
+    // From when the job starts rendering.
+    image: Option<rt::Image>,
+    // Once the job is done.
+    elapsed: Option<Duration>,
+    samples_per_pixel: Option<u32>,
+    noise: Option<f32>,
+    error: Option<String>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Server {
+    fn new(root: impl AsRef<Path>) -> io::Result<Server> {
+        Ok(Server { root: root.as_ref().canonicalize()?, jobs: Mutex::default(), queued: Condvar::new() })
+    }
+
+    // Renders the queued jobs in order.
+    fn run(&self, renderer: &rt::RayTracer) {
+        loop {
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = loop {
+                match jobs.queue.pop_front() {
+                    Some(job) => break job,
+                    None => jobs = self.queued.wait(jobs).unwrap(),
+                }
+            };
+            drop(jobs);
+            job.render(renderer);
+            self.forget_old_jobs();
+        }
+    }
+
+    fn forget_old_jobs(&self) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let mut kept = 0;
+        let old: Vec<u64> = jobs.all.values().rev().filter(|job| job.is_done()).filter(|job| {
+            kept += job.memory_size();
+            kept > MAX_DONE_BYTES
+        }).map(|job| job.id).collect();
+        for id in &old {
+            jobs.all.remove(id);
+        }
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+        stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let response = match read_request(&mut reader)? {
+            Ok(request) => self.route(&request),
+            Err(message) => Response::error(400, message),
+        };
+        response.write_to(stream)
+    }
+
+    fn route(&self, request: &Request) -> Response {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        let result = match (request.method.as_str(), &segments[..]) {
+            ("POST", ["jobs"]) => self.submit(&request.body),
+            ("GET", ["jobs"]) => {
+                let jobs: Vec<_> = self.jobs.lock().unwrap().all.values().map(|job| job.status()).collect();
+                Ok(Response::json(200, &jobs))
+            }
+            ("GET", ["jobs", id]) => self.job(id).map(|job| Response::json(200, &job.status())),
+            ("GET", ["jobs", id, image]) if image.starts_with("image.") => self.job(id).and_then(|job| job.image(&image["image.".len()..])),
+            ("POST", ["jobs", id, "cancel"]) => self.job(id).map(|job| {
+                job.cancel();
+                Response::json(200, &job.status())
+            }),
+            ("DELETE", ["jobs", id]) => self.job(id).map(|job| {
+                job.cancel();
+                let mut jobs = self.jobs.lock().unwrap();
+                jobs.all.remove(&job.id);
+                jobs.queue.retain(|queued| queued.id != job.id);
+                Response::json(200, &job.status())
+            }),
+            _ => Err(Response::error(404, format!("no such endpoint: {} {}", request.method, request.path))),
+        };
+        result.unwrap_or_else(|error| error)
+    }
+
+    fn job(&self, id: &str) -> Result<Arc<Job>, Response> {
+        let job = id.parse().ok().and_then(|id| self.jobs.lock().unwrap().all.get(&id).cloned());
+        job.ok_or_else(|| Response::error(404, format!("no job {id}")))
+    }
+
+    fn submit(&self, body: &[u8]) -> Result<Response, Response> {
+        let mut request: JobRequest = serde_json::from_slice(body).map_err(|error| Response::error(400, format!("invalid job: {error}")))?;
+        let options = job_options(&request).map_err(|error| Response::error(400, error))?;
+        check_paths(&mut request.scene, &self.root).map_err(|error| Response::error(400, error))?;
+        let control = rt::RenderControl::new();
+
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.queue.len() >= MAX_QUEUED_JOBS {
+            return Err(Response::error(503, format!("{MAX_QUEUED_JOBS} jobs are already waiting, try again later")));
+        }
+        jobs.next_id += 1;
+        let job = Arc::new(Job {
+            id: jobs.next_id,
+            width: request.width,
+            height: request.height,
+            options: rt::RenderOptions { control: Some(control.clone()), ..options },
+            control,
+            scene: Mutex::new(Some(request.scene)),
+            state: Mutex::new(JobState {
+                status: Status::Queued,
+                started: None,
+                progress: None,
+                image: None,
+                elapsed: None,
+                samples_per_pixel: None,
+                noise: None,
+                error: None,
+            }),
+        });
+        jobs.all.insert(job.id, job.clone());
+        jobs.queue.push_back(job.clone());
+        self.queued.notify_one();
+        Ok(Response::json(201, &job.status()))
+    }
+}
+
+// Checked like the options of the command line.
+fn job_options(request: &JobRequest) -> Result<rt::RenderOptions, String> {
+    if request.scene.is_null() {
+        return Err("a job needs a scene".to_string());
+    }
+    if request.width == 0 || request.height == 0 {
+        return Err("the resolution must not be empty".to_string());
+    }
+    if request.width as u64 * request.height as u64 > MAX_PIXELS {
+        return Err(format!("the resolution must not be over {MAX_PIXELS} pixels"));
+    }
+    if request.max_depth > MAX_DEPTH {
+        return Err(format!("the maximum depth must not be over {MAX_DEPTH}"));
+    }
+    let crop = request.crop.map(|[x, y, width, height]| rt::Crop { x, y, width, height });
+    if let Some(crop) = &crop {
+        let inside = |start: u32, size: u32, end: u32| start.checked_add(size).is_some_and(|stop| stop <= end);
+        if !inside(crop.x, crop.width, request.width) || !inside(crop.y, crop.height, request.height) {
+            return Err(format!("the crop region does not fit in a {}x{} image", request.width, request.height));
+        }
+    }
+    let time_limit = request.time_limit.map(|seconds| Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid time limit {seconds}"))).transpose()?;
+
+    Ok(rt::RenderOptions {
+        max_depth: request.max_depth,
+        rays_per_pixel: request.spp,
+        tile_order: request.tile_order.as_deref().map_or(Ok(rt::TileOrder::Scanline), cli::parse_tile_order)?,
+        seed: request.seed,
+        crop,
+        time_limit,
+        noise_target: request.noise_target,
+        control: None,
+    })
+}
+
+// Rejects the meshes and textures outside of the directory of the server, which clients have no business reading,
+// including through symbolic links. Paths are replaced by the files they lead to, so that the scene loads what was checked.
+fn check_paths(value: &mut serde_json::Value, root: &Path) -> Result<(), String> {
+    match value {
+        serde_json::Value::Object(fields) => fields.iter_mut().try_for_each(|(key, value)| match value {
+            serde_json::Value::String(path) if key == "path" => {
+                let resolved = root.join(&*path).canonicalize().map_err(|error| format!("the scene refers to {path}: {error}"))?;
+                if !resolved.starts_with(root) {
+                    return Err(format!("the scene refers to {path}, outside of the directory of the server"));
+                }
+                *path = resolved.into_os_string().into_string().map_err(|_| format!("the path of {path} is not valid text"))?;
+                Ok(())
+            }
+            value => check_paths(value, root),
+        }),
+        serde_json::Value::Array(values) => values.iter_mut().try_for_each(|value| check_paths(value, root)),
+        _ => Ok(()),
+    }
+}
+
+impl Job {
+    fn render(&self, renderer: &rt::RayTracer) {
+        {
+            let mut state = self.state.lock().unwrap();
+            // Cancelled while it was queued.
+            if state.status != Status::Queued {
+                return;
+            }
+            state.status = Status::Rendering;
+            state.started = Some(Instant::now());
+            state.image = Some(rt::Image::new(Vec3f::zeros(), self.width, self.height));
+        }
+
+        let scene = self.scene.lock().unwrap().take().unwrap_or_default();
+        let result = rt::Scene::from_value(scene, ".", &rt::LoadOptions::default()).map_err(|error| format!("could not load scene: {error}")).and_then(|scene| {
+            let mut target = rt::Image::new(Vec3f::zeros(), self.width, self.height);
+            let report = renderer.render(&scene, &mut target, &self.options, self).map_err(|error| error.to_string())?;
+            Ok((target, report))
+        });
+
+        let mut state = self.state.lock().unwrap();
+        state.elapsed = state.started.map(|started| started.elapsed());
+        match result {
+            Ok((target, report)) => {
+                state.image = Some(target);
+                state.status = if report.cancelled { Status::Cancelled } else { Status::Finished };
+                state.samples_per_pixel = Some(report.samples_per_pixel);
+                state.noise = report.noise;
+            }
+            Err(error) => {
+                state.status = Status::Failed;
+                state.error = Some(error);
+            }
+        }
+    }
+
+    fn cancel(&self) {
+        self.control.cancel();
+        let mut state = self.state.lock().unwrap();
+        if state.status == Status::Queued {
+            state.status = Status::Cancelled;
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        matches!(self.state.lock().unwrap().status, Status::Finished | Status::Cancelled | Status::Failed)
+    }
+
+    // Roughly what the job takes in memory once it is done, mostly its image.
+    fn memory_size(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        let image = state.image.as_ref().map_or(0, |image| image.pixels.len() * std::mem::size_of::<Vec3f>());
+        std::mem::size_of::<Job>() + image + state.error.as_ref().map_or(0, String::len)
+    }
+
+    fn status(&self) -> JobStatus {
+        let state = self.state.lock().unwrap();
+        let (progress, eta) = match (state.status, state.progress) {
+            (Status::Finished, _) => (1.0, None),
+            (_, Some(progress)) => (progress.fraction, progress.eta.map(|eta| eta.as_secs_f64())),
+            (_, None) => (0.0, None),
+        };
+        let elapsed = match (state.elapsed, state.started) {
+            (Some(elapsed), _) => elapsed,
+            (None, Some(started)) => started.elapsed(),
+            (None, None) => Duration::ZERO,
+        };
+        JobStatus {
+            id: self.id,
+            status: state.status,
+            width: self.width,
+            height: self.height,
+            progress,
+            elapsed: elapsed.as_secs_f64(),
+            eta,
+            samples_per_pixel: state.samples_per_pixel,
+            noise: state.noise,
+            error: state.error.clone(),
+        }
+    }
+
+    fn image(&self, extension: &str) -> Result<Response, Response> {
+        let format = rt::ImageFormat::from_extension(extension).ok_or_else(|| Response::error(404, format!("unknown image format \"{extension}\"")))?;
+        // Encoded outside of the lock so that the render doesn't wait.
+        let image = self.state.lock().unwrap().image.clone().ok_or_else(|| Response::error(404, format!("job {} has not started rendering", self.id)))?;
+        let bytes = image.encode(format, &rt::ImageSaveOptions::default()).map_err(|error| Response::error(400, error.to_string()))?;
+        let content_type = match format {
+            rt::ImageFormat::Png => "image/png",
+            rt::ImageFormat::Jpeg => "image/jpeg",
+            rt::ImageFormat::OpenExr => "image/x-exr",
+            rt::ImageFormat::Hdr => "image/vnd.radiance",
+            _ => "application/octet-stream",
+        };
+        Ok(Response { status: 200, content_type, body: bytes })
+    }
+}
+
+// Keeps the image of the job up to date as tiles finish, for previews.
+impl rt::RenderObserver for Job {
+    fn tile_finished(&self, tile: &rt::TileInfo, pixels: &[Vec3f]) {
+        let mut state = self.state.lock().unwrap();
+        let Some(image) = &mut state.image else { return };
+        let mut view = image.view(tile.x, tile.y, tile.width, tile.height);
+        for (k, pixel) in pixels.iter().enumerate() {
+            view[[k / tile.width as usize, k % tile.width as usize]] = *pixel;
+        }
+    }
+
+    fn progress(&self, progress: &rt::Progress) {
+        self.state.lock().unwrap().progress = Some(*progress);
+    }
+}
+
+// Reads an HTTP/1.1 request, or says what is wrong with it.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Result<Request, String>> {
+    let mut head = (&mut *reader).take(MAX_HEADER_SIZE);
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(Err(format!("invalid request line \"{}\"", line.trim_end())));
+    };
+    let path = target.split('?').next().unwrap_or_default().to_string();
+    let method = method.to_string();
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let Ok(length) = value.trim().parse() else {
+                    return Ok(Err(format!("invalid content length \"{}\"", value.trim())));
+                };
+                content_length = length;
+            }
+        }
+    }
+    if head.limit() == 0 {
+        return Ok(Err(format!("the headers are larger than {MAX_HEADER_SIZE} bytes")));
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Ok(Err(format!("the body is larger than {MAX_BODY_SIZE} bytes")));
+    }
+
+    // Grows as the bytes arrive rather than trusting the announced length.
+    let mut body = Vec::new();
+    reader.take(content_length as u64).read_to_end(&mut body)?;
+    if body.len() < content_length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Ok(Request { method, path, body }))
+}
+
+impl Response {
+    fn json(status: u16, value: &impl Serialize) -> Response {
+        let body = serde_json::to_vec_pretty(value).expect("responses can always be serialized");
+        Response { status, content_type: "application/json", body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Response {
+        Response::json(status, &serde_json::json!({ "error": message.into() }))
+    }
+
+    fn write_to(&self, mut stream: TcpStream) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            503 => "Service Unavailable",
+            _ => "",
+        };
+        write!(stream, "HTTP/1.1 {} {reason}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+               self.status, self.content_type, self.body.len())?;
+        stream.write_all(&self.body)?;
+        stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn scene() -> serde_json::Value {
+        let scene = rt::Scene::new(rt::Camera::new(Vec3f::zeros(), -Vec3f::z(), Vec3f::y(), 0.5), Box::new(rt::ConstantEnvironment { color: Vec3f::new(0.2, 0.4, 0.8) }));
+        serde_json::to_value(&scene).unwrap()
+    }
+
+    fn request(method: &str, path: &str, body: serde_json::Value) -> Request {
+        Request { method: method.to_string(), path: path.to_string(), body: body.to_string().into_bytes() }
+    }
+
+    fn json(response: &Response) -> serde_json::Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_read_request() {
+        let mut reader = Cursor::new(b"POST /jobs?wait=1 HTTP/1.1\r\nHost: localhost\r\ncontent-length: 4\r\n\r\n{}\r\nextra".to_vec());
+        let request = read_request(&mut reader).unwrap().unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str(), &request.body[..]), ("POST", "/jobs", &b"{}\r\n"[..]));
+
+        let request = read_request(&mut Cursor::new(b"GET /jobs HTTP/1.1\r\n\r\n".to_vec())).unwrap().unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str(), request.body.len()), ("GET", "/jobs", 0));
+
+        assert!(read_request(&mut Cursor::new(b"\r\n".to_vec())).unwrap().is_err());
+        assert!(read_request(&mut Cursor::new(b"POST /jobs HTTP/1.1\r\nContent-Length: lots\r\n\r\n".to_vec())).unwrap().is_err());
+        let too_large = format!("POST /jobs HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_SIZE + 1);
+        assert!(read_request(&mut Cursor::new(too_large.into_bytes())).unwrap().is_err());
+        let long_header = format!("GET /jobs HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(MAX_HEADER_SIZE as usize));
+        assert!(read_request(&mut Cursor::new(long_header.into_bytes())).unwrap().is_err());
+        // The body is shorter than announced.
+        assert!(read_request(&mut Cursor::new(b"POST /jobs HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_route() {
+        // Without a render thread, jobs stay queued.
+        let server = Server::new(".").unwrap();
+        let response = server.route(&request("POST", "/jobs", serde_json::json!({ "scene": scene(), "width": 8, "height": 4 })));
+        assert_eq!(response.status, 201);
+        assert_eq!(json(&response)["status"], "queued");
+        assert_eq!(json(&server.route(&request("GET", "/jobs/1", serde_json::Value::Null)))["width"], 8);
+        assert_eq!(json(&server.route(&request("GET", "/jobs", serde_json::Value::Null))).as_array().unwrap().len(), 1);
+
+        let response = server.route(&request("POST", "/jobs/1/cancel", serde_json::Value::Null));
+        assert_eq!(json(&response)["status"], "cancelled");
+        assert_eq!(server.route(&request("DELETE", "/jobs/1", serde_json::Value::Null)).status, 200);
+        assert_eq!(server.route(&request("GET", "/jobs/1", serde_json::Value::Null)).status, 404);
+        assert!(server.jobs.lock().unwrap().queue.is_empty());
+
+        assert_eq!(server.route(&request("GET", "/jobs/1/image.png", serde_json::Value::Null)).status, 404);
+        assert_eq!(server.route(&request("PUT", "/jobs", serde_json::Value::Null)).status, 404);
+
+        // Queued jobs have no image yet, and only so many of them can wait.
+        let job = serde_json::json!({ "scene": scene(), "width": 8, "height": 4 });
+        for _ in 0..MAX_QUEUED_JOBS {
+            assert_eq!(server.route(&request("POST", "/jobs", job.clone())).status, 201);
+        }
+        assert_eq!(server.route(&request("GET", "/jobs/2/image.png", serde_json::Value::Null)).status, 404);
+        assert_eq!(server.route(&request("POST", "/jobs", job.clone())).status, 503);
+        server.route(&request("DELETE", "/jobs/2", serde_json::Value::Null));
+        assert_eq!(server.route(&request("POST", "/jobs", job)).status, 201);
+        let invalid = [
+            serde_json::json!({ "width": 8 }),
+            serde_json::json!({ "scene": scene(), "colors": 3 }),
+            serde_json::json!({ "scene": scene(), "width": 100_000, "height": 100_000 }),
+            serde_json::json!({ "scene": scene(), "max_depth": 100_000 }),
+            serde_json::json!({ "scene": scene(), "crop": [1, 0, u32::MAX, 10] }),
+            serde_json::json!({ "scene": scene(), "tile_order": "random" }),
+            serde_json::json!({ "scene": { "meshes": [{ "value": { "source": { "path": "/etc/passwd" } } }] } }),
+            serde_json::json!({ "scene": { "materials": [{ "texture": { "path": "../secret.png" } }] } }),
+        ];
+        for body in invalid {
+            assert_eq!(server.route(&request("POST", "/jobs", body.clone())).status, 400, "{body}");
+        }
+    }
+
+    #[test]
+    fn test_check_paths() {
+        let directory = std::env::temp_dir().join(format!("render_scene_test_check_paths_{}", std::process::id()));
+        let root = directory.join("root");
+        std::fs::create_dir_all(root.join("meshes")).unwrap();
+        std::fs::write(root.join("meshes/cube.obj"), "").unwrap();
+        std::fs::write(directory.join("secret.obj"), "").unwrap();
+        std::os::unix::fs::symlink(directory.join("secret.obj"), root.join("link.obj")).unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let scene = |path: &str| serde_json::json!({ "meshes": [{ "value": { "source": { "path": path } } }] });
+        let mut inside = scene("./meshes/../meshes/cube.obj");
+        assert_eq!(check_paths(&mut inside, &root), Ok(()));
+        assert_eq!(inside, scene(root.join("meshes/cube.obj").to_str().unwrap()));
+        // Outside, through a link or not, and missing.
+        for path in ["../secret.obj", "link.obj", "/etc/passwd", "meshes/sphere.obj"] {
+            assert!(check_paths(&mut scene(path), &root).is_err(), "{path}");
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    // Sends a request to the server at `address` and returns the status and body of its response.
+    fn http(address: &str, method: &str, path: &str, body: &str) -> (u16, Vec<u8>) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "{method} {path} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let end = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap();
+        let status = String::from_utf8_lossy(&response[..end]).split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, response[end + 4..].to_vec())
+    }
+
+    #[test]
+    fn test_serve_jobs() {
+        // Left running until the end of the tests.
+        let server: &'static Server = Box::leak(Box::new(Server::new(".").unwrap()));
+        let renderer: &'static rt::RayTracer = Box::leak(Box::new(rt::RayTracer::with_threads(2)));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(|| server.run(renderer));
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                server.handle(stream.unwrap()).unwrap();
+            }
+        });
+
+        let wait_for = |id: u64, status: &str| {
+            let start = Instant::now();
+            loop {
+                let (_, body) = http(&address, "GET", &format!("/jobs/{id}"), "");
+                let job: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                if job["status"] == status {
+                    return job;
+                }
+                assert!(start.elapsed() < Duration::from_secs(60), "{job}");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+
+        let job = serde_json::json!({ "scene": scene(), "width": 16, "height": 8, "spp": 4 }).to_string();
+        let (status, body) = http(&address, "POST", "/jobs", &job);
+        assert_eq!(status, 201, "{}", String::from_utf8_lossy(&body));
+        let finished = wait_for(1, "finished");
+        assert_eq!((finished["progress"].as_f64(), finished["samples_per_pixel"].as_u64()), (Some(1.0), Some(4)));
+        let (status, png) = http(&address, "GET", "/jobs/1/image.png", "");
+        assert_eq!(status, 200);
+        assert!(png.starts_with(b"\x89PNG"));
+        assert_eq!(http(&address, "GET", "/jobs/1/image.bmp2", "").0, 404);
+
+        // Long enough to still be rendering when it is cancelled, in passes that check for it.
+        let job = serde_json::json!({ "scene": scene(), "width": 16, "height": 8, "spp": u32::MAX, "time_limit": 3600 }).to_string();
+        assert_eq!(http(&address, "POST", "/jobs", &job).0, 201);
+        wait_for(2, "rendering");
+        assert_eq!(http(&address, "POST", "/jobs/2/cancel", "").0, 200);
+        wait_for(2, "cancelled");
+
+        assert_eq!(http(&address, "DELETE", "/jobs/1", "").0, 200);
+        let (_, body) = http(&address, "GET", "/jobs", "");
+        let jobs: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(jobs.as_array().unwrap().iter().map(|job| job["id"].as_u64().unwrap()).collect::<Vec<_>>(), [2]);
+        assert_eq!(http(&address, "GET", "/nowhere", "").0, 404);
+    }
+}
@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::math::*;
+use crate::{Camera, MatearialHandle, Material, ObjectHandle};
+
+// Only called between two different keyframes, values before, after or on a keyframe are cloned.
+pub trait Interpolate: Sized + Clone {
+    fn interpolate(from: &Self, to: &Self, t: f32) -> Result<Self, InterpolationError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterpolationError {
+    pub message: String,
+}
+
+impl std::fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "could not interpolate between keyframes: {}", self.message)
+    }
+}
+
+impl std::error::Error for InterpolationError {}
+
+impl Interpolate for f32 {
+    fn interpolate(from: &Self, to: &Self, t: f32) -> Result<Self, InterpolationError> {
+        Ok(from + (to - from) * t)
+    }
+}
+
+impl Interpolate for Vec3f {
+    fn interpolate(from: &Self, to: &Self, t: f32) -> Result<Self, InterpolationError> {
+        Ok(from.lerp(to, t))
+    }
+}
+
+impl Interpolate for Transform {
+    fn interpolate(from: &Self, to: &Self, t: f32) -> Result<Self, InterpolationError> {
+        Ok(Transform {
+            translation: from.translation.lerp(&to.translation, t),
+            rotation: from.rotation.slerp(&to.rotation, t),
+            scale: from.scale.lerp(&to.scale, t),
+        })
+    }
+}
+
+impl Interpolate for Camera {
+    fn interpolate(from: &Self, to: &Self, t: f32) -> Result<Self, InterpolationError> {
+        Ok(Camera::new(
+            from.origin.lerp(&to.origin, t),
+            from.direction.lerp(&to.direction, t),
+            from.up.lerp(&to.up, t),
+            from.fov + (to.fov - from.fov) * t,
+        ))
+    }
+}
+
+// Materials are only known through their serialized form, so every float
+// field that both keyframes share is interpolated and the rest, integers included, is taken from `from`.
+// Textures are decoded again for every interpolated value.
+impl Interpolate for Box<dyn Material> {
+    fn interpolate(from: &Self, to: &Self, t: f32) -> Result<Self, InterpolationError> {
+        fn interpolate_value(from: &Value, to: &Value, t: f32) -> Value {
+            match (from, to) {
+                (Value::Number(a), Value::Number(b)) if a.is_f64() && b.is_f64() => {
+                    let (a, b) = (a.as_f64().unwrap(), b.as_f64().unwrap());
+                    serde_json::Number::from_f64(a + (b - a) * t as f64).map_or_else(|| from.clone(), Value::Number)
+                }
+                (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+                    Value::Array(a.iter().zip(b).map(|(a, b)| interpolate_value(a, b, t)).collect())
+                }
+                (Value::Object(a), Value::Object(b)) => {
+                    Value::Object(a.iter().map(|(key, a)| {
+                        let value = b.get(key).map(|b| interpolate_value(a, b, t)).unwrap_or_else(|| a.clone());
+                        (key.clone(), value)
+                    }).collect())
+                }
+                _ => from.clone(),
+            }
+        }
+
+        let error = |error: serde_json::Error| InterpolationError { message: error.to_string() };
+        let from = serde_json::to_value(from).map_err(error)?;
+        let to = serde_json::to_value(to).map_err(error)?;
+        serde_json::from_value(interpolate_value(&from, &to, t)).map_err(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Interpolation {
+    Step,
+    #[default]
+    Linear,
+    // Control points of a cubic bezier easing curve going from (0,0) to (1,1), as in CSS.
+    Bezier([f32; 4]),
+}
+
+impl Interpolation {
+    pub const EASE_IN_OUT: Interpolation = Interpolation::Bezier([0.42, 0.0, 0.58, 1.0]);
+
+    pub fn ease(&self, t: f32) -> f32 {
+        match *self {
+            Interpolation::Step => 0.0,
+            Interpolation::Linear => t,
+            Interpolation::Bezier([x1, y1, x2, y2]) => {
+                fn bezier(p1: f32, p2: f32, s: f32) -> f32 {
+                    let r = 1.0 - s;
+                    3.0 * r * r * s * p1 + 3.0 * r * s * s * p2 + s * s * s
+                }
+
+                // x(s) is monotonic for control points in [0,1], so bisection always converges.
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..32 {
+                    let s = (low + high) / 2.0;
+                    if bezier(x1, x2, s) < t { low = s; } else { high = s; }
+                }
+                bezier(y1, y2, (low + high) / 2.0)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Track { keyframes: Vec::new() }
+    }
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new() -> Track<T> {
+        Track::default()
+    }
+
+    pub fn add_keyframe(&mut self, time: f32, value: T, interpolation: Interpolation) {
+        let index = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        self.keyframes.insert(index, Keyframe { time, value, interpolation });
+    }
+
+    pub fn with_keyframe(mut self, time: f32, value: T, interpolation: Interpolation) -> Track<T> {
+        self.add_keyframe(time, value, interpolation);
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map(|keyframe| keyframe.time).unwrap_or(0.0)
+    }
+
+    // `None` for a track without keyframes.
+    pub fn sample(&self, time: f32) -> Result<Option<T>, InterpolationError> {
+        let (Some(first), Some(last)) = (self.keyframes.first(), self.keyframes.last()) else {
+            return Ok(None);
+        };
+
+        if time <= first.time {
+            return Ok(Some(first.value.clone()));
+        }
+        if time >= last.time {
+            return Ok(Some(last.value.clone()));
+        }
+
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        let (from, to) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let t = from.interpolation.ease((time - from.time) / (to.time - from.time));
+        if t == 0.0 {
+            Ok(Some(from.value.clone()))
+        } else if t == 1.0 {
+            Ok(Some(to.value.clone()))
+        } else {
+            T::interpolate(&from.value, &to.value, t).map(Some)
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Animation {
+    #[serde(default)]
+    pub objects: Vec<(ObjectHandle, Track<Transform>)>,
+    #[serde(default)]
+    pub materials: Vec<(MatearialHandle, Track<Box<dyn Material>>)>,
+    #[serde(default)]
+    pub camera: Track<Camera>,
+    // Rotation of the environment around the y axis, in radians.
+    #[serde(default)]
+    pub environment_rotation: Track<f32>,
+}
+
+impl Animation {
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty() && self.materials.is_empty() && self.camera.is_empty() && self.environment_rotation.is_empty()
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.objects.iter().map(|(_, track)| track.duration())
+            .chain(self.materials.iter().map(|(_, track)| track.duration()))
+            .chain([self.camera.duration(), self.environment_rotation.duration()])
+            .fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_linear_track() {
+        let track = Track::new()
+            .with_keyframe(1.0, 10.0, Interpolation::Linear)
+            .with_keyframe(0.0, 0.0, Interpolation::Linear)
+            .with_keyframe(2.0, 0.0, Interpolation::Step);
+
+        assert_eq!(track.sample(-1.0), Ok(Some(0.0)));
+        assert_eq!(track.sample(0.25), Ok(Some(2.5)));
+        assert_eq!(track.sample(1.5), Ok(Some(5.0)));
+        assert_eq!(track.sample(3.0), Ok(Some(0.0)));
+        assert_eq!(Track::<f32>::new().sample(1.0), Ok(None));
+    }
+
+    #[test]
+    fn test_bezier_easing() {
+        let ease = Interpolation::EASE_IN_OUT;
+        assert!(ease.ease(0.0).abs() < 1e-4);
+        assert!((ease.ease(0.5) - 0.5).abs() < 1e-4);
+        assert!((ease.ease(1.0) - 1.0).abs() < 1e-4);
+        assert!(ease.ease(0.1) < 0.1);
+    }
+
+    #[test]
+    fn test_animated_textured_material() {
+        use crate::{ConstantEnvironment, Scene, Standard, Texture};
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::from_pixel(2, 2, image::Rgb([0, 255, 0])).write_to(&mut png, image::ImageFormat::Png).unwrap();
+        // As glTF imports roughness, read from the green channel.
+        let material = |roughness: f32| Box::new(Standard {
+            roughness,
+            roughness_texture: Some(Texture::decode(png.get_ref().clone(), false).unwrap().with_channel(1)),
+            ..Standard::default()
+        }) as Box<dyn Material>;
+
+        let mut scene = Scene::new(Camera::new(Vec3f::zeros(), -Vec3f::z(), Vec3f::y(), 0.5), Box::new(ConstantEnvironment { color: Vec3f::zeros() }));
+        let handle = scene.add_material(material(0.0));
+        let track = Track::new()
+            .with_keyframe(0.0, material(0.2), Interpolation::Linear)
+            .with_keyframe(1.0, material(0.6), Interpolation::Linear);
+        scene.animation.materials.push((handle, track));
+
+        let roughness = |scene: &Scene| serde_json::to_value(&scene.materials()[handle]).unwrap();
+        for (time, expected) in [(-1.0, 0.2), (0.5, 0.4), (2.0, 0.6)] {
+            scene.set_time(time).unwrap();
+            let material = roughness(&scene);
+            assert!((material["roughness"].as_f64().unwrap() - expected).abs() < 1e-6, "{material}");
+            assert_eq!(material["roughness_texture"]["channel"], 1);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RawHandle {
+    pub index: u32,
+    pub generation: u32,
+}
+
+pub trait Handle: Copy {
+    fn from_raw(raw: RawHandle) -> Self;
+    fn raw(&self) -> RawHandle;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Slot<T> {
+    generation: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    value: Option<T>,
+}
+
+// A vector of optionally named values whose handles stay valid when other values are removed.
+// Removing a value bumps the generation of its slot, so stale handles never alias a newer value.
+#[derive(Debug, Deserialize)]
+#[serde(from = "Vec<Slot<T>>", bound(deserialize = "T: Deserialize<'de>"))]
+pub struct Arena<H, T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<usize>,
+    handle: PhantomData<H>,
+}
+
+impl<H, T: Serialize> Serialize for Arena<H, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.slots.serialize(serializer)
+    }
+}
+
+impl<H, T> Arena<H, T> {
+    // Serializes the arena like `Serialize` does, but with every value replaced by `f(value)`.
+    pub fn serialize_map<'a, S: serde::Serializer, V: Serialize>(&'a self, serializer: S, f: impl Fn(&'a T) -> V) -> Result<S::Ok, S::Error> {
+        let slots: Vec<_> = self.slots.iter().map(|slot| Slot {
+            generation: slot.generation,
+            name: slot.name.clone(),
+            value: slot.value.as_ref().map(&f),
+        }).collect();
+        slots.serialize(serializer)
+    }
+}
+
+impl<H, T> From<Vec<Slot<T>>> for Arena<H, T> {
+    fn from(slots: Vec<Slot<T>>) -> Self {
+        let free = slots.iter().enumerate()
+                        .filter(|(_, slot)| slot.value.is_none())
+                        .map(|(i, _)| i)
+                        .collect();
+        Arena { slots, free, handle: PhantomData }
+    }
+}
+
+impl<H, T> Default for Arena<H, T> {
+    fn default() -> Self {
+        Arena { slots: Vec::new(), free: Vec::new(), handle: PhantomData }
+    }
+}
+
+impl<H: Handle, T> Arena<H, T> {
+    pub fn new() -> Arena<H, T> {
+        Arena::default()
+    }
+
+    pub fn insert(&mut self, value: T, name: Option<String>) -> H {
+        let index = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.value = Some(value);
+                slot.name = name;
+                index
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, name, value: Some(value) });
+                self.slots.len() - 1
+            }
+        };
+
+        H::from_raw(RawHandle { index: index as u32, generation: self.slots[index].generation })
+    }
+
+    fn slot(&self, handle: H) -> Option<&Slot<T>> {
+        let RawHandle { index, generation } = handle.raw();
+        self.slots.get(index as usize).filter(|slot| slot.generation == generation && slot.value.is_some())
+    }
+
+    fn slot_mut(&mut self, handle: H) -> Option<&mut Slot<T>> {
+        let RawHandle { index, generation } = handle.raw();
+        self.slots.get_mut(index as usize).filter(|slot| slot.generation == generation && slot.value.is_some())
+    }
+
+    pub fn contains(&self, handle: H) -> bool {
+        self.slot(handle).is_some()
+    }
+
+    pub fn get(&self, handle: H) -> Option<&T> {
+        self.slot(handle).and_then(|slot| slot.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: H) -> Option<&mut T> {
+        self.slot_mut(handle).and_then(|slot| slot.value.as_mut())
+    }
+
+    pub fn name(&self, handle: H) -> Option<&str> {
+        self.slot(handle).and_then(|slot| slot.name.as_deref())
+    }
+
+    pub fn set_name(&mut self, handle: H, name: Option<String>) -> bool {
+        match self.slot_mut(handle) {
+            Some(slot) => { slot.name = name; true }
+            None => false,
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<H> {
+        self.iter().find(|(_, slot_name, _)| *slot_name == Some(name)).map(|(handle, _, _)| handle)
+    }
+
+    // Returns the previous value, or gives `value` back if the handle is stale.
+    pub fn replace(&mut self, handle: H, value: T) -> Result<T, T> {
+        match self.slot_mut(handle) {
+            Some(slot) => Ok(slot.value.replace(value).unwrap()),
+            None => Err(value),
+        }
+    }
+
+    pub fn remove(&mut self, handle: H) -> Option<T> {
+        let slot = self.slot_mut(handle)?;
+        let value = slot.value.take();
+        slot.name = None;
+        slot.generation += 1;
+        self.free.push(handle.raw().index as usize);
+        value
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (H, Option<&str>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let handle = H::from_raw(RawHandle { index: index as u32, generation: slot.generation });
+            slot.value.as_ref().map(|value| (handle, slot.name.as_deref(), value))
+        })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+}
+
+impl<H: Handle + Debug, T> Index<H> for Arena<H, T> {
+    type Output = T;
+    fn index(&self, handle: H) -> &Self::Output {
+        self.get(handle).unwrap_or_else(|| panic!("Invalid handle: {handle:?}"))
+    }
+}
+
+impl<H: Handle + Debug, T> IndexMut<H> for Arena<H, T> {
+    fn index_mut(&mut self, handle: H) -> &mut Self::Output {
+        self.get_mut(handle).unwrap_or_else(|| panic!("Invalid handle: {handle:?}"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct TestHandle(RawHandle);
+
+    impl Handle for TestHandle {
+        fn from_raw(raw: RawHandle) -> Self { TestHandle(raw) }
+        fn raw(&self) -> RawHandle { self.0 }
+    }
+
+    #[test]
+    fn test_stale_handles() {
+        let mut arena: Arena<TestHandle, &str> = Arena::new();
+        let a = arena.insert("a", Some("first".into()));
+        let b = arena.insert("b", None);
+
+        assert_eq!(arena.find("first"), Some(a));
+        assert_eq!(arena.remove(a), Some("a"));
+        assert_eq!(arena.get(a), None);
+
+        let c = arena.insert("c", None);
+        assert_eq!(c.0.index, a.0.index);
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena.get(c), Some(&"c"));
+        assert_eq!(arena.replace(b, "d"), Ok("b"));
+        assert_eq!(arena.len(), 2);
+    }
+}
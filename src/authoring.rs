@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::{Camera, Environment, LoadOptions, Mat4f, Material, Object, Quatf, Scene, SceneError, SceneIssue, SceneIssueKind, Transform, Vec3f};
+
+// A scene written by hand, in TOML:
+//
+//     environment_rotation = 30.0
+//
+//     [camera]
+//     position = [0, 1, 5]
+//     look_at = [0, 0, 0]
+//     fov = 40.0
+//
+//     [environment]
+//     type = "ConstantEnvironment"
+//     color = [1, 1, 1]
+//
+//     [meshes]
+//     bunny = "models/bunny.obj"
+//
+//     [materials.red]
+//     type = "Lambertian"
+//     color = [0.8, 0.1, 0.1]
+//     emission = [0, 0, 0]
+//
+//     [[objects]]
+//     name = "bunny"
+//     mesh = "bunny"
+//     material = "red"
+//     translate = [0, -1, 0]
+//     rotate = [0, 90, 0]
+//     scale = 2.0
+//
+// Angles are in degrees, meshes and textures are relative to the file, and meshes and materials are referred to by name.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    camera: CameraFile,
+    #[serde(default)]
+    cameras: BTreeMap<String, CameraFile>,
+    environment: Box<dyn Environment>,
+    #[serde(default)]
+    environment_rotation: f32,
+    #[serde(default)]
+    meshes: BTreeMap<String, PathBuf>,
+    #[serde(default)]
+    materials: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    objects: Vec<ObjectFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraFile {
+    position: Vec3f,
+    look_at: Vec3f,
+    #[serde(default = "Vec3f::y")]
+    up: Vec3f,
+    // Vertical angle of view for an image `aspect_ratio` (width / height) times wider than high.
+    #[serde(default = "default_fov")]
+    fov: f32,
+    #[serde(default = "default_aspect_ratio")]
+    aspect_ratio: f32,
+}
+
+fn default_fov() -> f32 {
+    40.0
+}
+
+fn default_aspect_ratio() -> f32 {
+    1.0
+}
+
+impl From<CameraFile> for Camera {
+    fn from(file: CameraFile) -> Self {
+        let mut camera = Camera::new(file.position, file.look_at - file.position, file.up, 1.0);
+        camera.set_vertical_fov(file.fov.to_radians(), file.aspect_ratio);
+        camera
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectFile {
+    name: Option<String>,
+    mesh: String,
+    material: String,
+    #[serde(default)]
+    translate: Vec3f,
+    // Rotations around x, then y, then z.
+    #[serde(default)]
+    rotate: Vec3f,
+    #[serde(default)]
+    scale: Scale,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scale {
+    Uniform(f32),
+    PerAxis(Vec3f),
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Scale::Uniform(1.0)
+    }
+}
+
+impl ObjectFile {
+    fn transform(&self) -> Mat4f {
+        let rotate = self.rotate.map(f32::to_radians);
+        let scale = match self.scale {
+            Scale::Uniform(scale) => Vec3f::repeat(scale),
+            Scale::PerAxis(scale) => scale,
+        };
+        Transform::new(self.translate, Quatf::from_euler_angles(rotate.x, rotate.y, rotate.z), scale).to_matrix()
+    }
+}
+
+impl Scene {
+    pub fn load_toml(path: impl AsRef<Path>, options: &LoadOptions) -> Result<Scene, SceneError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        Scene::from_toml(&text, path.parent().unwrap_or(Path::new("")), options)
+    }
+
+    // Builds a scene from the authoring format, with mesh and texture paths relative to `base`.
+    pub fn from_toml(text: &str, base: &Path, options: &LoadOptions) -> Result<Scene, SceneError> {
+        let file: SceneFile = toml::from_str(text)?;
+        let mut scene = Scene::new(file.camera.into(), file.environment);
+        scene.environment_rotation = file.environment_rotation.to_radians();
+        for (name, camera) in file.cameras {
+            scene.add_named_camera(name, camera.into());
+        }
+
+        let mut meshes = BTreeMap::new();
+        for (name, path) in file.meshes {
+            let path = base.join(path);
+            let mesh = options.load_mesh(&path).map_err(|error| SceneError::MeshLoad { path, error })?;
+            meshes.insert(name.clone(), scene.add_named_mesh(name, mesh));
+        }
+
+        let mut materials = BTreeMap::new();
+        for (name, mut material) in file.materials {
+            resolve_texture_paths(&mut material, base);
+            let material: Box<dyn Material> = material.try_into()
+                .map_err(|error: toml::de::Error| SceneError::Authoring(format!("material \"{name}\": {}", error.message())))?;
+            materials.insert(name.clone(), scene.add_named_material(name, material));
+        }
+
+        // Every wrong reference is reported at once, like `Scene::validate` does.
+        let mut issues = Vec::new();
+        for (i, object) in file.objects.iter().enumerate() {
+            let path = format!("objects[{i}]");
+            let mesh = meshes.get(&object.mesh).copied();
+            let material = materials.get(&object.material).copied();
+            if mesh.is_none() {
+                issues.push(SceneIssue { path: format!("{path}.mesh"), kind: SceneIssueKind::UnknownMesh(object.mesh.clone()) });
+            }
+            if material.is_none() {
+                issues.push(SceneIssue { path: format!("{path}.material"), kind: SceneIssueKind::UnknownMaterial(object.material.clone()) });
+            }
+            let (Some(mesh), Some(material)) = (mesh, material) else { continue };
+
+            let Ok(new_object) = Object::new(mesh, object.transform(), material) else {
+                issues.push(SceneIssue { path, kind: SceneIssueKind::NonInvertibleTransform });
+                continue;
+            };
+            match &object.name {
+                Some(name) => scene.add_named_object(name.clone(), new_object),
+                None => scene.add_object(new_object),
+            };
+        }
+
+        issues.extend(scene.validate());
+        if issues.iter().any(|issue| !issue.is_warning()) {
+            return Err(SceneError::Invalid(issues));
+        }
+        Ok(scene)
+    }
+}
+
+// Textures are tables with a `path`, which is made relative to the scene file rather than to the working directory.
+fn resolve_texture_paths(value: &mut toml::Value, base: &Path) {
+    let toml::Value::Table(table) = value else { return };
+    if let Some(toml::Value::String(path)) = table.get_mut("path") {
+        *path = base.join(&*path).to_string_lossy().into_owned();
+    }
+    for (_, value) in table.iter_mut() {
+        resolve_texture_paths(value, base);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_authoring_format() {
+        let directory = TempDir::new("authoring_format");
+        std::fs::write(directory.join("triangle.obj"), "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+        let text = r#"
+            [camera]
+            position = [0, 0, 5]
+            look_at = [0, 0, 0]
+            fov = 30
+
+            [environment]
+            type = "ConstantEnvironment"
+            color = [1, 1, 1]
+
+            [meshes]
+            triangle = "triangle.obj"
+
+            [materials.red]
+            type = "Lambertian"
+            color = [0.8, 0.1, 0.1]
+            emission = [0, 0, 0]
+
+            [[objects]]
+            name = "moved"
+            mesh = "triangle"
+            material = "red"
+            translate = [1, 2, 3]
+            rotate = [0, 0, 90]
+            scale = 2
+
+            [[objects]]
+            mesh = "triangle"
+            material = "blue"
+        "#;
+        let Err(SceneError::Invalid(issues)) = Scene::from_toml(text, &directory, &LoadOptions::default()) else { panic!("the missing material should be reported") };
+        assert_eq!(issues, vec![SceneIssue { path: "objects[1].material".to_string(), kind: SceneIssueKind::UnknownMaterial("blue".to_string()) }]);
+
+        let scene = Scene::from_toml(&text.replace("blue", "red"), &directory, &LoadOptions::default()).unwrap();
+        assert_eq!(scene.object_count(), 2);
+        assert!((scene.camera.direction + Vec3f::z()).norm() < 1e-6);
+        assert!((scene.camera.vertical_fov(1.0) - 30f32.to_radians()).abs() < 1e-5);
+
+        let handle = scene.objects().find("moved").unwrap();
+        let transform = scene.objects().get(handle).unwrap().get_transform();
+        let corner = transform * crate::Vec4f::new(1.0, 0.0, 0.0, 1.0);
+        assert!((corner - crate::Vec4f::new(1.0, 4.0, 3.0, 1.0)).norm() < 1e-5);
+    }
+}
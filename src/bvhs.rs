@@ -101,13 +101,13 @@ pub struct BVHNode {
     pub content: NodeContent,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BVH {
     nodes: Vec<BVHNode>,
 }
 
 impl BVH {
-    const MAX_DEPTH: usize = 32;
+    pub(crate) const MAX_DEPTH: usize = 32;
 
     fn bin_centroids<const BIN_COUNT: usize>(vertices: &[Vertex], centroids: &[Vec3f], triangles: &[[usize;3]], dimension: usize) 
                             -> ([AABB; BIN_COUNT], [usize; BIN_COUNT], f32, f32) {
@@ -259,14 +259,17 @@ impl BVH {
         bvh
     }
 
-    pub fn intersects(&self, ray: &Ray, min_t: f32, max_t: f32) -> BVHIterator {
+    // The nodes left to visit are kept in `stack`, which callers reuse from one ray to the next.
+    pub fn intersects<'a>(&'a self, ray: &Ray, min_t: f32, max_t: f32, stack: &'a mut TraversalStack) -> BVHIterator<'a> {
 
         let root = self.get_root();
         let head = (root.aabb.intersects(ray, min_t, max_t).is_some() as isize) - 1;
+        // The stack still holds the nodes of the last traversal.
+        stack.0[0] = 0;
         
         BVHIterator {
             bvh: &self,
-            stack: [0;BVH::MAX_DEPTH],
+            stack: &mut stack.0,
             ray: *ray,
             head
         }
@@ -313,6 +316,38 @@ impl BVH {
         triangle_count / leaf_count
     }
 
+    // Wraps nodes built beforehand, e.g. read back from a binary mesh, after checking that they refer to existing
+    // nodes and to `triangle_count` triangles, and that the traversal stack can hold them.
+    pub fn from_nodes(nodes: Vec<BVHNode>, triangle_count: usize) -> Result<BVH, String> {
+        if nodes.is_empty() {
+            return Err("the BVH has no root".to_string());
+        }
+        // Children come after their parent, as in `BVH::build`, which rules out cycles and gives the depth of every
+        // node in a single pass.
+        let mut depths = vec![1u8; nodes.len()];
+        for (index, node) in nodes.iter().enumerate() {
+            match node.content {
+                NodeContent::Children((left, right)) => {
+                    if depths[index] as usize >= BVH::MAX_DEPTH {
+                        return Err(format!("the BVH is deeper than {}", BVH::MAX_DEPTH));
+                    }
+                    for child in [left, right] {
+                        if child <= index || child >= nodes.len() {
+                            return Err(format!("the children of the BVH node {index} are out of range"));
+                        }
+                        depths[child] = depths[child].max(depths[index] + 1);
+                    }
+                }
+                NodeContent::Triangles((start, end)) => {
+                    if start > end || end > triangle_count {
+                        return Err(format!("the triangles of the BVH node {index} are out of range"));
+                    }
+                }
+            }
+        }
+        Ok(BVH { nodes })
+    }
+
     pub fn get_nodes(&self) -> &Vec<BVHNode> {
         &self.nodes
     }
@@ -322,8 +357,17 @@ impl BVH {
     }
 }
 
+// Room for the nodes a traversal of any BVH has left to visit, which `BVH::from_nodes` makes sure of.
+pub struct TraversalStack([usize; BVH::MAX_DEPTH]);
+
+impl Default for TraversalStack {
+    fn default() -> Self {
+        TraversalStack([0; BVH::MAX_DEPTH])
+    }
+}
+
 pub struct BVHIterator<'a> {
-    stack: [usize; BVH::MAX_DEPTH],
+    stack: &'a mut [usize; BVH::MAX_DEPTH],
     ray: Ray,
     head: isize,
     bvh: &'a BVH,
@@ -19,4 +19,14 @@ impl Camera {
             fov,
         }
     }
+
+    // `fov` is chosen so that the image plane at distance 1 is `tan(fov)` wide, these convert it from and to
+    // the vertical angle of view other tools use, for an image `aspect_ratio` (width / height) times wider than high.
+    pub fn vertical_fov(&self, aspect_ratio: f32) -> f32 {
+        2.0 * (self.fov.tan() / (2.0 * aspect_ratio)).atan()
+    }
+
+    pub fn set_vertical_fov(&mut self, vertical_fov: f32, aspect_ratio: f32) {
+        self.fov = (2.0 * (vertical_fov / 2.0).tan() * aspect_ratio).atan();
+    }
 }
@@ -1,9 +1,8 @@
 use crate::math::*;
 use crate::Ray;
-use crate::bvhs::BVH;
+use crate::bvhs::{BVH, TraversalStack};
 use crate::Vertex;
-use std::ffi::OsStr;
-use std::path::Path;
+use crate::loaders::MeshSource;
 use serde::{Serialize, Deserialize};
 
 pub struct CollisionInfo {
@@ -86,6 +85,11 @@ pub struct Mesh {
     pub vertices: Vec<Vertex>,
     pub triangles: Vec<[usize;3]>,
     pub bvh: BVH,
+    // Optional per-vertex colors, either empty or one per vertex.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub colors: Vec<Vec3f>,
+    #[serde(skip)]
+    pub source: Option<MeshSource>,
 }
 
 impl Mesh {
@@ -98,69 +102,34 @@ impl Mesh {
         Mesh {
             vertices,
             triangles,
-            bvh
+            bvh,
+            colors: Vec::new(),
+            source: None,
         }
     }
+}
 
-    pub fn load_obj(path: &(impl AsRef<OsStr> + ?Sized)) -> Result<Mesh,tobj::LoadError> {
-        let path = Path::new(path);
-        let params = tobj::LoadOptions {
-            single_index: true,
-            ..tobj::LoadOptions::default()
-        };
-        let (models, _) = tobj::load_obj(path, &params)?;
-        if models.len() != 1 {
-            Err(tobj::LoadError::InvalidLoadOptionConfig)
-        }
-        else {
-            let tobj::Mesh {positions, indices,texcoords, normals,..} = &models[0].mesh;
-            assert!(positions.len() % 3 == 0, "Position array's length is a not multiple of 3({})", positions.len());
-            assert!(normals.len() % 3 == 0, "Normal array's length is a not multiple of 3({})", normals.len());
-            assert!(texcoords.len() % 2 == 0, "TexCoords array's length is a not multiple of 2({})", texcoords.len());
-            assert!(indices  .len() % 3 == 0, "Indices array's length is a not multiple of 3({})", indices  .len());
-            
-            let positions_iter = (0..positions.len() / 3).map(|i| (3*i,3*i+1,3*i+2))
-                                                            .map(|(i,j,k)| (positions[i], positions[j], positions[k]))
-                                                            .map(|(x,y,z)| Vec3f::new(x,y,z));
-
-            let uvs_iter = (0..texcoords.len() / 2).map(|i| (2*i,2*i+1))
-                                                       .map(|(i,j)| (texcoords[i], texcoords[j]))
-                                                       .map(|(u,v)| Vec2f::new(u,v));
-            let normals_iter = (0..normals.len() / 3).map(|i| (3*i,3*i+1,3*i+2))
-                                                       .map(|(i,j,k)| (normals[i], normals[j], normals[k]))
-                                                       .map(|(x,y,z)| Vec3f::new(x,y,z));
-
-            let vertices: Vec<_> = positions_iter.zip(normals_iter).zip(uvs_iter)
-                                    .map(|((position, normal), uv_coord)| Vertex {position, normal, uv_coord})
-                                    .collect();
-
-            let mut triangles : Vec<_> = (0..indices.len() / 3).map(|i| (3*i,3*i+1,3*i+2))
-                                                       .map(|(i,j,k)| [indices[i] as usize, indices[j]as usize, indices[k]as usize])
-                                                       .collect();
-            
-            let bvh = BVH::build(&vertices, &mut triangles);
-            
-            Ok(Mesh {
-                vertices,
-                triangles,
-                bvh
-            })
-        }
+impl Collider for Mesh {
+    fn collide(&self, ray: &Ray, min_t: f32, max_t: f32) -> (Option<CollisionInfo>, CollisionReport) {
+        self.collide_with(ray, min_t, max_t, &mut TraversalStack::default())
     }
 }
 
-impl Collider for Mesh {
-    fn collide(&self, ray: &Ray, min_t: f32, mut max_t: f32) -> (Option<CollisionInfo>, CollisionReport) {
+impl Mesh {
+    // Like `collide`, with a traversal stack kept by the caller.
+    pub(crate) fn collide_with(&self, ray: &Ray, min_t: f32, mut max_t: f32, stack: &mut TraversalStack) -> (Option<CollisionInfo>, CollisionReport) {
         let mut closest_dist = max_t;
         let mut closest_hit = None;
 
-        let mut triangle_iter = self.bvh.intersects(ray, min_t, max_t);
-        let mut report = CollisionReport::default();
+        let mut triangle_iter = self.bvh.intersects(ray, min_t, max_t, stack);
+        // `intersects` tests the box of the root.
+        let mut report = CollisionReport { aabb_tests: 1, triangle_tests: 0 };
 
         let (mut begin, mut end, mut aabb_tests) = triangle_iter.next(min_t, max_t);
         report.aabb_tests += aabb_tests;
 
         while (begin,end) != (0,0){
+            report.triangle_tests += (end - begin) as u64;
 
             for &[i,j,k] in &self.triangles[begin..end] {
                 let triangle = Triangle {
@@ -183,7 +152,7 @@ impl Collider for Mesh {
                             t: collision.t,
                             inside: collision.inside,
                             normal,
-                            uv: collision.uv
+                            uv
                         });
 
                         max_t = collision.t;
@@ -193,11 +162,33 @@ impl Collider for Mesh {
 
             (begin,end, aabb_tests) = triangle_iter.next(min_t, max_t);
             report.aabb_tests += aabb_tests;
-            report.triangle_tests += (end - begin) as u64;
         }
 
         (closest_hit, report)
     
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reused_traversal_stack() {
+        // A row of squares, for the BVH to have a few levels.
+        let vertex = |x: f32, y: f32| Vertex { position: Vec3f::new(x, y, 0.0), normal: Vec3f::z(), uv_coord: Vec2f::zeros() };
+        let vertices: Vec<_> = (0..=16).flat_map(|i| [vertex(i as f32, 0.0), vertex(i as f32, 1.0)]).collect();
+        let triangles: Vec<_> = (0..16).flat_map(|i| [[2 * i, 2 * i + 2, 2 * i + 3], [2 * i, 2 * i + 3, 2 * i + 1]]).collect();
+        let mesh = Mesh::new(vertices, triangles);
+        assert!(mesh.bvh.depth() > 2);
+
+        // Each traversal starts from the root, whatever the last one left on the stack.
+        let mut stack = TraversalStack::default();
+        for x in [15.5, 0.5, 8.5, 3.5] {
+            let ray = Ray { origin: Vec3f::new(x, 0.5, 1.0), direction: -Vec3f::z() };
+            let (hit, _) = mesh.collide_with(&ray, 0.0, f32::INFINITY, &mut stack);
+            assert_eq!(hit.map(|hit| hit.point.x), Some(x));
+        }
+    }
+}
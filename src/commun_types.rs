@@ -1,21 +1,76 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
 use crate::math::*;
+use crate::TileInfo;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ray {
     pub origin : Vec3f,
     pub direction : Vec3f,
 }
+// `repr(C)` so that the vertices of a binary mesh can be copied in one go, see `Mesh::from_binary`.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[repr(C)]
 pub struct Vertex {
     pub position: Vec3f,
     pub normal: Vec3f,
     pub uv_coord: Vec2f,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct RenderReport {
     pub aabb_tests: u64,
     pub triangle_tests: u64,
+    // Rays shot from the camera, and rays scattered by the surfaces they hit.
+    pub primary_rays: u64,
+    pub secondary_rays: u64,
+    // `path_lengths[n]` is the number of paths made of n rays, either because the last one escaped to the
+    // environment or because the path reached `RenderOptions::max_depth`.
+    pub path_lengths: Vec<u64>,
+    // Time each tile of each pass took to render.
+    pub tile_times: Vec<(TileInfo, Duration)>,
+    pub elapsed: Duration,
+    // Samples every pixel of the image received, fewer than asked for when the time limit cut the render short.
+    pub samples_per_pixel: u32,
+    // Noise estimated from the variance of the samples, see `RenderOptions::noise_target`. Only estimated with a noise target.
+    pub noise: Option<f32>,
+    // Samples accumulated in each pixel, row by row, zero outside of the crop.
+    pub sample_counts: Vec<u32>,
+    // Whether `RenderControl::cancel` stopped the render, the image then holds the average of the samples done so far.
+    pub cancelled: bool,
+}
+
+impl RenderReport {
+    pub fn rays(&self) -> u64 {
+        self.primary_rays + self.secondary_rays
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        self.rays() as f64 / self.elapsed.as_secs_f64()
+    }
+
+    // Adds the ray counters and tile times of `other`, e.g. the ones a worker thread gathered.
+    pub fn merge(&mut self, other: &RenderReport) {
+        self.aabb_tests += other.aabb_tests;
+        self.triangle_tests += other.triangle_tests;
+        self.primary_rays += other.primary_rays;
+        self.secondary_rays += other.secondary_rays;
+        if self.path_lengths.len() < other.path_lengths.len() {
+            self.path_lengths.resize(other.path_lengths.len(), 0);
+        }
+        for (count, other) in self.path_lengths.iter_mut().zip(&other.path_lengths) {
+            *count += other;
+        }
+        self.tile_times.extend_from_slice(&other.tile_times);
+    }
+
+    pub(crate) fn record_path(&mut self, length: u32) {
+        let length = length as usize;
+        if self.path_lengths.len() <= length {
+            self.path_lengths.resize(length + 1, 0);
+        }
+        self.path_lengths[length] += 1;
+    }
 }
@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+// Lets another thread cancel, pause and resume a render through `RenderOptions::control`.
+// Renders check it before each tile and each pass, so they stop or pause within a tile's worth of work.
+#[derive(Debug, Clone, Default)]
+pub struct RenderControl {
+    state: Arc<ControlState>,
+}
+
+#[derive(Debug, Default)]
+struct ControlState {
+    cancelled: AtomicBool,
+    pause: Mutex<PauseState>,
+    resumed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct PauseState {
+    paused_since: Option<Instant>,
+    // Time spent paused, which doesn't count towards `RenderOptions::time_limit`.
+    paused_for: Duration,
+}
+
+impl RenderControl {
+    pub fn new() -> RenderControl {
+        RenderControl::default()
+    }
+
+    // The render returns as soon as its workers finish the tile they are on, with what it has accumulated.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+        // Paused workers wake up to see the cancellation.
+        let _pause = self.state.pause.lock().unwrap();
+        self.state.resumed.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn pause(&self) {
+        let mut pause = self.state.pause.lock().unwrap();
+        pause.paused_since.get_or_insert_with(Instant::now);
+    }
+
+    pub fn resume(&self) {
+        let mut pause = self.state.pause.lock().unwrap();
+        if let Some(since) = pause.paused_since.take() {
+            pause.paused_for += since.elapsed();
+        }
+        self.state.resumed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.pause.lock().unwrap().paused_since.is_some()
+    }
+
+    // Blocks while the render is paused, then returns whether it should go on.
+    pub(crate) fn proceed(&self) -> bool {
+        let pause = self.state.pause.lock().unwrap();
+        let _pause = self.state.resumed.wait_while(pause, |pause| pause.paused_since.is_some() && !self.is_cancelled()).unwrap();
+        !self.is_cancelled()
+    }
+
+    // Like `proceed`, but gives up after `timeout` with `None` if the render is still paused.
+    pub(crate) fn proceed_within(&self, timeout: Duration) -> Option<bool> {
+        let pause = self.state.pause.lock().unwrap();
+        let (_pause, wait) = self.state.resumed.wait_timeout_while(pause, timeout, |pause| pause.paused_since.is_some() && !self.is_cancelled()).unwrap();
+        (!wait.timed_out()).then(|| !self.is_cancelled())
+    }
+
+    pub(crate) fn paused_for(&self) -> Duration {
+        let pause = self.state.pause.lock().unwrap();
+        pause.paused_for + pause.paused_since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+}
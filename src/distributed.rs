@@ -0,0 +1,540 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::ray_tracer::resolve;
+use crate::{Crop, Image, Progress, RayTracer, RenderControl, RenderError, RenderObserver, RenderOptions, RenderReport, Scene, SceneError, TileInfo, Vec3f};
+
+// Splits renders across worker processes, started with `render_scene worker` on this machine or others.
+// Each worker is sent the scene, then units of work one at a time: a region of the image and a number of samples
+// per pixel. The units of a worker that disconnects are given to the others.
+// Textures are sent as the path they were loaded from, workers need them at the same place.
+#[derive(Debug, Clone)]
+pub struct Coordinator {
+    // Addresses of the workers, such as 192.168.1.20:7878.
+    pub workers: Vec<String>,
+    // Side of the square regions of the image given to the workers, in pixels.
+    pub region_size: u32,
+    // Samples per pixel of a region given to a worker at once.
+    pub samples_per_unit: u32,
+    // A worker that doesn't answer for a unit within this time is considered lost, as if it had disconnected.
+    pub unit_timeout: Duration,
+}
+
+// Workers waiting for a unit are sent a keep-alive at this interval, and hang up when they haven't heard from their
+// coordinator for `IDLE_TIMEOUT`.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+// Largest image a worker accepts to render for a coordinator, 8192x8192.
+const MAX_PIXELS: u64 = 1 << 26;
+// Longest message either side reads, other than the scene and the results of the units.
+const MAX_MESSAGE_SIZE: usize = 1 << 16;
+// Scenes with embedded meshes can get large, but not that large.
+const MAX_SCENE_SIZE: usize = 1 << 30;
+// Room for the sums of a pixel of a unit result, written as JSON.
+const RESULT_SIZE_PER_PIXEL: usize = 256;
+
+#[derive(Debug)]
+pub enum DistributedError {
+    Io(io::Error),
+    // A worker or the coordinator sent something unexpected.
+    Protocol(String),
+    // A tile panicked on a worker, the render is stopped as it would be on this machine.
+    Render(RenderError),
+    // Every worker is gone with work left to do, with the address of each worker and why it was lost.
+    WorkersLost(Vec<(String, String)>),
+}
+
+impl std::fmt::Display for DistributedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DistributedError::Io(error) => write!(f, "{error}"),
+            DistributedError::Protocol(message) => write!(f, "protocol error: {message}"),
+            DistributedError::Render(error) => write!(f, "{error}"),
+            DistributedError::WorkersLost(workers) => {
+                write!(f, "every worker was lost before the end of the render")?;
+                for (address, error) in workers {
+                    write!(f, "\n    {address}: {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for DistributedError {}
+
+impl From<io::Error> for DistributedError {
+    fn from(error: io::Error) -> Self {
+        DistributedError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for DistributedError {
+    fn from(error: serde_json::Error) -> Self {
+        DistributedError::Protocol(error.to_string())
+    }
+}
+
+// Sent first to a worker, followed by the scene on its own line.
+#[derive(Debug, Serialize, Deserialize)]
+struct Job {
+    width: u32,
+    height: u32,
+    max_depth: u32,
+}
+
+// Sent to a worker after the scene, one at a time.
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    Render(Unit),
+    // Nothing to do for now, more units may come.
+    KeepAlive,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Unit {
+    // Units are numbered by batch of samples then by region, which seeds their random numbers.
+    id: usize,
+    batch: u32,
+    region: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    samples: u32,
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Response {
+    Done(UnitResult),
+    Failed(String),
+    // The scene didn't pass `Scene::validate`, with the issues found.
+    InvalidScene(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UnitResult {
+    id: usize,
+    // Sums of the samples of the region, row by row, and the sums of their squared luminance and their count.
+    sums: Vec<[f32; 3]>,
+    moments: Vec<[f32; 2]>,
+    aabb_tests: u64,
+    triangle_tests: u64,
+    primary_rays: u64,
+    secondary_rays: u64,
+    path_lengths: Vec<u64>,
+}
+
+struct Schedule {
+    pending: VecDeque<Unit>,
+    in_flight: usize,
+    done: usize,
+    failure: Option<DistributedError>,
+    lost: Vec<(String, String)>,
+}
+
+struct Accumulation {
+    sums: Image,
+    counts: Image<u32>,
+    moments: Image<f32>,
+    counters: RenderReport,
+}
+
+impl Coordinator {
+    pub fn new(workers: Vec<String>) -> Coordinator {
+        Coordinator { workers, region_size: 128, samples_per_unit: 16, unit_timeout: Duration::from_secs(600) }
+    }
+
+    // Renders like `RayTracer::render` with the workers. Renders are not progressive, `options.time_limit` and
+    // `options.noise_target` are ignored. Each unit finished is reported to `observer` as a tile.
+    pub fn render(&self, scene: &Scene, target: &mut Image, options: &RenderOptions, observer: &dyn RenderObserver) -> Result<RenderReport, DistributedError> {
+        let start = Instant::now();
+        let crop = options.crop.unwrap_or(Crop { x: 0, y: 0, width: target.width, height: target.height }).clamp(target.width, target.height);
+        let job = serde_json::to_string(&Job { width: target.width, height: target.height, max_depth: options.max_depth })?;
+        let scene = serde_json::to_string(scene)?;
+
+        let units = self.units(target, &crop, options);
+        let unit_count = units.len();
+        let schedule = Mutex::new(Schedule { pending: units.into(), in_flight: 0, done: 0, failure: None, lost: Vec::new() });
+        let changed = Condvar::new();
+        let accumulation = Mutex::new(Accumulation {
+            sums: Image::new(Vec3f::zeros(), target.width, target.height),
+            counts: Image::new(0, target.width, target.height),
+            moments: Image::new(0.0, target.width, target.height),
+            counters: RenderReport::default(),
+        });
+
+        std::thread::scope(|scope| {
+            for address in &self.workers {
+                let (job, scene, schedule, changed, accumulation) = (&job, &scene, &schedule, &changed, &accumulation);
+                scope.spawn(move || {
+                    let on_done = |schedule: &mut Schedule| {
+                        let elapsed = start.elapsed();
+                        let fraction = schedule.done as f32 / unit_count as f32;
+                        let eta = (fraction > 0.0).then(|| elapsed.mul_f32((1.0 - fraction) / fraction));
+                        observer.progress(&Progress { fraction, elapsed, eta });
+                    };
+                    let result = work_with(address, self.unit_timeout, job, scene, schedule, changed, accumulation, options, observer, on_done);
+                    let mut schedule = schedule.lock().unwrap();
+                    match result {
+                        Err(DistributedError::Render(error)) => {
+                            schedule.failure.get_or_insert(DistributedError::Render(error));
+                        }
+                        Err(error) => schedule.lost.push((address.clone(), error.to_string())),
+                        Ok(()) => {}
+                    }
+                    changed.notify_all();
+                });
+            }
+        });
+
+        let schedule = schedule.into_inner().unwrap();
+        if let Some(failure) = schedule.failure {
+            return Err(failure);
+        }
+        let cancelled = options.control.as_ref().is_some_and(RenderControl::is_cancelled);
+        if !cancelled && !schedule.pending.is_empty() {
+            return Err(DistributedError::WorkersLost(schedule.lost));
+        }
+
+        let Accumulation { sums, counts, moments, counters } = accumulation.into_inner().unwrap();
+        let report = resolve(target, &sums, counts, Some(&moments), &crop, RenderReport { elapsed: start.elapsed(), cancelled, ..counters });
+        observer.render_finished(&report);
+        Ok(report)
+    }
+
+    // The regions of the crop in `options.tile_order`, once for every batch of samples.
+    fn units(&self, target: &Image, crop: &Crop, options: &RenderOptions) -> Vec<Unit> {
+        let region_size = self.region_size.max(1);
+        let samples_per_unit = self.samples_per_unit.max(1);
+        let columns = target.width.div_ceil(region_size);
+        let rows = target.height.div_ceil(region_size);
+        let order = options.tile_order.order(columns, rows);
+
+        let mut units = Vec::new();
+        for batch in 0..options.rays_per_pixel.div_ceil(samples_per_unit) {
+            let samples = samples_per_unit.min(options.rays_per_pixel - batch * samples_per_unit);
+            for &region in &order {
+                let x = (region as u32 % columns * region_size).max(crop.x);
+                let y = (region as u32 / columns * region_size).max(crop.y);
+                let right = ((region as u32 % columns + 1) * region_size).min(crop.x + crop.width);
+                let bottom = ((region as u32 / columns + 1) * region_size).min(crop.y + crop.height);
+                if x >= right || y >= bottom {
+                    continue;
+                }
+                let id = batch as usize * order.len() + region;
+                // Spread far apart so that the seeds of the tiles of different units, which follow each other, never meet.
+                let seed = options.seed.map(|seed| seed.wrapping_add((id as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)));
+                units.push(Unit { id, batch, region, x, y, width: right - x, height: bottom - y, samples, seed });
+            }
+        }
+        units
+    }
+}
+
+// Sends units to the worker at `address` until there are none left. The unit it is working on goes back to the others
+// if it disconnects or doesn't answer in time.
+#[allow(clippy::too_many_arguments)]
+fn work_with(address: &str, unit_timeout: Duration, job: &str, scene: &str, schedule: &Mutex<Schedule>, changed: &Condvar,
+             accumulation: &Mutex<Accumulation>, options: &RenderOptions, observer: &dyn RenderObserver,
+             on_done: impl Fn(&mut Schedule)) -> Result<(), DistributedError> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(unit_timeout))?;
+    stream.set_write_timeout(Some(unit_timeout))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    writeln!(writer, "{job}")?;
+    writeln!(writer, "{scene}")?;
+    writer.flush()?;
+
+    loop {
+        let unit = match next_unit(schedule, changed, options) {
+            Next::Unit(unit) => unit,
+            Next::Wait => {
+                write_message(&mut writer, &Request::KeepAlive)?;
+                continue;
+            }
+            Next::Stop => break,
+        };
+        let unit_start = Instant::now();
+        let result = match exchange(&mut reader, &mut writer, &unit, unit_timeout) {
+            Ok(result) => result,
+            Err(error) => {
+                let mut schedule = schedule.lock().unwrap();
+                schedule.pending.push_front(unit);
+                schedule.in_flight -= 1;
+                changed.notify_all();
+                return Err(error);
+            }
+        };
+
+        let info = TileInfo { pass: unit.batch, index: unit.region, x: unit.x, y: unit.y, width: unit.width, height: unit.height };
+        let pixels = merge(accumulation, &unit, &result, info, unit_start.elapsed());
+        observer.tile_finished(&info, &pixels);
+
+        let mut schedule = schedule.lock().unwrap();
+        schedule.in_flight -= 1;
+        schedule.done += 1;
+        on_done(&mut schedule);
+        changed.notify_all();
+    }
+    Ok(())
+}
+
+// Has the worker render a unit.
+fn exchange(reader: &mut impl BufRead, writer: &mut impl Write, unit: &Unit, timeout: Duration) -> Result<UnitResult, DistributedError> {
+    write_message(writer, &Request::Render(*unit))?;
+    let limit = MAX_MESSAGE_SIZE + RESULT_SIZE_PER_PIXEL * (unit.width * unit.height) as usize;
+    let response = read_message(reader, limit).map_err(|error| match error {
+        // Read timeouts are reported as `WouldBlock` on some platforms.
+        DistributedError::Io(error) if matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) =>
+            io::Error::new(io::ErrorKind::TimedOut, format!("no answer for unit {} after {}s", unit.id, timeout.as_secs_f32())).into(),
+        error => error,
+    })?;
+    match response {
+        Some(Response::Done(result)) if result.id == unit.id && result.sums.len() == (unit.width * unit.height) as usize
+                                        && result.moments.len() == result.sums.len() => Ok(result),
+        Some(Response::Done(_)) => Err(DistributedError::Protocol(format!("unexpected result for unit {}", unit.id))),
+        Some(Response::Failed(message)) => Err(DistributedError::Render(RenderError::Panicked { message })),
+        Some(Response::InvalidScene(message)) => Err(DistributedError::Protocol(format!("the worker rejected the scene: {message}"))),
+        None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the worker hung up").into()),
+    }
+}
+
+enum Next {
+    Unit(Unit),
+    // Nothing to do for `KEEP_ALIVE_INTERVAL`, while paused or waiting for the units of the others.
+    Wait,
+    // Every unit is done, the render is cancelled or has failed.
+    Stop,
+}
+
+fn next_unit(schedule: &Mutex<Schedule>, changed: &Condvar, options: &RenderOptions) -> Next {
+    loop {
+        // Waits while the render is paused, outside of the lock so that the other workers can hand their results in.
+        match options.control.as_ref().map_or(Some(true), |control| control.proceed_within(KEEP_ALIVE_INTERVAL)) {
+            Some(true) => {}
+            Some(false) => return Next::Stop,
+            None => return Next::Wait,
+        }
+        let mut schedule = schedule.lock().unwrap();
+        if schedule.failure.is_some() {
+            return Next::Stop;
+        }
+        if let Some(unit) = schedule.pending.pop_front() {
+            schedule.in_flight += 1;
+            return Next::Unit(unit);
+        }
+        if schedule.in_flight == 0 {
+            return Next::Stop;
+        }
+        // Another worker may still be lost and leave its unit behind.
+        if changed.wait_timeout(schedule, KEEP_ALIVE_INTERVAL).unwrap().1.timed_out() {
+            return Next::Wait;
+        }
+    }
+}
+
+// Adds the samples of a unit to the image, returns the region as rendered so far.
+fn merge(accumulation: &Mutex<Accumulation>, unit: &Unit, result: &UnitResult, info: TileInfo, elapsed: Duration) -> Vec<Vec3f> {
+    let mut accumulation = accumulation.lock().unwrap();
+    let Accumulation { sums, counts, moments, counters } = &mut *accumulation;
+    let mut pixels = Vec::with_capacity(result.sums.len());
+    for (k, (sum, moment)) in result.sums.iter().zip(&result.moments).enumerate() {
+        let i = (unit.y + k as u32 / unit.width) as usize;
+        let j = (unit.x + k as u32 % unit.width) as usize;
+        sums[[i,j]] += Vec3f::from(*sum);
+        moments[[i,j]] += moment[0];
+        counts[[i,j]] += moment[1] as u32;
+        let count = counts[[i,j]];
+        pixels.push(if count > 0 { sums[[i,j]] / count as f32 } else { Vec3f::zeros() });
+    }
+
+    counters.merge(&RenderReport {
+        aabb_tests: result.aabb_tests,
+        triangle_tests: result.triangle_tests,
+        primary_rays: result.primary_rays,
+        secondary_rays: result.secondary_rays,
+        path_lengths: result.path_lengths.clone(),
+        // The time a unit took, transfers included.
+        tile_times: vec![(info, elapsed)],
+        ..Default::default()
+    });
+    pixels
+}
+
+// Renders the units a coordinator sends on `stream` with `renderer`, until it hangs up or stops sending keep-alives.
+pub fn serve_coordinator(stream: TcpStream, renderer: &RayTracer) -> Result<(), DistributedError> {
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+    stream.set_write_timeout(Some(IDLE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    let job: Job = read_message(&mut reader, MAX_MESSAGE_SIZE)?.ok_or_else(|| DistributedError::Protocol("expected a job".to_string()))?;
+    let mut scene: Scene = read_message(&mut reader, MAX_SCENE_SIZE)?.ok_or_else(|| DistributedError::Protocol("expected a scene".to_string()))?;
+    if job.width as u64 * job.height as u64 > MAX_PIXELS {
+        return Err(DistributedError::Protocol(format!("the image is too large, {}x{}", job.width, job.height)));
+    }
+    // Checked as `Scene::load` does, the issues are sent back in answer to the first unit.
+    let issues = scene.validate();
+    let invalid = issues.iter().any(|issue| !issue.is_warning()).then(|| SceneError::Invalid(issues).to_string());
+    // Objects placed through the scene graph aren't serialized.
+    scene.update_world_transforms();
+
+    let mut sums = Image::new(Vec3f::zeros(), job.width, job.height);
+    let mut counts = Image::new(0, job.width, job.height);
+    let mut moments = Image::new(0.0, job.width, job.height);
+    while let Some(request) = read_message::<Request>(&mut reader, MAX_MESSAGE_SIZE)? {
+        let Request::Render(unit) = request else { continue };
+        if let Some(message) = invalid {
+            write_message(&mut writer, &Response::InvalidScene(message.clone()))?;
+            return Err(DistributedError::Protocol(message));
+        }
+        let inside = |start: u32, size: u32, end: u32| start.checked_add(size).is_some_and(|stop| stop <= end);
+        if !inside(unit.x, unit.width, job.width) || !inside(unit.y, unit.height, job.height) {
+            return Err(DistributedError::Protocol(format!("unit {} is outside of the image", unit.id)));
+        }
+        let crop = Crop { x: unit.x, y: unit.y, width: unit.width, height: unit.height };
+        sums.view(crop.x, crop.y, crop.width, crop.height).fill(Vec3f::zeros());
+        counts.view(crop.x, crop.y, crop.width, crop.height).fill(0);
+        moments.view(crop.x, crop.y, crop.width, crop.height).fill(0.0);
+        let options = RenderOptions { max_depth: job.max_depth, rays_per_pixel: unit.samples, seed: unit.seed, crop: Some(crop), ..Default::default() };
+
+        let response = match renderer.accumulate(&scene, &mut sums, &mut counts, Some(&mut moments), &crop, &options, &(), Instant::now()) {
+            Ok(counters) => {
+                let pixels = (crop.y..crop.y + crop.height).flat_map(|i| (crop.x..crop.x + crop.width).map(move |j| [i as usize, j as usize]));
+                Response::Done(UnitResult {
+                    id: unit.id,
+                    sums: pixels.clone().map(|pixel| sums[pixel].into()).collect(),
+                    moments: pixels.map(|pixel| [moments[pixel], counts[pixel] as f32]).collect(),
+                    aabb_tests: counters.aabb_tests,
+                    triangle_tests: counters.triangle_tests,
+                    primary_rays: counters.primary_rays,
+                    secondary_rays: counters.secondary_rays,
+                    path_lengths: counters.path_lengths,
+                })
+            }
+            Err(error) => Response::Failed(error.to_string()),
+        };
+        write_message(&mut writer, &response)?;
+    }
+    Ok(())
+}
+
+// Messages are JSON, one per line of at most `limit` bytes. `None` once the other side hung up.
+fn read_message<T: DeserializeOwned>(reader: &mut impl BufRead, limit: usize) -> Result<Option<T>, DistributedError> {
+    let mut line = String::new();
+    if reader.take(limit as u64 + 1).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.len() > limit {
+        return Err(DistributedError::Protocol(format!("a message is longer than {limit} bytes")));
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+fn write_message(writer: &mut impl Write, message: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, message)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{grey, quad, sky_scene};
+    use crate::{Mat4f, Object};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_distributed_render() {
+        let mut scene = sky_scene(Vec3f::new(0.2, 0.4, 0.8));
+        let quad = scene.add_mesh(quad(Vec3f::new(-0.3, -0.3, -3.0), 0.6));
+        let material = scene.add_material(grey());
+        scene.add_object(Object::new(quad, Mat4f::identity(), material).unwrap());
+
+        // Three workers, the second one hangs up as soon as it is given a unit and the third one never answers.
+        // Their units go to the first one.
+        let mut addresses = Vec::new();
+        let mut workers = Vec::new();
+        for behavior in ["reliable", "hangs up", "silent"] {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            addresses.push(listener.local_addr().unwrap().to_string());
+            workers.push(std::thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                let lines = BufReader::new(stream.try_clone().unwrap()).lines();
+                match behavior {
+                    "reliable" => serve_coordinator(stream, &RayTracer::with_threads(2)).unwrap(),
+                    // The job, the scene and a unit.
+                    "hangs up" => lines.take(3).for_each(drop),
+                    // Until the coordinator gives up on it.
+                    _ => lines.for_each(drop),
+                }
+            }));
+        }
+
+        let coordinator = Coordinator { region_size: 16, samples_per_unit: 2, unit_timeout: Duration::from_millis(500), ..Coordinator::new(addresses) };
+        let options = RenderOptions { rays_per_pixel: 4, seed: Some(1), ..Default::default() };
+        let mut target = Image::new(Vec3f::zeros(), 40, 30);
+        let report = coordinator.render(&scene, &mut target, &options, &()).unwrap();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        // 3x2 regions and 2 batches of 2 samples.
+        assert_eq!(report.samples_per_pixel, 4);
+        assert_eq!(report.primary_rays, 40 * 30 * 4);
+        assert_eq!(report.tile_times.len(), 12);
+        assert!((target[[0, 0]] - Vec3f::new(0.2, 0.4, 0.8)).norm() < 1e-5);
+        assert!(target[[15, 20]].x > 0.0 && target[[15, 20]].x < 0.2);
+
+        // Without any worker left, the render fails instead of returning a partial image.
+        let error = Coordinator::new(vec!["127.0.0.1:1".to_string()]).render(&scene, &mut target, &options, &()).unwrap_err();
+        assert!(matches!(error, DistributedError::WorkersLost(ref lost) if lost.len() == 1), "{error}");
+    }
+
+    #[test]
+    fn test_worker_checks_what_it_is_sent() {
+        let worker = |messages: Vec<String>| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            let worker = std::thread::spawn(move || serve_coordinator(listener.accept().unwrap().0, &RayTracer::with_threads(1)));
+            let mut stream = TcpStream::connect(address).unwrap();
+            for message in messages {
+                writeln!(stream, "{message}").unwrap();
+            }
+            let mut response = String::new();
+            BufReader::new(stream).read_line(&mut response).unwrap();
+            (worker.join().unwrap(), response)
+        };
+        let job = serde_json::to_string(&Job { width: 8, height: 8, max_depth: 4 }).unwrap();
+        let unit = serde_json::to_string(&Request::Render(Unit { id: 0, batch: 0, region: 0, x: 0, y: 0, width: 8, height: 8, samples: 1, seed: None })).unwrap();
+
+        // An object refers to a mesh that doesn't exist.
+        let mut scene = sky_scene(Vec3f::zeros());
+        let mesh = scene.add_mesh(quad(Vec3f::zeros(), 1.0));
+        let material = scene.add_material(grey());
+        scene.add_object(Object::new(mesh, Mat4f::identity(), material).unwrap());
+        let mut invalid = serde_json::to_value(&scene).unwrap();
+        invalid["objects"][0]["value"]["mesh"]["index"] = 7.into();
+        let (result, response) = worker(vec![job.clone(), invalid.to_string(), unit.clone()]);
+        assert!(matches!(result, Err(DistributedError::Protocol(ref message)) if message.contains("objects[0].value.mesh")), "{result:?}");
+        assert!(matches!(serde_json::from_str(&response).unwrap(), Response::InvalidScene(_)), "{response}");
+
+        let (result, response) = worker(vec![" ".repeat(MAX_MESSAGE_SIZE + 1)]);
+        assert!(matches!(result, Err(DistributedError::Protocol(_))), "{result:?}");
+        assert!(response.is_empty());
+
+        let (result, response) = worker(vec![job, serde_json::to_string(&scene).unwrap(), unit]);
+        assert!(matches!(serde_json::from_str(&response).unwrap(), Response::Done(_)), "{response}");
+        assert!(result.is_ok(), "{result:?}");
+    }
+}
@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::{json, Value};
+
+use crate::loaders::relative_path;
+use crate::{Scene, TextureSource, Vec3f};
+use crate::export::ExportedMaterial;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+// The binary buffer of the file with the buffer views and accessors describing it.
+#[derive(Default)]
+struct Buffer {
+    bytes: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl Buffer {
+    fn push_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        // Accessors need their data aligned on 4 bytes.
+        while !self.bytes.len().is_multiple_of(4) {
+            self.bytes.push(0);
+        }
+        let mut view = json!({"buffer": 0, "byteOffset": self.bytes.len(), "byteLength": bytes.len()});
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.bytes.extend_from_slice(bytes);
+        self.views.push(view);
+        self.views.len() - 1
+    }
+
+    fn push_floats<const N: usize>(&mut self, values: &[[f32;N]], kind: &str, bounds: bool) -> usize {
+        let bytes: Vec<u8> = values.iter().flatten().flat_map(|x| x.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, Some(ARRAY_BUFFER));
+        let mut accessor = json!({"bufferView": view, "componentType": FLOAT, "count": values.len(), "type": kind});
+        // Positions must have their bounds.
+        if bounds {
+            let min = (0..N).map(|c| values.iter().map(|v| v[c]).fold(f32::INFINITY, f32::min)).collect::<Vec<_>>();
+            let max = (0..N).map(|c| values.iter().map(|v| v[c]).fold(f32::NEG_INFINITY, f32::max)).collect::<Vec<_>>();
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn push_indices(&mut self, triangles: &[[usize;3]]) -> usize {
+        let bytes: Vec<u8> = triangles.iter().flatten().flat_map(|&i| (i as u32).to_le_bytes()).collect();
+        let view = self.push_view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(json!({"bufferView": view, "componentType": UNSIGNED_INT, "count": 3 * triangles.len(), "type": "SCALAR"}));
+        self.accessors.len() - 1
+    }
+}
+
+impl Scene {
+    // Writes every object in world space, its approximated material and the camera to a glTF file.
+    // `.glb` files get the binary container, other extensions a JSON file with its buffer inlined.
+    pub fn export_gltf(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let base = path.parent().unwrap_or(Path::new(""));
+        let (meshes, materials) = self.export_meshes();
+        let mut buffer = Buffer::default();
+
+        let mut images = Vec::new();
+        let mut textures = Vec::new();
+        let mut texture = |source: &Option<TextureSource>, buffer: &mut Buffer| -> Option<Value> {
+            let image = match source.as_ref()? {
+                TextureSource::File(texture_path) => json!({"uri": relative_path(texture_path, base).to_string_lossy().replace('\\', "/")}),
+                TextureSource::Embedded(bytes) => {
+                    let mime_type = match image::guess_format(bytes) {
+                        Ok(image::ImageFormat::Jpeg) => "image/jpeg",
+                        _ => "image/png",
+                    };
+                    json!({"bufferView": buffer.push_view(bytes, None), "mimeType": mime_type})
+                }
+            };
+            images.push(image);
+            textures.push(json!({"source": images.len() - 1}));
+            Some(json!({"index": textures.len() - 1}))
+        };
+
+        let materials: Vec<Value> = materials.iter().map(|material| {
+            let diffuse_texture = texture(&material.diffuse_texture, &mut buffer);
+            let emission_texture = texture(&material.emission_texture, &mut buffer);
+            translate_material(material, diffuse_texture, emission_texture)
+        }).collect();
+
+        let mut nodes = Vec::new();
+        let gltf_meshes: Vec<Value> = meshes.iter().enumerate().map(|(i, mesh)| {
+            let positions = buffer.push_floats(&mesh.positions.iter().map(|p| [p.x, p.y, p.z]).collect::<Vec<_>>(), "VEC3", true);
+            let normals = buffer.push_floats(&mesh.normals.iter().map(|n| [n.x, n.y, n.z]).collect::<Vec<_>>(), "VEC3", false);
+            // glTF uvs start at the top of images.
+            let uvs = buffer.push_floats(&mesh.uvs.iter().map(|uv| [uv.x, 1.0 - uv.y]).collect::<Vec<_>>(), "VEC2", false);
+            let mut attributes = json!({"POSITION": positions, "NORMAL": normals, "TEXCOORD_0": uvs});
+            if mesh.colors.len() == mesh.positions.len() && !mesh.colors.is_empty() {
+                attributes["COLOR_0"] = json!(buffer.push_floats(&mesh.colors.iter().map(|c| [c.x, c.y, c.z]).collect::<Vec<_>>(), "VEC3", false));
+            }
+            let indices = buffer.push_indices(&mesh.triangles);
+
+            nodes.push(json!({"name": mesh.name, "mesh": i}));
+            json!({"name": mesh.name, "primitives": [{"attributes": attributes, "indices": indices, "material": mesh.material}]})
+        }).collect();
+
+        // glTF cameras look towards -z with y up, the field of view is exported for a square image.
+        let camera = &self.camera;
+        let right = camera.direction.cross(&camera.up).normalize();
+        let up = right.cross(&camera.direction).normalize();
+        let backward = -camera.direction.normalize();
+        let matrix: Vec<f32> = [right, up, backward].iter().flat_map(|axis| [axis.x, axis.y, axis.z, 0.0])
+                                                    .chain([camera.origin.x, camera.origin.y, camera.origin.z, 1.0]).collect();
+        nodes.push(json!({"name": "camera", "camera": 0, "matrix": matrix}));
+
+        let mut document = json!({
+            "asset": {"version": "2.0", "generator": "ray-tracer"},
+            "scene": 0,
+            "scenes": [{"nodes": (0..nodes.len()).collect::<Vec<_>>()}],
+            "nodes": nodes,
+            "meshes": gltf_meshes,
+            "materials": materials,
+            "cameras": [{"type": "perspective", "perspective": {"yfov": camera.vertical_fov(1.0), "aspectRatio": 1.0, "znear": 0.001}}],
+            "bufferViews": buffer.views,
+            "accessors": buffer.accessors,
+        });
+        if !images.is_empty() {
+            document["images"] = json!(images);
+            document["textures"] = json!(textures);
+        }
+        let extensions: Vec<&str> = ["KHR_materials_transmission", "KHR_materials_ior", "KHR_materials_emissive_strength"].into_iter()
+            .filter(|extension| document["materials"].as_array().unwrap().iter().any(|material| !material["extensions"][extension].is_null()))
+            .collect();
+        if !extensions.is_empty() {
+            document["extensionsUsed"] = json!(extensions);
+        }
+
+        let is_binary = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("glb"));
+        if is_binary {
+            document["buffers"] = json!([{"byteLength": buffer.bytes.len()}]);
+            std::fs::write(path, glb(&serde_json::to_vec(&document)?, buffer.bytes))
+        } else {
+            let uri = format!("data:application/octet-stream;base64,{}", BASE64.encode(&buffer.bytes));
+            document["buffers"] = json!([{"byteLength": buffer.bytes.len(), "uri": uri}]);
+            std::fs::write(path, serde_json::to_vec_pretty(&document)?)
+        }
+    }
+}
+
+fn translate_material(material: &ExportedMaterial, diffuse_texture: Option<Value>, emission_texture: Option<Value>) -> Value {
+    let (base_color, metallic) = if material.metallic { (material.specular, 1.0) } else { (material.diffuse, 0.0) };
+    let mut pbr = json!({
+        "baseColorFactor": [base_color.x, base_color.y, base_color.z, 1.0],
+        "metallicFactor": metallic,
+        // Inverse of the squaring done by the importer.
+        "roughnessFactor": material.roughness.clamp(0.0, 1.0).sqrt(),
+    });
+    if let Some(texture) = diffuse_texture {
+        pbr["baseColorTexture"] = texture;
+    }
+
+    // Emission factors are limited to 1, stronger emissions need the emissive strength extension.
+    let strength = material.emission.max();
+    let emissive = if strength > 1.0 { material.emission / strength } else { material.emission };
+    let mut value = json!({"name": material.name, "pbrMetallicRoughness": pbr, "emissiveFactor": [emissive.x, emissive.y, emissive.z]});
+    if let Some(texture) = emission_texture {
+        value["emissiveTexture"] = texture;
+        if material.emission == Vec3f::zeros() {
+            value["emissiveFactor"] = json!([1.0, 1.0, 1.0]);
+        }
+    }
+    if strength > 1.0 {
+        value["extensions"]["KHR_materials_emissive_strength"] = json!({"emissiveStrength": strength});
+    }
+    if material.opacity < 1.0 {
+        value["extensions"]["KHR_materials_transmission"] = json!({"transmissionFactor": 1.0 - material.opacity});
+    }
+    if material.refraction_index != 1.5 && material.opacity < 1.0 {
+        value["extensions"]["KHR_materials_ior"] = json!({"ior": material.refraction_index});
+    }
+    value
+}
+
+// The binary container: a header followed by a JSON chunk and a binary chunk, both padded to 4 bytes.
+fn glb(json: &[u8], mut bin: Vec<u8>) -> Vec<u8> {
+    let mut json = json.to_vec();
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    let mut bytes = Vec::with_capacity(length);
+    bytes.extend_from_slice(b"glTF");
+    bytes.extend(2u32.to_le_bytes());
+    bytes.extend((length as u32).to_le_bytes());
+    bytes.extend((json.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(b"JSON");
+    bytes.extend(json);
+    bytes.extend((bin.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(b"BIN\0");
+    bytes.extend(bin);
+    bytes
+}
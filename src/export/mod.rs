@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::Value;
+
+use crate::{Material, Scene, TextureSource, Vec2f, Vec3f, vec3_to_vec4};
+
+mod obj;
+mod gltf;
+
+// The mesh of an object, transformed to world space.
+struct WorldMesh {
+    name: String,
+    material: usize,
+    positions: Vec<Vec3f>,
+    normals: Vec<Vec3f>,
+    uvs: Vec<Vec2f>,
+    colors: Vec<Vec3f>,
+    triangles: Vec<[usize;3]>,
+}
+
+// The parameters of a material that OBJ and glTF files can both express.
+struct ExportedMaterial {
+    name: String,
+    diffuse: Vec3f,
+    diffuse_texture: Option<TextureSource>,
+    specular: Vec3f,
+    roughness: f32,
+    metallic: bool,
+    opacity: f32,
+    refraction_index: f32,
+    emission: Vec3f,
+    emission_texture: Option<TextureSource>,
+}
+
+impl Default for ExportedMaterial {
+    fn default() -> Self {
+        ExportedMaterial {
+            name: String::new(),
+            diffuse: Vec3f::repeat(0.8),
+            diffuse_texture: None,
+            specular: Vec3f::zeros(),
+            roughness: 1.0,
+            metallic: false,
+            opacity: 1.0,
+            refraction_index: 1.0,
+            emission: Vec3f::zeros(),
+            emission_texture: None,
+        }
+    }
+}
+
+// Materials are trait objects, so they are approximated from their serialized form, as material animations are.
+// Unknown material types become a grey diffuse material.
+fn approximate_material(name: String, material: &dyn Material) -> ExportedMaterial {
+    let value = serde_json::to_value(material).unwrap_or(Value::Null);
+    let vector = |key: &str| serde_json::from_value::<Vec3f>(value[key].clone()).ok();
+    let number = |key: &str| value[key].as_f64().map(|x| x as f32);
+    let texture = |key: &str| -> Option<TextureSource> {
+        let texture = &value[key];
+        match (texture["path"].as_str(), texture["data"].as_str()) {
+            (Some(path), _) => Some(TextureSource::File(PathBuf::from(path))),
+            (None, Some(data)) => BASE64.decode(data).ok().map(TextureSource::Embedded),
+            (None, None) => None,
+        }
+    };
+
+    let exported = ExportedMaterial { name, ..ExportedMaterial::default() };
+    match value["type"].as_str() {
+        Some("Lambertian") => ExportedMaterial {
+            diffuse: vector("color").unwrap_or(exported.diffuse),
+            emission: vector("emission").unwrap_or_default(),
+            ..exported
+        },
+        Some("Metal") => ExportedMaterial {
+            diffuse: Vec3f::zeros(),
+            specular: vector("color").unwrap_or_default(),
+            roughness: number("roughness").unwrap_or_default(),
+            metallic: true,
+            ..exported
+        },
+        Some("Dialectric") => ExportedMaterial {
+            diffuse: Vec3f::zeros(),
+            specular: Vec3f::repeat(1.0),
+            roughness: 0.0,
+            opacity: 0.0,
+            refraction_index: number("refraction_index").unwrap_or(1.5),
+            ..exported
+        },
+        Some("Standard") => {
+            let diffuse = vector("diffuse").unwrap_or_default();
+            let specular = vector("specular").unwrap_or_default();
+            ExportedMaterial {
+                diffuse,
+                diffuse_texture: texture("diffuse_texture"),
+                specular,
+                roughness: number("roughness").unwrap_or(1.0),
+                metallic: diffuse == Vec3f::zeros() && specular.max() > 0.04,
+                opacity: number("opacity").unwrap_or(1.0),
+                refraction_index: number("refraction_index").unwrap_or(1.0),
+                emission: vector("emission").unwrap_or_default(),
+                emission_texture: texture("emission_texture"),
+                ..exported
+            }
+        }
+        _ => exported,
+    }
+}
+
+impl Scene {
+    // Every object of the scene, including the ones of scene graph nodes, with its mesh in world space.
+    fn export_meshes(&self) -> (Vec<WorldMesh>, Vec<ExportedMaterial>) {
+        let mut material_indices = HashMap::new();
+        let materials = self.materials.iter().enumerate().map(|(i, (handle, name, material))| {
+            material_indices.insert(handle, i);
+            approximate_material(name.map(str::to_string).unwrap_or_else(|| format!("material{i}")), material.as_ref())
+        }).collect();
+
+        let named_objects = self.objects.iter().map(|(_, name, object)| (name.map(str::to_string), object));
+        let node_objects = self.world_objects.iter().map(|object| (None, object));
+
+        let meshes = named_objects.chain(node_objects).enumerate().filter_map(|(i, (name, object))| {
+            let mesh = self.meshes.get(object.get_mesh()).filter(|mesh| !mesh.triangles.is_empty())?;
+            let material = *material_indices.get(&object.get_material())?;
+            let transform = object.get_transform();
+            let normal_matrix = transform.fixed_view::<3, 3>(0, 0).try_inverse().unwrap_or_default().transpose();
+
+            Some(WorldMesh {
+                name: name.unwrap_or_else(|| format!("object{i}")),
+                material,
+                positions: mesh.vertices.iter().map(|vertex| (transform * vec3_to_vec4(&vertex.position, 1.0)).xyz()).collect(),
+                normals: mesh.vertices.iter().map(|vertex| (normal_matrix * vertex.normal).try_normalize(0.0).unwrap_or_default()).collect(),
+                uvs: mesh.vertices.iter().map(|vertex| vertex.uv_coord).collect(),
+                colors: mesh.colors.clone(),
+                triangles: mesh.triangles.clone(),
+            })
+        }).collect();
+
+        (meshes, materials)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{quad, sky_scene, TempDir};
+    use crate::{Camera, Import, Lambertian, Mat4f, Metal, Object};
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut scene = sky_scene(Vec3f::zeros());
+        scene.camera = Camera::new(Vec3f::new(0.0, 1.0, 5.0), -Vec3f::z(), Vec3f::y(), 0.5);
+        let mesh = scene.add_mesh(quad(Vec3f::zeros(), 1.0));
+        let white = scene.add_named_material("white", Box::new(Lambertian { color: Vec3f::repeat(0.9), emission: Vec3f::zeros() }));
+        let gold = scene.add_named_material("gold", Box::new(Metal { color: Vec3f::new(1.0, 0.8, 0.2), roughness: 0.25 }));
+        scene.add_named_object("left", Object::new(mesh, Mat4f::new_translation(&Vec3f::new(-2.0, 0.0, 0.0)), white).unwrap());
+        scene.add_named_object("right", Object::new(mesh, Mat4f::new_nonuniform_scaling(&Vec3f::new(2.0, 1.0, 1.0)), gold).unwrap());
+
+        let directory = TempDir::new("export_round_trip");
+        for file in ["scene.obj", "scene.gltf", "scene.glb"] {
+            let path = directory.join(file);
+            if file.ends_with(".obj") {
+                scene.export_obj(&path).unwrap();
+            } else {
+                scene.export_gltf(&path).unwrap();
+            }
+
+            let import = Import::load(&path).unwrap();
+            assert_eq!(import.objects.len(), 2, "{file}");
+            let positions = |i: usize| {
+                let object = &import.objects[i];
+                let mesh = &import.meshes[object.mesh].1;
+                mesh.vertices.iter().map(|vertex| (object.transform * vec3_to_vec4(&vertex.position, 1.0)).xyz()).collect::<Vec<_>>()
+            };
+            assert!(positions(0).contains(&Vec3f::new(-1.0, 0.0, 0.0)), "{file}");
+            assert!(positions(1).contains(&Vec3f::new(2.0, 0.0, 0.0)), "{file}");
+
+            let material = serde_json::to_value(&import.materials[import.objects[1].material].1).unwrap();
+            assert_eq!(material["roughness"], 0.25, "{file}");
+
+            if let Some(camera) = import.camera {
+                assert!((camera.origin - scene.camera.origin).norm() < 1e-5);
+                assert!((camera.direction - scene.camera.direction).norm() < 1e-5);
+                assert!((camera.fov - scene.camera.fov).abs() < 1e-5);
+            }
+        }
+    }
+}
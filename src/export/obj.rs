@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::loaders::relative_path;
+use crate::{Scene, TextureSource};
+use crate::export::ExportedMaterial;
+
+impl Scene {
+    // Writes every object in world space to an OBJ file, with its materials in an MTL file next to it.
+    // OBJ has no cameras, the camera is kept as a comment.
+    pub fn export_obj(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mtl_path = path.with_extension("mtl");
+        let (meshes, materials) = self.export_meshes();
+
+        let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+        for material in &materials {
+            write_material(&mut mtl, material, path, base)?;
+        }
+        mtl.flush()?;
+
+        let mut obj = BufWriter::new(File::create(path)?);
+        let camera = &self.camera;
+        writeln!(obj, "# camera origin {} {} {}", camera.origin.x, camera.origin.y, camera.origin.z)?;
+        writeln!(obj, "# camera direction {} {} {}", camera.direction.x, camera.direction.y, camera.direction.z)?;
+        writeln!(obj, "# camera up {} {} {}", camera.up.x, camera.up.y, camera.up.z)?;
+        writeln!(obj, "# camera fov {}", camera.fov)?;
+        writeln!(obj, "mtllib {}", mtl_path.file_name().unwrap_or_default().to_string_lossy())?;
+
+        // Indices are global to the file and start at 1.
+        let mut offset = 1;
+        for mesh in &meshes {
+            writeln!(obj, "o {}", mesh.name)?;
+            for (i, position) in mesh.positions.iter().enumerate() {
+                match mesh.colors.get(i) {
+                    Some(color) => writeln!(obj, "v {} {} {} {} {} {}", position.x, position.y, position.z, color.x, color.y, color.z)?,
+                    None => writeln!(obj, "v {} {} {}", position.x, position.y, position.z)?,
+                }
+            }
+            for uv in &mesh.uvs {
+                writeln!(obj, "vt {} {}", uv.x, uv.y)?;
+            }
+            for normal in &mesh.normals {
+                writeln!(obj, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+            }
+            writeln!(obj, "usemtl {}", materials[mesh.material].name)?;
+            for triangle in &mesh.triangles {
+                let [a, b, c] = triangle.map(|i| i + offset);
+                writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+            }
+            offset += mesh.positions.len();
+        }
+        obj.flush()
+    }
+}
+
+fn write_material(mtl: &mut impl Write, material: &ExportedMaterial, path: &Path, base: &Path) -> std::io::Result<()> {
+    let ExportedMaterial { name, diffuse, specular, emission, .. } = material;
+    writeln!(mtl, "newmtl {name}")?;
+    writeln!(mtl, "Kd {} {} {}", diffuse.x, diffuse.y, diffuse.z)?;
+    writeln!(mtl, "Ks {} {} {}", specular.x, specular.y, specular.z)?;
+    writeln!(mtl, "Ke {} {} {}", emission.x, emission.y, emission.z)?;
+    // Inverse of the roughness to Phong exponent conversion of the importer.
+    let roughness = material.roughness.max(1e-3);
+    writeln!(mtl, "Ns {}", (2.0 / (roughness * roughness) - 2.0).min(1000.0))?;
+    writeln!(mtl, "d {}", material.opacity)?;
+    writeln!(mtl, "Ni {}", material.refraction_index)?;
+    writeln!(mtl, "illum {}", if material.opacity < 1.0 { 4 } else if material.specular.max() > 0.0 { 2 } else { 1 })?;
+
+    for (statement, texture, suffix) in [("map_Kd", &material.diffuse_texture, "diffuse"), ("map_Ke", &material.emission_texture, "emission")] {
+        if let Some(texture) = texture {
+            let texture_path = texture_file(texture, &format!("{name}_{suffix}"), path, base)?;
+            writeln!(mtl, "{statement} {}", texture_path.display())?;
+        }
+    }
+    writeln!(mtl)
+}
+
+// Path of a texture relative to the OBJ file, embedded images are written next to it.
+fn texture_file(texture: &TextureSource, name: &str, path: &Path, base: &Path) -> std::io::Result<PathBuf> {
+    match texture {
+        TextureSource::File(texture_path) => Ok(relative_path(texture_path, base)),
+        TextureSource::Embedded(bytes) => {
+            let extension = image::guess_format(bytes).ok().and_then(|format| format.extensions_str().first().copied()).unwrap_or("png");
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let file_name = PathBuf::from(format!("{stem}_{name}.{extension}"));
+            std::fs::write(base.join(&file_name), bytes)?;
+            Ok(file_name)
+        }
+    }
+}
@@ -1,95 +1,183 @@
 use std::ops::{Index, IndexMut};
 use std::path::Path;
-use std::slice::from_raw_parts_mut;
 use itertools::Itertools;
 
 use crate::math::*;
 
+pub use ::image::ImageFormat;
+
 
 pub trait RenderTraget : Index<[usize;2]> + IndexMut<[usize;2]> {
     fn get_resolution(&self) -> (u32,u32);
 }
 
+// How colors brighter than 1 are brought back into the displayable range of 8 bits images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMapping {
+    #[default]
+    Clamp,
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+impl ToneMapping {
+    pub fn apply(&self, color: Vec3f) -> Vec3f {
+        match self {
+            ToneMapping::Clamp => color,
+            ToneMapping::Reinhard => color.map(|x| x / (1.0 + x)),
+            ToneMapping::Aces => color.map(|x| (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImageSaveOptions {
+    // Guessed from the extension of the file when `None`.
+    pub format: Option<ImageFormat>,
+    pub tone_mapping: ToneMapping,
+    // In stops, each one doubles the brightness.
+    pub exposure: f32,
+}
+
+// Colors by default, renders also keep e.g. the number of samples of each pixel in an `Image<u32>`.
 #[derive(Debug, Clone)]
-pub struct Image {
-    pub pixels : Vec<Vec3f>,
+pub struct Image<P = Vec3f> {
+    pub pixels : Vec<P>,
     pub width  : u32,
     pub height : u32
 }
 
-impl Image {
-    pub fn new(color : Vec3f, width : u32, height : u32) -> Image{
+impl<P: Copy> Image<P> {
+    pub fn new(color : P, width : u32, height : u32) -> Image<P>{
         Image {
-            pixels: (0..(width as usize)*(height as usize)).into_iter().map(|_| color).collect(),
+            pixels: vec![color; (width as usize)*(height as usize)],
             width,
             height
         }
     }
 
-    pub fn fill(&mut self, color: Vec3f) {
+    pub fn fill(&mut self, color: P) {
         self.pixels.fill(color);
     } 
 
-    pub fn save(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
-        let bytes: Vec<u8> = self.pixels.iter()
-                                        .map(|vec| vec.iter())
-                                        .flatten()
-                                        .map(|x| (x.powf(1.0/2.2) * 255.0) as u8) //Gamma correction
-                                        .collect();
-
-        image::save_buffer(path, &bytes, self.width as u32, self.height as u32, image::ColorType::Rgb8)?;
-        Ok(())
-    }
-
-    pub fn view(&mut self, offset_x: u32, offset_y: u32, width: u32, height: u32) -> ImageView {
+    pub fn view(&mut self, offset_x: u32, offset_y: u32, width: u32, height: u32) -> ImageView<'_, P> {
+        let (x, width) = (offset_x as usize, width as usize);
+        let rows = self.pixels.chunks_mut(self.width as usize)
+                              .skip(offset_y as usize)
+                              .take(height as usize)
+                              .map(|row| &mut row[x..x + width])
+                              .collect();
         ImageView {
-            source: &mut self.pixels,
+            rows,
             offset_x,
             offset_y,
-            width,
+            width: width as u32,
             height,
             source_width: self.width,
             source_height: self.height,
         }
     }
 
-    
-    
-    pub fn split_tiles(& mut self, width: u32, height: u32) -> TileIterator {
-        TileIterator {
-            tile_count_h: (self.height as f32 / height as f32).ceil() as u32,
-            tile_count_w: (self.width as f32 / width as f32).ceil() as u32,
-            width,
-            height,
-            i:0,
-            j:0,
-            source: self,
+    // Splits the image into tiles that each borrow their own part of every row they cover, so they can be
+    // written to from different threads.
+    pub fn split_tiles(&mut self, width: u32, height: u32) -> TileIterator<'_, P> {
+        let (source_width, source_height) = (self.width, self.height);
+        let mut tiles = Vec::new();
+        if self.pixels.is_empty() {
+            return TileIterator { tiles: tiles.into_iter() };
+        }
+
+        for (band_index, band) in self.pixels.chunks_mut((source_width * height) as usize).enumerate() {
+            let first = tiles.len();
+            for row in band.chunks_mut(source_width as usize) {
+                for (column, cells) in row.chunks_mut(width as usize).enumerate() {
+                    // The first row of a band creates its tiles.
+                    if first + column == tiles.len() {
+                        tiles.push(ImageView {
+                            rows: Vec::new(),
+                            offset_x: column as u32 * width,
+                            offset_y: band_index as u32 * height,
+                            width: cells.len() as u32,
+                            height: 0,
+                            source_width,
+                            source_height,
+                        });
+                    }
+                    let tile = &mut tiles[first + column];
+                    tile.rows.push(cells);
+                    tile.height += 1;
+                }
+            }
+        }
+        TileIterator { tiles: tiles.into_iter() }
+    }
+}
+
+impl Image {
+    pub fn save(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        self.save_with_options(path, &ImageSaveOptions::default())
+    }
+
+    // HDR formats (OpenEXR, Radiance) keep the linear colors, others are tone mapped and gamma corrected to 8 bits.
+    pub fn save_with_options(&self, path: impl AsRef<Path>, options: &ImageSaveOptions) -> image::ImageResult<()> {
+        let path = path.as_ref();
+        let format = match options.format {
+            Some(format) => format,
+            None => ImageFormat::from_path(path)?,
+        };
+        self.to_dynamic_image(format, options).save_with_format(path, format)
+    }
+
+    // The file `save_with_options` would write in `format`, e.g. to send it over the network.
+    pub fn encode(&self, format: ImageFormat, options: &ImageSaveOptions) -> image::ImageResult<Vec<u8>> {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        self.to_dynamic_image(format, options).write_to(&mut bytes, format)?;
+        Ok(bytes.into_inner())
+    }
+
+    fn to_dynamic_image(&self, format: ImageFormat, options: &ImageSaveOptions) -> image::DynamicImage {
+        let scale = options.exposure.exp2();
+
+        if matches!(format, ImageFormat::OpenExr | ImageFormat::Hdr) {
+            let floats: Vec<f32> = self.pixels.iter().flat_map(|color| (color * scale).into_iter().copied().collect::<Vec<_>>()).collect();
+            let buffer = image::Rgb32FImage::from_raw(self.width, self.height, floats).expect("the image has one color per pixel");
+            return buffer.into();
         }
+
+        let bytes: Vec<u8> = self.pixels.iter()
+                                        .map(|color| options.tone_mapping.apply(color * scale))
+                                        .flat_map(|color| color.into_iter().copied().collect::<Vec<_>>())
+                                        .map(|x| (x.powf(1.0/2.2) * 255.0) as u8) //Gamma correction
+                                        .collect();
+        image::RgbImage::from_raw(self.width, self.height, bytes).expect("the image has one color per pixel").into()
     }
 }
 
-impl RenderTraget for Image {
+impl<P> RenderTraget for Image<P> {
     fn get_resolution(&self) -> (u32,u32) {
         (self.width, self.height)
     }
 }
 
-impl Index<[usize;2]> for Image {
-    type Output = Vec3f;
+impl<P> Index<[usize;2]> for Image<P> {
+    type Output = P;
     fn index(&self, [i,j]: [usize;2]) -> &Self::Output {
         &self.pixels[i*(self.width as usize) + j]
     }
 }
 
-impl IndexMut<[usize;2]> for Image {
+impl<P> IndexMut<[usize;2]> for Image<P> {
     fn index_mut (&mut self, [i,j]: [usize;2]) -> &mut Self::Output {
         &mut self.pixels[i*(self.width as usize) + j]
     }
 }
 
+// A rectangle of an image, borrowing only its own pixels.
 #[derive(Debug)]
-pub struct ImageView<'a> {
-    pub source : &'a mut [Vec3f],
+pub struct ImageView<'a, P = Vec3f> {
+    // The pixels of each row of the rectangle, from the top.
+    rows: Vec<&'a mut [P]>,
     pub source_width: u32,
     pub source_height: u32,
     pub offset_x: u32,
@@ -98,83 +186,66 @@ pub struct ImageView<'a> {
     pub height : u32
 }
 
-impl<'a> RenderTraget for ImageView<'a> {
+impl<'a, P> RenderTraget for ImageView<'a, P> {
     fn get_resolution(&self) -> (u32,u32) {
         (self.width, self.height)
     }
 }
 
-impl<'a> Index<[usize;2]> for ImageView<'a> {
-    type Output = Vec3f;
+impl<'a, P> Index<[usize;2]> for ImageView<'a, P> {
+    type Output = P;
     fn index(&self, [i,j]: [usize;2]) -> &Self::Output {
-        assert!(i < self.height  as usize && j < self.width as usize, "index out of bound");
-        let x = self.offset_x as usize + j;
-        let y = (self.offset_y as usize + i)*(self.source_width as usize);
-        &self.source[x + y]
+        &self.rows[i][j]
     }
 }
 
-impl<'a> IndexMut<[usize;2]> for ImageView<'a> {
+impl<'a, P> IndexMut<[usize;2]> for ImageView<'a, P> {
     fn index_mut (&mut self, [i,j]: [usize;2]) -> &mut Self::Output {
-        assert!(i < self.height  as usize && j < self.width as usize, "index out of bound");
-        let x = self.offset_x as usize + j;
-        let y = (self.offset_y as usize + i)*(self.source_width as usize);
-        &mut self.source[x + y]
+        &mut self.rows[i][j]
     }
 }
 
-impl<'a> ImageView<'a> {
-    pub fn fill(&mut self, color: Vec3f) {
-        for i in 0..self.height as usize{
-            for j in 0..self.width as usize {
-                self[[i,j]] = color;
-            }
+impl<'a, P: Copy> ImageView<'a, P> {
+    pub fn fill(&mut self, color: P) {
+        for row in &mut self.rows {
+            row.fill(color);
         }
-    } 
+    }
+
+    // The part of the view inside a rectangle of the source image, if any.
+    pub fn clip(self, x: u32, y: u32, width: u32, height: u32) -> Option<ImageView<'a, P>> {
+        let left = self.offset_x.max(x);
+        let top = self.offset_y.max(y);
+        let right = (self.offset_x + self.width).min(x.saturating_add(width));
+        let bottom = (self.offset_y + self.height).min(y.saturating_add(height));
+        if left >= right || top >= bottom {
+            return None;
+        }
+
+        let columns = (left - self.offset_x) as usize..(right - self.offset_x) as usize;
+        let rows = self.rows.into_iter()
+                            .skip((top - self.offset_y) as usize)
+                            .take((bottom - top) as usize)
+                            .map(|row| &mut row[columns.clone()])
+                            .collect();
+        Some(ImageView { rows, offset_x: left, offset_y: top, width: right - left, height: bottom - top, ..self })
+    }
 }
 
 #[derive(Debug)]
-pub struct TileIterator<'a> {
-    source: &'a mut Image,
-    width: u32, 
-    height: u32,
-    tile_count_w: u32,
-    tile_count_h: u32,
-    i: u32,
-    j: u32
-}
-
-impl<'a> Iterator for TileIterator<'a> {
-    type Item = ImageView<'a>;
+pub struct TileIterator<'a, P = Vec3f> {
+    tiles: std::vec::IntoIter<ImageView<'a, P>>,
+}
+
+impl<'a, P> Iterator for TileIterator<'a, P> {
+    type Item = ImageView<'a, P>;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.j >= self.tile_count_w {
-            self.j = 0;
-            self.i += 1;
-        }
-        if self.i >= self.tile_count_h {
-            return None;
-        }
-        let ptr = self.source.pixels.as_mut_ptr();
-        let len = self.source.pixels.len();
-        let tile = unsafe {
-            let offset_x = self.j*self.width;
-            let offset_y = self.i*self.height;
-            Some(ImageView {
-                source: from_raw_parts_mut(ptr, len),
-                offset_x,
-                offset_y ,
-                width: self.width.min(self.source.width - offset_x),
-                height: self.height.min(self.source.height - offset_y),
-                source_width: self.source.width,
-                source_height: self.source.height,
-            })
-        };
-        self.j += 1;
-        tile
+        self.tiles.next()
     }
 }
 
 
+#[cfg(test)]
 mod test {
     use super::*;
     #[test]
@@ -195,4 +266,29 @@ mod test {
         Vec3f::new(1.0, 1.0, 0.0),
         Vec3f::new(0.0, 0.0, 1.0),]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_split_uneven_tiles() {
+        let mut image = Image::new(Vec3f::zeros(), 5, 3);
+        let tiles: Vec<_> = image.split_tiles(2, 2).collect();
+        let sizes: Vec<_> = tiles.iter().map(|tile| (tile.offset_x, tile.offset_y, tile.width, tile.height)).collect();
+        assert_eq!(sizes, [(0, 0, 2, 2), (2, 0, 2, 2), (4, 0, 1, 2), (0, 2, 2, 1), (2, 2, 2, 1), (4, 2, 1, 1)]);
+
+        // Tiles can be filled from several threads at once, each one only sees its own pixels.
+        std::thread::scope(|scope| {
+            for (index, mut tile) in tiles.into_iter().enumerate() {
+                scope.spawn(move || tile.fill(Vec3f::repeat(index as f32)));
+            }
+        });
+        let indices: Vec<_> = image.pixels.iter().map(|pixel| pixel.x as u32).collect();
+        assert_eq!(indices, [0, 0, 1, 1, 2,
+                             0, 0, 1, 1, 2,
+                             3, 3, 4, 4, 5]);
+
+        let mut clipped = image.view(0, 0, 5, 3).clip(1, 1, 3, 5).unwrap();
+        assert_eq!((clipped.offset_x, clipped.offset_y, clipped.width, clipped.height), (1, 1, 3, 2));
+        clipped[[1, 2]] = Vec3f::repeat(9.0);
+        assert_eq!(image[[2, 3]], Vec3f::repeat(9.0));
+        assert!(image.view(0, 0, 2, 2).clip(3, 0, 1, 1).is_none());
+    }
+}
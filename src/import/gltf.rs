@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ::gltf::buffer::{self, Data};
+use ::gltf::camera::Projection;
+use ::gltf::material::AlphaMode;
+use ::gltf::mesh::Mode;
+
+use crate::import::{Import, ImportError, ImportedObject};
+use crate::loaders::{compute_normals, remap_colors, Normals};
+use crate::{Camera, Lambertian, Mat4f, Material, Mesh, MeshLoadError, Standard, Texture, Vec2f, Vec3f, Vec4f, Vertex};
+
+impl Import {
+    // Reads a `.gltf` or `.glb` file. The node hierarchy of the default scene is flattened into one object per
+    // primitive, metallic-roughness materials become `Standard` materials and the first camera found is kept.
+    // Primitives that aren't made of triangles are skipped.
+    pub fn load_gltf(path: impl AsRef<Path>) -> Result<Import, ImportError> {
+        let path = path.as_ref();
+        let ::gltf::Gltf { document, blob } = ::gltf::Gltf::open(path)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        let buffers = ::gltf::import_buffers(&document, Some(base), blob)?;
+
+        let mut import = Import::default();
+        let mut textures = TextureCache { path, base, buffers: &buffers, textures: HashMap::new() };
+        for material in document.materials() {
+            let name = material.name().map(str::to_string).unwrap_or_else(|| format!("material{}", import.materials.len()));
+            import.materials.push((name, translate_material(&material, &mut textures)?));
+        }
+
+        // Each glTF mesh is a list of primitives, imported as one `Mesh` each along with their material.
+        let mut primitives = Vec::new();
+        let mut default_material = None;
+        for mesh in document.meshes() {
+            let mut mesh_primitives = Vec::new();
+            for primitive in mesh.primitives().filter(|primitive| primitive.mode() == Mode::Triangles) {
+                let name = match (mesh.name(), mesh.primitives().len()) {
+                    (Some(name), 1) => name.to_string(),
+                    (Some(name), _) => format!("{name}.{}", primitive.index()),
+                    (None, _) => format!("mesh{}.{}", mesh.index(), primitive.index()),
+                };
+                let converted = convert_primitive(&primitive, &buffers).map_err(|error| ImportError::Mesh { name: name.clone(), error })?;
+                import.meshes.push((name, converted));
+
+                let material = match primitive.material().index() {
+                    Some(index) => index,
+                    None => *default_material.get_or_insert_with(|| {
+                        import.materials.push(("default".to_string(), Box::new(Lambertian { color: Vec3f::repeat(0.8), emission: Vec3f::zeros() }) as Box<dyn Material>));
+                        import.materials.len() - 1
+                    }),
+                };
+                mesh_primitives.push((import.meshes.len() - 1, material));
+            }
+            primitives.push(mesh_primitives);
+        }
+
+        let scene = document.default_scene().or_else(|| document.scenes().next());
+        for node in scene.iter().flat_map(|scene| scene.nodes()) {
+            add_node(&node, &Mat4f::identity(), &primitives, &mut import);
+        }
+
+        Ok(import)
+    }
+}
+
+fn add_node(node: &::gltf::Node, parent: &Mat4f, primitives: &[Vec<(usize, usize)>], import: &mut Import) {
+    let transform = parent * Mat4f::from(node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        let name = node.name().map(str::to_string).unwrap_or_else(|| format!("node{}", node.index()));
+        for (i, &(mesh, material)) in primitives[mesh.index()].iter().enumerate() {
+            let name = if i == 0 { name.clone() } else { format!("{name}.{i}") };
+            import.objects.push(ImportedObject { name, mesh, material, transform });
+        }
+    }
+
+    if let (Some(camera), None) = (node.camera(), &import.camera) {
+        if let Projection::Perspective(perspective) = camera.projection() {
+            // glTF cameras look towards -z with y up.
+            let origin = (transform * Vec4f::new(0.0, 0.0, 0.0, 1.0)).xyz();
+            let direction = (transform * Vec4f::new(0.0, 0.0, -1.0, 0.0)).xyz();
+            let up = (transform * Vec4f::new(0.0, 1.0, 0.0, 0.0)).xyz();
+            let mut camera = Camera::new(origin, direction, up, 1.0);
+            camera.set_vertical_fov(perspective.yfov(), perspective.aspect_ratio().unwrap_or(1.0));
+            import.camera = Some(camera);
+        }
+    }
+
+    for child in node.children() {
+        add_node(&child, &transform, primitives, import);
+    }
+}
+
+fn convert_primitive(primitive: &::gltf::Primitive, buffers: &[Data]) -> Result<Mesh, MeshLoadError> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+    let positions: Vec<[f32;3]> = reader.read_positions()
+                                        .ok_or_else(|| MeshLoadError::InvalidGltf("a primitive has no positions".to_string()))?
+                                        .collect();
+    let normals: Option<Vec<[f32;3]>> = reader.read_normals().map(Iterator::collect);
+    let uvs: Option<Vec<[f32;2]>> = reader.read_tex_coords(0).map(|uvs| uvs.into_f32().collect());
+    let colors: Vec<Vec3f> = reader.read_colors(0).map(|colors| colors.into_rgb_f32().map(Vec3f::from).collect()).unwrap_or_default();
+
+    let vertices: Vec<_> = positions.iter().enumerate().map(|(i, &position)| Vertex {
+        position: position.into(),
+        normal: normals.as_ref().and_then(|normals| normals.get(i)).map(|&normal| normal.into()).unwrap_or(Vec3f::zeros()),
+        // glTF puts the origin of uvs at the top of the image.
+        uv_coord: uvs.as_ref().and_then(|uvs| uvs.get(i)).map(|&[u, v]| Vec2f::new(u, 1.0 - v)).unwrap_or(Vec2f::zeros()),
+    }).collect();
+
+    let indices: Vec<usize> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().map(|index| index as usize).collect(),
+        None => (0..vertices.len()).collect(),
+    };
+    if let Some(index) = indices.iter().find(|&&index| index >= vertices.len()) {
+        return Err(MeshLoadError::InvalidGltf(format!("vertex index {index} is out of range, there are {} vertices", vertices.len())));
+    }
+    let triangles: Vec<[usize;3]> = indices.chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect();
+
+    let (vertices, triangles, colors) = if normals.is_some() {
+        (vertices, triangles, colors)
+    } else {
+        let (new_vertices, new_triangles) = compute_normals(&vertices, &triangles, Normals::default());
+        let colors = if colors.len() == vertices.len() { remap_colors(&colors, &triangles, &new_triangles, new_vertices.len()) } else { Vec::new() };
+        (new_vertices, new_triangles, colors)
+    };
+
+    let mut mesh = Mesh::new(vertices, triangles);
+    if colors.len() == mesh.vertices.len() {
+        mesh.colors = colors;
+    }
+    Ok(mesh)
+}
+
+// Textures are shared between materials, and files referenced by path stay references when the scene is saved.
+struct TextureCache<'a> {
+    path: &'a Path,
+    base: &'a Path,
+    buffers: &'a [Data],
+    textures: HashMap<(usize, bool, Option<usize>), Texture>,
+}
+
+impl TextureCache<'_> {
+    fn get(&mut self, texture: Option<::gltf::texture::Info>, srgb: bool, channel: Option<usize>) -> Result<Option<Texture>, ImportError> {
+        let Some(image) = texture.map(|info| info.texture().source()) else { return Ok(None) };
+        let key = (image.index(), srgb, channel);
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(Some(texture.clone()));
+        }
+
+        let error_path = || PathBuf::from(format!("{}#image{}", self.path.display(), image.index()));
+        let texture = match image.source() {
+            ::gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => {
+                let path = self.base.join(uri);
+                Texture::load(&path, srgb).map_err(|error| ImportError::Texture { path, error })?
+            }
+            ::gltf::image::Source::Uri { uri, .. } => {
+                let Data(bytes) = Data::from_source(buffer::Source::Uri(uri), None)?;
+                Texture::decode(bytes, srgb).map_err(|error| ImportError::Texture { path: error_path(), error })?
+            }
+            ::gltf::image::Source::View { view, .. } => {
+                let bytes = self.buffers[view.buffer().index()][view.offset()..view.offset() + view.length()].to_vec();
+                Texture::decode(bytes, srgb).map_err(|error| ImportError::Texture { path: error_path(), error })?
+            }
+        };
+        let texture = match channel {
+            Some(channel) => texture.with_channel(channel),
+            None => texture,
+        };
+
+        self.textures.insert(key, texture.clone());
+        Ok(Some(texture))
+    }
+}
+
+fn translate_material(material: &::gltf::Material, textures: &mut TextureCache) -> Result<Box<dyn Material>, ImportError> {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, alpha] = pbr.base_color_factor();
+    let base_color = Vec3f::new(r, g, b);
+    let base_texture = textures.get(pbr.base_color_texture(), true, None)?;
+    let metallic = pbr.metallic_factor();
+
+    // Dielectrics reflect about 4% of the light, metals reflect with their base color.
+    let specular = Vec3f::repeat(0.04).lerp(&base_color, metallic);
+    let specular_texture = if metallic >= 0.5 { base_texture.clone() } else { None };
+
+    let opacity = match material.alpha_mode() {
+        AlphaMode::Blend => alpha,
+        _ => 1.0,
+    } * (1.0 - material.transmission().map(|transmission| transmission.transmission_factor()).unwrap_or(0.0));
+
+    Ok(Box::new(Standard {
+        diffuse: base_color * (1.0 - metallic),
+        diffuse_texture: base_texture,
+        specular,
+        specular_texture,
+        // glTF roughness is perceptual, it is squared like in the glTF BRDF.
+        roughness: pbr.roughness_factor().powi(2),
+        // Roughness is in the green channel of metallic-roughness textures.
+        roughness_texture: textures.get(pbr.metallic_roughness_texture(), false, Some(1))?,
+        glossiness_texture: None,
+        opacity,
+        opacity_texture: None,
+        refraction_index: material.ior().unwrap_or(1.5),
+        emission: Vec3f::from(material.emissive_factor()) * material.emissive_strength().unwrap_or(1.0),
+        emission_texture: textures.get(material.emissive_texture(), true, None)?,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use base64::Engine;
+
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::{ConstantEnvironment, Scene};
+
+    #[test]
+    fn test_import_gltf() {
+        let positions: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter().flat_map(|x| x.to_le_bytes()).collect();
+        let buffer = base64::engine::general_purpose::STANDARD.encode(&positions);
+        let gltf = serde_json::json!({
+            "asset": {"version": "2.0"},
+            "scene": 0,
+            "scenes": [{"nodes": [0, 2]}],
+            "nodes": [
+                {"name": "parent", "translation": [0.0, 0.0, -5.0], "children": [1]},
+                {"name": "triangle", "mesh": 0, "scale": [2.0, 2.0, 2.0]},
+                {"camera": 0, "translation": [0.0, 1.0, 0.0]}
+            ],
+            "cameras": [{"type": "perspective", "perspective": {"yfov": 0.8, "aspectRatio": 1.5, "znear": 0.1}}],
+            "meshes": [{"name": "triangle", "primitives": [{"attributes": {"POSITION": 0}, "material": 0}]}],
+            "materials": [{"name": "gold", "pbrMetallicRoughness": {"baseColorFactor": [1.0, 0.8, 0.2, 1.0], "metallicFactor": 1.0, "roughnessFactor": 0.5}}],
+            "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]}],
+            "bufferViews": [{"buffer": 0, "byteLength": 36}],
+            "buffers": [{"byteLength": 36, "uri": format!("data:application/octet-stream;base64,{buffer}")}]
+        });
+
+        let directory = TempDir::new("import_gltf");
+        let path = directory.join("scene.gltf");
+        std::fs::write(&path, gltf.to_string()).unwrap();
+
+        let import = Import::load_gltf(&path).unwrap();
+        assert_eq!(import.meshes.len(), 1);
+        assert_eq!(import.meshes[0].1.vertices.len(), 3);
+        assert_eq!(import.objects[0].name, "triangle");
+        let corner = import.objects[0].transform * Vec4f::new(1.0, 0.0, 0.0, 1.0);
+        assert_eq!(corner, Vec4f::new(2.0, 0.0, -5.0, 1.0));
+
+        let material = serde_json::to_value(&import.materials[0].1).unwrap();
+        assert_eq!(material["type"], "Standard");
+        assert_eq!(material["diffuse"], serde_json::json!([0.0, 0.0, 0.0]));
+        assert_eq!(material["roughness"], 0.25);
+
+        let camera = import.camera.clone().unwrap();
+        assert_eq!(camera.origin, Vec3f::y());
+        assert_eq!(camera.direction, -Vec3f::z());
+        assert!((camera.vertical_fov(1.5) - 0.8).abs() < 1e-5);
+
+        let mut scene = Scene::new(Camera::new(Vec3f::zeros(), Vec3f::x(), Vec3f::y(), 1.0),
+            Box::new(ConstantEnvironment { color: Vec3f::zeros() }));
+        scene.add_import(import, &Mat4f::identity()).unwrap();
+        assert_eq!(scene.camera.direction, -Vec3f::z());
+    }
+}
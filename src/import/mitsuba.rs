@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use roxmltree::{Document, Node};
+
+use crate::import::{Import, ImportError, ImportedObject};
+use crate::import::shapes::{cube, rectangle, sphere};
+use crate::{Camera, ConstantEnvironment, Dialectric, Lambertian, Mat4f, Material, Mesh, Metal, Standard, Texture, Vec3f, Vec4f};
+
+impl Import {
+    // Reads Mitsuba 3 XML scenes: obj, ply, rectangle, sphere and cube shapes, diffuse, conductor, roughconductor and
+    // dielectric BSDFs, area and constant emitters and perspective sensors, with `<default>` values substituted in `$name`s.
+    // Anything else is skipped with a warning in `warnings`.
+    pub fn load_mitsuba(path: impl AsRef<Path>) -> Result<Import, ImportError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let document = Document::parse(&text).map_err(|error| {
+            let position = error.pos();
+            ImportError::Mitsuba { path: path.to_path_buf(), line: position.row as usize, message: error.to_string() }
+        })?;
+
+        let mut parser = Parser {
+            path,
+            document: &document,
+            directory: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            defaults: HashMap::new(),
+            import: Import::default(),
+            materials: HashMap::new(),
+            default_material: None,
+        };
+        // Without emitters around the scene, everything outside of it is black.
+        parser.import.environment = Some(Box::new(ConstantEnvironment { color: Vec3f::zeros() }));
+
+        for node in document.root_element().children().filter(Node::is_element) {
+            parser.read(node)?;
+        }
+        Ok(parser.import)
+    }
+}
+
+struct Parser<'a, 'input> {
+    path: &'a Path,
+    document: &'a Document<'input>,
+    directory: PathBuf,
+    defaults: HashMap<String, String>,
+    import: Import,
+    // BSDFs declared with an id, with the reflectance area emitters reuse.
+    materials: HashMap<String, (usize, Vec3f)>,
+    default_material: Option<(usize, Vec3f)>,
+}
+
+impl<'a, 'input> Parser<'a, 'input> {
+    fn error(&self, node: Node, message: String) -> ImportError {
+        let line = self.document.text_pos_at(node.range().start).row as usize;
+        ImportError::Mitsuba { path: self.path.to_path_buf(), line, message }
+    }
+
+    fn warn(&mut self, node: Node, message: String) {
+        let line = self.document.text_pos_at(node.range().start).row;
+        self.import.warnings.push(format!("{}:{line}: {message}", self.path.display()));
+    }
+
+    // An attribute with the `$name` of defaults replaced by their value.
+    fn attribute(&self, node: Node, name: &str) -> Option<String> {
+        let mut value = node.attribute(name)?.to_string();
+        // Longer names first so that `$spp` does not replace the start of `$spp_max`.
+        let mut defaults: Vec<_> = self.defaults.iter().collect();
+        defaults.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+        for (name, default) in defaults {
+            value = value.replace(&format!("${name}"), default);
+        }
+        Some(value)
+    }
+
+    // The child property element named `name`, such as `<float name="radius" value="2"/>`.
+    fn property<'n>(&self, node: Node<'n, 'input>, name: &str) -> Option<Node<'n, 'input>> {
+        node.children().filter(Node::is_element).find(|child| child.attribute("name") == Some(name))
+    }
+
+    fn float(&self, node: Node, name: &str, default: f32) -> Result<f32, ImportError> {
+        let Some(property) = self.property(node, name) else { return Ok(default) };
+        let value = self.attribute(property, "value").unwrap_or_default();
+        value.trim().parse().map_err(|_| self.error(property, format!("invalid number {value:?} for {name:?}")))
+    }
+
+    fn string(&self, node: Node, name: &str) -> Option<String> {
+        self.attribute(self.property(node, name)?, "value")
+    }
+
+    fn numbers(&self, node: Node, attribute: &str) -> Result<Vec<f32>, ImportError> {
+        let value = self.attribute(node, attribute).unwrap_or_default();
+        value.split(|c: char| c == ',' || c.is_whitespace()).filter(|word| !word.is_empty())
+             .map(|word| word.parse().map_err(|_| self.error(node, format!("invalid number {word:?}"))))
+             .collect()
+    }
+
+    fn vector(&self, node: Node, attribute: &str, default: Vec3f) -> Result<Vec3f, ImportError> {
+        if node.attribute(attribute).is_none() {
+            return Ok(default);
+        }
+        match *self.numbers(node, attribute)?.as_slice() {
+            [x] => Ok(Vec3f::repeat(x)),
+            [x, y, z] => Ok(Vec3f::new(x, y, z)),
+            _ => Err(self.error(node, format!("expected 3 numbers in {attribute:?}"))),
+        }
+    }
+
+    // `<rgb>`, `<float>` and single valued `<spectrum>` colors, anything else is replaced by `default` with a warning.
+    fn color(&mut self, node: Node, name: &str, default: Vec3f) -> Result<Vec3f, ImportError> {
+        let Some(property) = self.property(node, name) else { return Ok(default) };
+        let numbers = self.numbers(property, "value");
+        match (property.tag_name().name(), numbers.as_deref()) {
+            ("rgb" | "float" | "spectrum", Ok(&[x])) => Ok(Vec3f::repeat(x)),
+            ("rgb", Ok(&[r, g, b])) => Ok(Vec3f::new(r, g, b)),
+            (tag, _) => {
+                self.warn(property, format!("unsupported {tag} value for {name:?}"));
+                Ok(default)
+            }
+        }
+    }
+
+    // The `to_world` transform of an element, its operations apply in order.
+    fn transform(&self, node: Node) -> Result<Mat4f, ImportError> {
+        let mut transform = Mat4f::identity();
+        let Some(property) = self.property(node, "to_world") else { return Ok(transform) };
+        for operation in property.children().filter(Node::is_element) {
+            let xyz = |default: f32| -> Result<Vec3f, ImportError> {
+                if operation.attribute("value").is_some() {
+                    return self.vector(operation, "value", Vec3f::repeat(default));
+                }
+                let [x, y, z] = ["x", "y", "z"].map(|axis| self.attribute(operation, axis).map(|value| value.trim().parse::<f32>()));
+                let component = |value: Option<Result<f32, _>>| value.unwrap_or(Ok(default)).map_err(|_| self.error(operation, "invalid number".to_string()));
+                Ok(Vec3f::new(component(x)?, component(y)?, component(z)?))
+            };
+            let matrix = match operation.tag_name().name() {
+                "translate" => Mat4f::new_translation(&xyz(0.0)?),
+                "scale" => Mat4f::new_nonuniform_scaling(&xyz(1.0)?),
+                "rotate" => {
+                    let angle = self.attribute(operation, "angle").unwrap_or_default().trim().parse::<f32>()
+                                    .map_err(|_| self.error(operation, "rotate needs an angle".to_string()))?;
+                    Mat4f::from_scaled_axis(xyz(0.0)?.normalize() * angle.to_radians())
+                }
+                // Mitsuba matrices are given row by row.
+                "matrix" => {
+                    let numbers = self.numbers(operation, "value")?;
+                    if numbers.len() != 16 {
+                        return Err(self.error(operation, "a matrix needs 16 numbers".to_string()));
+                    }
+                    Mat4f::from_row_slice(&numbers)
+                }
+                "lookat" => {
+                    let origin = self.vector(operation, "origin", Vec3f::zeros())?;
+                    let target = self.vector(operation, "target", Vec3f::z())?;
+                    let up = self.vector(operation, "up", Vec3f::y())?;
+                    let direction = (target - origin).normalize();
+                    let left = up.cross(&direction).normalize();
+                    let up = direction.cross(&left);
+                    Mat4f::new(left.x, up.x, direction.x, origin.x,
+                               left.y, up.y, direction.y, origin.y,
+                               left.z, up.z, direction.z, origin.z,
+                               0.0, 0.0, 0.0, 1.0)
+                }
+                name => return Err(self.error(operation, format!("unknown transform {name:?}"))),
+            };
+            transform = matrix * transform;
+        }
+        Ok(transform)
+    }
+
+    fn read(&mut self, node: Node) -> Result<(), ImportError> {
+        let kind = self.attribute(node, "type").unwrap_or_default();
+        match node.tag_name().name() {
+            "default" => {
+                let (Some(name), Some(value)) = (node.attribute("name"), node.attribute("value")) else {
+                    return Err(self.error(node, "<default> needs a name and a value".to_string()));
+                };
+                self.defaults.insert(name.to_string(), value.to_string());
+            }
+            "bsdf" => {
+                let material = self.read_bsdf(node)?;
+                match node.attribute("id") {
+                    Some(id) => { self.materials.insert(id.to_string(), material); }
+                    None => self.warn(node, "BSDF without an id".to_string()),
+                }
+            }
+            "sensor" => self.read_sensor(node)?,
+            "shape" => self.read_shape(node)?,
+            "emitter" => match kind.as_str() {
+                "constant" => {
+                    let radiance = self.color(node, "radiance", Vec3f::repeat(1.0))?;
+                    self.import.environment = Some(Box::new(ConstantEnvironment { color: radiance }));
+                }
+                "envmap" => {
+                    let scale = self.float(node, "scale", 1.0)?;
+                    self.import.environment = Some(Box::new(ConstantEnvironment { color: Vec3f::repeat(scale) }));
+                    self.warn(node, "environment maps are not supported, using a constant environment".to_string());
+                }
+                _ => self.warn(node, format!("unsupported emitter {kind:?}")),
+            },
+            "integrator" => {}
+            name => self.warn(node, format!("unsupported element <{name}>")),
+        }
+        Ok(())
+    }
+
+    fn read_sensor(&mut self, node: Node) -> Result<(), ImportError> {
+        let kind = self.attribute(node, "type").unwrap_or_default();
+        if kind != "perspective" && kind != "thinlens" {
+            self.warn(node, format!("unsupported sensor {kind:?}, using a perspective camera"));
+        }
+
+        let film = node.children().find(|child| child.has_tag_name("film"));
+        let (width, height) = match film {
+            Some(film) => (self.float(film, "width", 768.0)?, self.float(film, "height", 576.0)?),
+            None => (768.0, 576.0),
+        };
+        let aspect_ratio = width / height;
+
+        let transform = self.transform(node)?;
+        let origin = (transform * Vec4f::new(0.0, 0.0, 0.0, 1.0)).xyz();
+        let direction = (transform * Vec4f::new(0.0, 0.0, 1.0, 0.0)).xyz();
+        let up = (transform * Vec4f::new(0.0, 1.0, 0.0, 0.0)).xyz();
+        let mut camera = Camera::new(origin, direction, up, 1.0);
+
+        // The field of view is horizontal by default.
+        let fov = self.float(node, "fov", 45.0)?.to_radians();
+        let horizontal_to_vertical = |fov: f32| 2.0 * ((fov / 2.0).tan() / aspect_ratio).atan();
+        let vertical_fov = match self.string(node, "fov_axis").as_deref().unwrap_or("x") {
+            "y" => fov,
+            "smaller" if aspect_ratio < 1.0 => horizontal_to_vertical(fov),
+            "smaller" => fov,
+            "larger" if aspect_ratio < 1.0 => fov,
+            "diagonal" => 2.0 * ((fov / 2.0).tan() / (1.0 + aspect_ratio * aspect_ratio).sqrt()).atan(),
+            _ => horizontal_to_vertical(fov),
+        };
+        camera.set_vertical_fov(vertical_fov, aspect_ratio);
+        self.import.camera = Some(camera);
+        Ok(())
+    }
+
+    // Adds the material of a BSDF, returning its index and the reflectance area emitters attached to it keep.
+    fn read_bsdf(&mut self, node: Node) -> Result<(usize, Vec3f), ImportError> {
+        let kind = self.attribute(node, "type").unwrap_or_default();
+        // Two sided BSDFs wrap the actual one, our materials are all two sided.
+        if kind == "twosided" {
+            let Some(inner) = node.children().find(|child| child.has_tag_name("bsdf")) else {
+                return Err(self.error(node, "twosided BSDF without a nested BSDF".to_string()));
+            };
+            let material = self.read_bsdf(inner)?;
+            if let Some(id) = node.attribute("id") {
+                self.import.materials[material.0].0 = id.to_string();
+            }
+            return Ok(material);
+        }
+
+        let (material, albedo): (Box<dyn Material>, Vec3f) = match kind.as_str() {
+            "diffuse" => {
+                let texture = node.children().find(|child| child.has_tag_name("texture") && child.attribute("name") == Some("reflectance"));
+                match texture {
+                    Some(texture) => self.read_textured_diffuse(texture)?,
+                    None => {
+                        let reflectance = self.color(node, "reflectance", Vec3f::repeat(0.5))?;
+                        (Box::new(Lambertian { color: reflectance, emission: Vec3f::zeros() }), reflectance)
+                    }
+                }
+            }
+            "conductor" | "roughconductor" => {
+                // Without spectral data, conductors use the color of their metal.
+                let name = self.string(node, "material").unwrap_or_else(|| "none".to_string());
+                let color = match metal_color(&name) {
+                    Some(color) => color,
+                    None => {
+                        self.warn(node, format!("unknown conductor {name:?}, using a mirror"));
+                        Vec3f::repeat(1.0)
+                    }
+                };
+                let color = color.component_mul(&self.color(node, "specular_reflectance", Vec3f::repeat(1.0))?);
+                let roughness = if kind == "roughconductor" { self.float(node, "alpha", 0.1)? } else { 0.0 };
+                (Box::new(Metal { color, roughness }), color)
+            }
+            "dielectric" | "thindielectric" | "roughdielectric" => {
+                let int_ior = self.ior(node, "int_ior", 1.5046)?;
+                let ext_ior = self.ior(node, "ext_ior", 1.000277)?;
+                (Box::new(Dialectric { refraction_index: int_ior / ext_ior }), Vec3f::zeros())
+            }
+            _ => {
+                self.warn(node, format!("unsupported BSDF {kind:?}, using a diffuse material"));
+                let reflectance = self.color(node, "reflectance", Vec3f::repeat(0.5))?;
+                (Box::new(Lambertian { color: reflectance, emission: Vec3f::zeros() }), reflectance)
+            }
+        };
+
+        let name = node.attribute("id").map(str::to_string).unwrap_or_else(|| format!("material{}", self.import.materials.len()));
+        self.import.materials.push((name, material));
+        Ok((self.import.materials.len() - 1, albedo))
+    }
+
+    fn read_textured_diffuse(&mut self, texture: Node) -> Result<(Box<dyn Material>, Vec3f), ImportError> {
+        let kind = self.attribute(texture, "type").unwrap_or_default();
+        let Some(file) = self.string(texture, "filename").filter(|_| kind == "bitmap") else {
+            self.warn(texture, format!("unsupported texture {kind:?}"));
+            return Ok((Box::new(Lambertian { color: Vec3f::repeat(0.5), emission: Vec3f::zeros() }), Vec3f::repeat(0.5)));
+        };
+        let path = self.directory.join(file);
+        let texture = Texture::load(&path, true).map_err(|error| ImportError::Texture { path, error })?;
+        let material = Standard { diffuse: Vec3f::repeat(1.0), diffuse_texture: Some(texture), ..Standard::default() };
+        Ok((Box::new(material), Vec3f::repeat(0.5)))
+    }
+
+    // Indices of refraction are numbers or names of common media.
+    fn ior(&self, node: Node, name: &str, default: f32) -> Result<f32, ImportError> {
+        let Some(value) = self.string(node, name) else { return Ok(default) };
+        if let Ok(ior) = value.trim().parse() {
+            return Ok(ior);
+        }
+        let ior = match value.as_str() {
+            "vacuum" => 1.0,
+            "air" => 1.000277,
+            "water" => 1.3330,
+            "acrylic glass" | "polypropylene" => 1.49,
+            "bk7" => 1.5046,
+            "fused quartz" => 1.458,
+            "sodium chloride" => 1.544,
+            "diamond" => 2.419,
+            _ => return Err(self.error(node, format!("unknown index of refraction {value:?}"))),
+        };
+        Ok(ior)
+    }
+
+    fn read_shape(&mut self, node: Node) -> Result<(), ImportError> {
+        let kind = self.attribute(node, "type").unwrap_or_default();
+        let mut transform = self.transform(node)?;
+        let mesh = match kind.as_str() {
+            "obj" | "ply" => {
+                let Some(file) = self.string(node, "filename") else {
+                    return Err(self.error(node, format!("{kind} shape without a filename")));
+                };
+                let path = self.directory.join(&file);
+                let mesh = if kind == "obj" { Mesh::load_obj(&path) } else { Mesh::load_ply(&path) };
+                mesh.map_err(|error| ImportError::Mesh { name: file, error })?
+            }
+            "rectangle" => rectangle(),
+            "cube" => cube(),
+            "sphere" => {
+                let center = match self.property(node, "center") {
+                    Some(center) => self.point(center)?,
+                    None => Vec3f::zeros(),
+                };
+                transform *= Mat4f::new_translation(&center);
+                sphere(self.float(node, "radius", 1.0)?)
+            }
+            _ => {
+                self.warn(node, format!("unsupported shape {kind:?}"));
+                return Ok(());
+            }
+        };
+
+        let mut material = None;
+        let mut emission = None;
+        for child in node.children().filter(Node::is_element) {
+            match child.tag_name().name() {
+                "bsdf" => material = Some(self.read_bsdf(child)?),
+                "ref" => {
+                    let id = self.attribute(child, "id").unwrap_or_default();
+                    material = Some(*self.materials.get(&id).ok_or_else(|| self.error(child, format!("unknown BSDF {id:?}")))?);
+                }
+                "emitter" if child.attribute("type") == Some("area") => emission = Some(self.color(child, "radiance", Vec3f::repeat(1.0))?),
+                "emitter" => self.warn(child, format!("unsupported emitter {:?}", child.attribute("type").unwrap_or(""))),
+                _ => {}
+            }
+        }
+
+        let material = match (emission, material) {
+            (Some(emission), material) => {
+                let color = material.map(|(_, albedo)| albedo).unwrap_or(Vec3f::repeat(0.5));
+                self.import.materials.push((format!("emitter{}", self.import.materials.len()), Box::new(Lambertian { color, emission })));
+                self.import.materials.len() - 1
+            }
+            (None, Some((material, _))) => material,
+            (None, None) => {
+                let import = &mut self.import;
+                self.default_material.get_or_insert_with(|| {
+                    import.materials.push(("default".to_string(), Box::new(Lambertian { color: Vec3f::repeat(0.5), emission: Vec3f::zeros() })));
+                    (import.materials.len() - 1, Vec3f::repeat(0.5))
+                }).0
+            }
+        };
+
+        let name = node.attribute("id").map(str::to_string).unwrap_or_else(|| format!("{kind}{}", self.import.objects.len()));
+        self.import.meshes.push((name.clone(), mesh));
+        self.import.objects.push(ImportedObject { name, mesh: self.import.meshes.len() - 1, material, transform });
+        Ok(())
+    }
+
+    // A `<point>` given either as a `value` or with x, y and z attributes.
+    fn point(&self, node: Node) -> Result<Vec3f, ImportError> {
+        if node.attribute("value").is_some() {
+            return self.vector(node, "value", Vec3f::zeros());
+        }
+        let [x, y, z] = ["x", "y", "z"].map(|axis| self.attribute(node, axis).unwrap_or_else(|| "0".to_string()));
+        let parse = |value: &str| value.trim().parse::<f32>().map_err(|_| self.error(node, format!("invalid number {value:?}")));
+        Ok(Vec3f::new(parse(&x)?, parse(&y)?, parse(&z)?))
+    }
+}
+
+// Approximate colors of Mitsuba's conductors, `none` is a perfect mirror.
+fn metal_color(name: &str) -> Option<Vec3f> {
+    let [r, g, b] = match name {
+        "none" => [1.0, 1.0, 1.0],
+        "Ag" => [0.97, 0.96, 0.91],
+        "Al" => [0.91, 0.92, 0.92],
+        "Au" => [1.0, 0.78, 0.34],
+        "Cr" => [0.55, 0.56, 0.55],
+        "Cu" => [0.95, 0.64, 0.54],
+        "Fe" => [0.56, 0.57, 0.58],
+        "Ni" => [0.66, 0.61, 0.53],
+        "Ti" => [0.54, 0.50, 0.45],
+        _ => return None,
+    };
+    Some(Vec3f::new(r, g, b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_import_mitsuba() {
+        let directory = TempDir::new("import_mitsuba");
+        let path = directory.join("scene.xml");
+        std::fs::write(&path, r#"<scene version="3.0.0">
+            <default name="radius" value="0.5"/>
+            <integrator type="path"/>
+            <sensor type="perspective">
+                <float name="fov" value="60"/>
+                <transform name="to_world">
+                    <lookat origin="0, 0, 5" target="0, 0, 0" up="0, 1, 0"/>
+                </transform>
+                <film type="hdrfilm">
+                    <integer name="width" value="200"/>
+                    <integer name="height" value="100"/>
+                </film>
+            </sensor>
+            <bsdf type="twosided" id="red">
+                <bsdf type="diffuse">
+                    <rgb name="reflectance" value="0.8, 0.1, 0.1"/>
+                </bsdf>
+            </bsdf>
+            <shape type="sphere">
+                <point name="center" x="1" y="0" z="0"/>
+                <float name="radius" value="$radius"/>
+                <ref id="red"/>
+            </shape>
+            <shape type="rectangle">
+                <transform name="to_world">
+                    <scale value="2"/>
+                    <translate y="3"/>
+                </transform>
+                <emitter type="area">
+                    <rgb name="radiance" value="10"/>
+                </emitter>
+            </shape>
+            <shape type="disk"/>
+            <emitter type="constant">
+                <rgb name="radiance" value="0.1"/>
+            </emitter>
+        </scene>"#).unwrap();
+
+        let import = Import::load(&path).unwrap();
+        assert_eq!(import.objects.len(), 2);
+        assert_eq!(import.warnings.len(), 1);
+        assert!(import.warnings[0].contains("scene.xml:33: unsupported shape \"disk\""), "{}", import.warnings[0]);
+
+        let sphere = &import.objects[0];
+        assert_eq!(import.materials[sphere.material].0, "red");
+        let top = sphere.transform * Vec4f::new(0.0, 0.0, 0.5, 1.0);
+        assert_eq!(top, Vec4f::new(1.0, 0.0, 0.5, 1.0));
+        assert!(import.meshes[sphere.mesh].1.vertices.iter().all(|vertex| (vertex.position.norm() - 0.5).abs() < 1e-5));
+
+        let light = &import.objects[1];
+        let corner = light.transform * Vec4f::new(1.0, 1.0, 0.0, 1.0);
+        assert_eq!(corner, Vec4f::new(2.0, 5.0, 0.0, 1.0));
+        let emitter = serde_json::to_value(&import.materials[light.material].1).unwrap();
+        assert_eq!(emitter["emission"], serde_json::json!([10.0, 10.0, 10.0]));
+        assert_eq!(emitter["color"], serde_json::to_value(Vec3f::new(0.5, 0.5, 0.5)).unwrap());
+
+        // The field of view is horizontal.
+        let camera = import.camera.unwrap();
+        assert!((camera.origin - Vec3f::new(0.0, 0.0, 5.0)).norm() < 1e-5);
+        assert!((camera.direction + Vec3f::z()).norm() < 1e-5);
+        assert!((camera.vertical_fov(2.0) - 2.0 * (30f32.to_radians().tan() / 2.0).atan()).abs() < 1e-5);
+
+        std::fs::write(&path, "<scene>\n<shape type=\"sphere\"><ref id=\"missing\"/></shape>\n</scene>").unwrap();
+        let error = Import::load(&path).unwrap_err();
+        assert!(matches!(error, ImportError::Mitsuba { line: 2, .. }), "{error}");
+    }
+}
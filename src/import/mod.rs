@@ -0,0 +1,134 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use crate::{vec3_to_vec4, Camera, Environment, MatearialHandle, MeshLoadError, Mat4f, Material, Mesh, MeshHandle, NonInvertibleTransform, Object, ObjectHandle, Scene};
+
+mod obj;
+mod gltf;
+mod pbrt;
+mod mitsuba;
+mod shapes;
+
+// Meshes, materials and objects read from a file, to be added to a scene with `Scene::add_import`.
+// Objects refer to meshes and materials by their index in the import.
+#[derive(Debug, Default)]
+pub struct Import {
+    pub meshes: Vec<(String, Mesh)>,
+    pub materials: Vec<(String, Box<dyn Material>)>,
+    pub objects: Vec<ImportedObject>,
+    pub camera: Option<Camera>,
+    pub environment: Option<Box<dyn Environment>>,
+    // Parts of the file that were skipped because they are not supported.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedObject {
+    pub name: String,
+    pub mesh: usize,
+    pub material: usize,
+    pub transform: Mat4f,
+}
+
+// The scene handles of everything an import added, in the order of the import.
+#[derive(Debug, Clone, Default)]
+pub struct ImportHandles {
+    pub meshes: Vec<MeshHandle>,
+    pub materials: Vec<MatearialHandle>,
+    pub objects: Vec<ObjectHandle>,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    Obj(tobj::LoadError),
+    Gltf(::gltf::Error),
+    Mesh { name: String, error: MeshLoadError },
+    Texture { path: PathBuf, error: image::ImageError },
+    Pbrt { path: PathBuf, line: usize, message: String },
+    Mitsuba { path: PathBuf, line: usize, message: String },
+    UnsupportedFormat(PathBuf),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Io(error) => write!(f, "{error}"),
+            ImportError::Obj(error) => write!(f, "invalid OBJ file: {error}"),
+            ImportError::Gltf(error) => write!(f, "invalid glTF file: {error}"),
+            ImportError::Mesh { name, error } => write!(f, "mesh {name}: {error}"),
+            ImportError::Texture { path, error } => write!(f, "could not load texture {}: {error}", path.display()),
+            ImportError::Pbrt { path, line, message } | ImportError::Mitsuba { path, line, message } => {
+                write!(f, "{}:{line}: {message}", path.display())
+            }
+            ImportError::UnsupportedFormat(path) => write!(f, "unsupported scene format: {}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl Import {
+    // Imports a scene file, picking the format from its extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Import, ImportError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("obj") => Import::load_obj(path),
+            Some("gltf" | "glb") => Import::load_gltf(path),
+            Some("pbrt") => Import::load_pbrt(path),
+            Some("xml") => Import::load_mitsuba(path),
+            _ => Err(ImportError::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+}
+
+impl From<std::io::Error> for ImportError {
+    fn from(error: std::io::Error) -> Self {
+        ImportError::Io(error)
+    }
+}
+
+impl From<tobj::LoadError> for ImportError {
+    fn from(error: tobj::LoadError) -> Self {
+        ImportError::Obj(error)
+    }
+}
+
+impl From<::gltf::Error> for ImportError {
+    fn from(error: ::gltf::Error) -> Self {
+        ImportError::Gltf(error)
+    }
+}
+
+impl Scene {
+    // Adds every mesh, material and object of `import`, with the objects placed relative to `transform`.
+    // The imported camera and environment, if any, replace the scene's ones.
+    // Nothing is added if one of the resulting transforms is not invertible.
+    pub fn add_import(&mut self, import: Import, transform: &Mat4f) -> Result<ImportHandles, NonInvertibleTransform> {
+        let transforms: Vec<_> = import.objects.iter().map(|object| transform * object.transform).collect();
+        if transforms.iter().any(|transform| transform.try_inverse().is_none()) {
+            return Err(NonInvertibleTransform);
+        }
+
+        let meshes: Vec<_> = import.meshes.into_iter().map(|(name, mesh)| self.add_named_mesh(name, mesh)).collect();
+        let materials: Vec<_> = import.materials.into_iter().map(|(name, material)| self.add_named_material(name, material)).collect();
+        let objects = import.objects.into_iter().zip(transforms).map(|(object, transform)| {
+            let new_object = Object::new(meshes[object.mesh], transform, materials[object.material])?;
+            Ok(self.add_named_object(object.name, new_object))
+        }).collect::<Result<_, _>>()?;
+
+        if let Some(camera) = import.camera {
+            let origin = transform * vec3_to_vec4(&camera.origin, 1.0);
+            let direction = transform * vec3_to_vec4(&camera.direction, 0.0);
+            let up = transform * vec3_to_vec4(&camera.up, 0.0);
+            self.camera = Camera::new(origin.xyz(), direction.xyz(), up.xyz(), camera.fov);
+        }
+        if let Some(environment) = import.environment {
+            self.environment = environment;
+        }
+
+        Ok(ImportHandles { meshes, materials, objects })
+    }
+}
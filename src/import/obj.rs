@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+
+use crate::import::{Import, ImportError, ImportedObject};
+use crate::loaders::tobj_load_options;
+use crate::{Lambertian, Mat4f, Material, Mesh, Normals, Standard, Texture, Vec3f};
+
+impl Import {
+    // Reads every model of an OBJ file as a mesh, and every material of its MTL libraries as the closest `Material`.
+    // Models without a material, or whose MTL file is missing, share a default diffuse material.
+    pub fn load_obj(path: impl AsRef<Path>) -> Result<Import, ImportError> {
+        let path = path.as_ref();
+        let (models, materials) = tobj::load_obj(path, &tobj_load_options())?;
+        let materials = materials.unwrap_or_default();
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        let mut import = Import::default();
+        for material in &materials {
+            import.materials.push((material.name.clone(), translate_material(material, directory)?));
+        }
+
+        let mut default_material = None;
+        for model in models {
+            if model.mesh.indices.is_empty() {
+                continue;
+            }
+
+            let material = match model.mesh.material_id.filter(|&id| id < materials.len()) {
+                Some(id) => id,
+                None => *default_material.get_or_insert_with(|| {
+                    import.materials.push(("default".to_string(), Box::new(Lambertian { color: Vec3f::repeat(0.8), emission: Vec3f::zeros() }) as Box<dyn Material>));
+                    import.materials.len() - 1
+                }),
+            };
+
+            let mesh = Mesh::from_tobj(&model.mesh, Normals::default())
+                            .map_err(|error| ImportError::Mesh { name: model.name.clone(), error })?;
+            import.meshes.push((model.name.clone(), mesh));
+            import.objects.push(ImportedObject {
+                name: model.name,
+                mesh: import.meshes.len() - 1,
+                material,
+                transform: Mat4f::identity(),
+            });
+        }
+
+        Ok(import)
+    }
+}
+
+// Plain diffuse materials become a `Lambertian`, anything glossy, transparent or textured a `Standard`.
+fn translate_material(material: &tobj::Material, directory: &Path) -> Result<Box<dyn Material>, ImportError> {
+    let texture = |name: Option<&str>, srgb: bool| -> Result<Option<Texture>, ImportError> {
+        let Some(path) = name.and_then(texture_path).map(|path| directory.join(path)) else { return Ok(None) };
+        Texture::load(&path, srgb).map(Some).map_err(|error| ImportError::Texture { path, error })
+    };
+    let parameter = |key: &str| material.unknown_param.get(key).map(String::as_str);
+
+    let diffuse_texture = texture(material.diffuse_texture.as_deref(), true)?;
+    let specular_texture = texture(material.specular_texture.as_deref(), true)?;
+    let glossiness_texture = texture(material.shininess_texture.as_deref(), false)?;
+    let opacity_texture = texture(material.dissolve_texture.as_deref(), false)?;
+    let emission_texture = texture(parameter("map_Ke"), true)?;
+
+    // A texture alone means its colors are used as they are.
+    let default_diffuse = if diffuse_texture.is_some() { 1.0 } else { 0.8 };
+    let diffuse = material.diffuse.map(Vec3f::from).unwrap_or(Vec3f::repeat(default_diffuse));
+    let specular = material.specular.map(Vec3f::from).or(specular_texture.as_ref().map(|_| Vec3f::repeat(1.0))).unwrap_or(Vec3f::zeros());
+    let emission = parameter("Ke").and_then(parse_color).or(emission_texture.as_ref().map(|_| Vec3f::repeat(1.0))).unwrap_or(Vec3f::zeros());
+    // High Phong exponents give sharp reflections.
+    let roughness = material.shininess.map(|shininess| (2.0 / (shininess.max(0.0) + 2.0)).sqrt()).unwrap_or(1.0);
+    let opacity = material.dissolve
+                          .or(parameter("Tr").and_then(|tr| tr.parse::<f32>().ok()).map(|tr| 1.0 - tr))
+                          .unwrap_or(1.0);
+    let refraction_index = material.optical_density.unwrap_or(1.0);
+
+    let textured = [&diffuse_texture, &specular_texture, &glossiness_texture, &opacity_texture, &emission_texture].iter().any(|texture| texture.is_some());
+    if !textured && specular == Vec3f::zeros() && opacity >= 1.0 {
+        return Ok(Box::new(Lambertian { color: diffuse, emission }));
+    }
+
+    Ok(Box::new(Standard {
+        diffuse,
+        diffuse_texture,
+        specular,
+        specular_texture,
+        roughness,
+        roughness_texture: None,
+        glossiness_texture,
+        opacity,
+        opacity_texture,
+        refraction_index,
+        emission,
+        emission_texture,
+    }))
+}
+
+// Texture statements may start with options such as `-bm 0.5`, the file name comes last.
+fn texture_path(statement: &str) -> Option<PathBuf> {
+    statement.split_whitespace().last().map(PathBuf::from)
+}
+
+fn parse_color(value: &str) -> Option<Vec3f> {
+    let values = value.split_whitespace().map(str::parse::<f32>).collect::<Result<Vec<_>, _>>().ok()?;
+    match values[..] {
+        [x] => Some(Vec3f::repeat(x)),
+        [r, g, b] => Some(Vec3f::new(r, g, b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::{Camera, ConstantEnvironment, Scene};
+
+    #[test]
+    fn test_import_obj_with_materials() {
+        let directory = TempDir::new("import_obj_with_materials");
+        image::RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0])).save(directory.join("red.png")).unwrap();
+        std::fs::write(directory.join("scene.mtl"), "\
+newmtl plain\nKd 0.5 0.5 0.5\nKe 1 1 1\n\
+newmtl textured\nKd 1 1 1\nKs 0.2 0.2 0.2\nNs 100\nd 0.5\nNi 1.5\nmap_Kd -bm 1 red.png\n").unwrap();
+        std::fs::write(directory.join("scene.obj"), "\
+mtllib scene.mtl\n\
+v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvn 0 0 1\n\
+o bare\nf 1/1/1 3/3/1 4/4/1\n\
+o quad\nusemtl plain\nf 1/1/1 2/2/1 3/3/1 4/4/1\n\
+o triangle\nusemtl textured\nf 1/1/1 2/2/1 3/3/1\n").unwrap();
+
+        let import = Import::load_obj(directory.join("scene.obj")).unwrap();
+        assert_eq!(import.meshes.len(), 3);
+        assert_eq!(import.meshes[1].1.triangles.len(), 2);
+        assert_eq!(import.materials.len(), 3);
+
+        let material = |i: usize| serde_json::to_value(&import.materials[i].1).unwrap();
+        assert_eq!(material(0)["type"], "Lambertian");
+        assert_eq!(material(0)["emission"], serde_json::json!([1.0, 1.0, 1.0]));
+        assert_eq!(material(1)["type"], "Standard");
+        assert_eq!(material(1)["opacity"], 0.5);
+        assert_eq!(material(1)["refraction_index"], 1.5);
+        assert!(material(1)["diffuse_texture"]["path"].as_str().unwrap().ends_with("red.png"));
+        assert_eq!(import.objects[0].material, 2);
+
+        let mut scene = Scene::new(Camera::new(Vec3f::zeros(), Vec3f::z(), Vec3f::y(), 1.0),
+            Box::new(ConstantEnvironment { color: Vec3f::zeros() }));
+        let handles = scene.add_import(import, &Mat4f::identity()).unwrap();
+        assert_eq!(handles.objects.len(), 3);
+        assert_eq!(scene.objects().name(handles.objects[1]), Some("quad"));
+        assert_eq!(scene.materials().find("textured"), Some(handles.materials[1]));
+
+        // Textures are saved relative to the scene file and found from it, whatever the working directory.
+        std::fs::create_dir_all(directory.join("scenes")).unwrap();
+        let path = directory.join("scenes/scene.json");
+        scene.save(&path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let textured = json["materials"].as_array().unwrap().iter().find(|slot| slot["name"] == "textured").unwrap();
+        let texture = &textured["value"]["diffuse_texture"]["path"];
+        assert_eq!(Path::new(texture.as_str().unwrap()), Path::new("../red.png"));
+        let loaded = Scene::load(&path).unwrap();
+        let material = serde_json::to_value(&loaded.materials()[handles.materials[1]]).unwrap();
+        assert_eq!(Path::new(material["diffuse_texture"]["path"].as_str().unwrap()), directory.join("scenes/../red.png"));
+    }
+}
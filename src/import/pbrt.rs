@@ -0,0 +1,565 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::import::{Import, ImportError, ImportedObject};
+use crate::import::shapes::sphere;
+use crate::{compute_normals, ConstantEnvironment, Camera, Dialectric, Lambertian, Mat4f, Material, Mesh, Metal, Normals, Vec2f, Vec3f, Vec4f, Vertex};
+
+// pbrt uses left-handed coordinates, mirroring x keeps images the right way round in our right-handed renderer.
+fn handedness() -> Mat4f {
+    Mat4f::new_nonuniform_scaling(&Vec3f::new(-1.0, 1.0, 1.0))
+}
+
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+impl Import {
+    // Reads a subset of pbrt-v4 scenes: cameras, transforms, attribute blocks, triangle, PLY and sphere shapes,
+    // diffuse, conductor and dielectric materials, infinite and diffuse area lights, and included files.
+    // Anything else is skipped with a warning in `warnings`. Render settings (film, sampler, integrator...) are ignored.
+    pub fn load_pbrt(path: impl AsRef<Path>) -> Result<Import, ImportError> {
+        let path = path.as_ref();
+        let mut parser = Parser {
+            directory: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            import: Import::default(),
+            state: GraphicsState::default(),
+            stack: Vec::new(),
+            named_materials: HashMap::new(),
+            default_material: None,
+            camera: None,
+            resolution: (1280.0, 720.0),
+        };
+        // Without an infinite light, everything outside of the scene is black.
+        parser.import.environment = Some(Box::new(ConstantEnvironment { color: Vec3f::zeros() }));
+        parser.parse_file(path, 0)?;
+
+        let mut import = parser.import;
+        if let Some((camera_to_world, fov)) = parser.camera {
+            let camera_to_world = handedness() * camera_to_world;
+            let origin = (camera_to_world * Vec4f::new(0.0, 0.0, 0.0, 1.0)).xyz();
+            let direction = (camera_to_world * Vec4f::new(0.0, 0.0, 1.0, 0.0)).xyz();
+            let up = (camera_to_world * Vec4f::new(0.0, 1.0, 0.0, 0.0)).xyz();
+            let mut camera = Camera::new(origin, direction, up, 1.0);
+
+            // pbrt's field of view is the one of the shorter side of the image.
+            let aspect_ratio = parser.resolution.0 / parser.resolution.1;
+            let vertical_fov = if aspect_ratio >= 1.0 { fov } else { 2.0 * ((fov / 2.0).tan() / aspect_ratio).atan() };
+            camera.set_vertical_fov(vertical_fov, aspect_ratio);
+            import.camera = Some(camera);
+        }
+        Ok(import)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Number(f32),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, (usize, String)> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '#' => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '[' => tokens.push((Token::Open, line)),
+            ']' => tokens.push((Token::Close, line)),
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\n') | None => return Err((line, "unterminated string".to_string())),
+                        Some(c) => string.push(c),
+                    }
+                }
+                tokens.push((Token::String(string), line));
+            }
+            c => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && !matches!(c, '"' | '[' | ']' | '#')) {
+                    word.push(c);
+                }
+                let token = match word.parse::<f32>() {
+                    Ok(number) => Token::Number(number),
+                    Err(_) => Token::Word(word),
+                };
+                tokens.push((token, line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f32),
+    String(String),
+    Bool(bool),
+}
+
+#[derive(Debug)]
+struct Parameter {
+    kind: String,
+    name: String,
+    values: Vec<Value>,
+}
+
+const PARAMETER_TYPES: [&str; 17] = ["integer", "float", "point2", "vector2", "point3", "vector3", "normal", "normal3", "point",
+                                     "vector", "color", "rgb", "spectrum", "blackbody", "bool", "string", "texture"];
+
+// A directive with its positional arguments (`Shape "sphere"`, `Translate 1 2 3`...) and its `"type name" value` parameters.
+#[derive(Debug)]
+struct Directive {
+    name: String,
+    line: usize,
+    arguments: Vec<Value>,
+    parameters: Vec<Parameter>,
+}
+
+impl Directive {
+    fn parameter(&self, name: &str) -> Option<&Parameter> {
+        self.parameters.iter().find(|parameter| parameter.name == name)
+    }
+
+    fn numbers(&self, name: &str) -> Option<Vec<f32>> {
+        let parameter = self.parameter(name)?;
+        Some(parameter.values.iter().filter_map(|value| match value {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }).collect())
+    }
+
+    fn float(&self, name: &str, default: f32) -> f32 {
+        self.numbers(name).and_then(|numbers| numbers.first().copied()).unwrap_or(default)
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        self.parameter(name)?.values.iter().find_map(|value| match value {
+            Value::String(string) => Some(string.as_str()),
+            _ => None,
+        })
+    }
+
+    fn argument(&self, index: usize) -> Option<&str> {
+        match self.arguments.get(index)? {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    fn numeric_arguments(&self) -> Vec<f32> {
+        self.arguments.iter().filter_map(|value| match value {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }).collect()
+    }
+}
+
+fn parse_directives(tokens: Vec<(Token, usize)>) -> Result<Vec<Directive>, (usize, String)> {
+    let mut directives: Vec<Directive> = Vec::new();
+    let mut depth = 0;
+    for (token, line) in tokens {
+        let directive = match (&token, directives.last_mut()) {
+            (Token::Word(word), _) if word != "true" && word != "false" => {
+                if depth != 0 {
+                    return Err((line, format!("unexpected `{word}` inside brackets")));
+                }
+                directives.push(Directive { name: word.clone(), line, arguments: Vec::new(), parameters: Vec::new() });
+                continue;
+            }
+            (_, Some(directive)) => directive,
+            (_, None) => return Err((line, "expected a directive".to_string())),
+        };
+
+        let value = match token {
+            Token::Open => { depth += 1; continue; }
+            Token::Close if depth == 0 => return Err((line, "unmatched `]`".to_string())),
+            Token::Close => { depth -= 1; continue; }
+            Token::Word(word) => Value::Bool(word == "true"),
+            Token::Number(number) => Value::Number(number),
+            Token::String(string) => {
+                // Outside of brackets, a string such as "float radius" starts a new parameter,
+                // unless it is the value of the parameter before it.
+                let waiting_for_value = directive.parameters.last().is_some_and(|parameter| parameter.values.is_empty());
+                let declaration = string.split_whitespace().collect::<Vec<_>>();
+                if let (0, false, &[kind, name]) = (depth, waiting_for_value, declaration.as_slice()) {
+                    if PARAMETER_TYPES.contains(&kind) {
+                        directive.parameters.push(Parameter { kind: kind.to_string(), name: name.to_string(), values: Vec::new() });
+                        continue;
+                    }
+                }
+                // "bool" values can also be quoted.
+                match (directive.parameters.last().map(|parameter| parameter.kind.as_str()), string.as_str()) {
+                    (Some("bool"), "true" | "false") => Value::Bool(string == "true"),
+                    _ => Value::String(string),
+                }
+            }
+        };
+        match directive.parameters.last_mut() {
+            Some(parameter) => parameter.values.push(value),
+            None => directive.arguments.push(value),
+        }
+    }
+    Ok(directives)
+}
+
+#[derive(Clone)]
+struct GraphicsState {
+    transform: Mat4f,
+    // Index in the import's materials and the reflectance area lights reuse.
+    material: Option<(usize, Vec3f)>,
+    area_light: Option<Vec3f>,
+}
+
+impl Default for GraphicsState {
+    fn default() -> Self {
+        GraphicsState { transform: Mat4f::identity(), material: None, area_light: None }
+    }
+}
+
+struct Parser {
+    directory: PathBuf,
+    import: Import,
+    state: GraphicsState,
+    stack: Vec<GraphicsState>,
+    named_materials: HashMap<String, (usize, Vec3f)>,
+    default_material: Option<(usize, Vec3f)>,
+    camera: Option<(Mat4f, f32)>,
+    resolution: (f32, f32),
+}
+
+impl Parser {
+    fn parse_file(&mut self, path: &Path, depth: usize) -> Result<(), ImportError> {
+        let error = |line: usize, message: String| ImportError::Pbrt { path: path.to_path_buf(), line, message };
+        let text = std::fs::read_to_string(path)?;
+        let tokens = tokenize(&text).map_err(|(line, message)| error(line, message))?;
+        let directives = parse_directives(tokens).map_err(|(line, message)| error(line, message))?;
+
+        for directive in directives {
+            let warning = self.apply(&directive, depth).map_err(|message| match message {
+                DirectiveError::Import(error) => error,
+                DirectiveError::Invalid(message) => error(directive.line, message),
+            })?;
+            if let Some(warning) = warning {
+                self.import.warnings.push(format!("{}:{}: {warning}", path.display(), directive.line));
+            }
+        }
+        Ok(())
+    }
+
+    // Applies a directive, returning a warning when it is not supported.
+    fn apply(&mut self, directive: &Directive, depth: usize) -> Result<Option<String>, DirectiveError> {
+        let numbers = directive.numeric_arguments();
+        let expect = |count: usize| -> Result<(), DirectiveError> {
+            if numbers.len() != count {
+                return Err(DirectiveError::Invalid(format!("{} expects {count} numbers, found {}", directive.name, numbers.len())));
+            }
+            Ok(())
+        };
+
+        match directive.name.as_str() {
+            "Identity" => self.state.transform = Mat4f::identity(),
+            "Translate" => {
+                expect(3)?;
+                self.state.transform *= Mat4f::new_translation(&Vec3f::new(numbers[0], numbers[1], numbers[2]));
+            }
+            "Scale" => {
+                expect(3)?;
+                self.state.transform *= Mat4f::new_nonuniform_scaling(&Vec3f::new(numbers[0], numbers[1], numbers[2]));
+            }
+            "Rotate" => {
+                expect(4)?;
+                let axis = Vec3f::new(numbers[1], numbers[2], numbers[3]);
+                self.state.transform *= Mat4f::from_scaled_axis(axis.normalize() * numbers[0].to_radians());
+            }
+            "LookAt" => {
+                expect(9)?;
+                let [eye, target, up] = [0, 3, 6].map(|i| Vec3f::new(numbers[i], numbers[i + 1], numbers[i + 2]));
+                let direction = (target - eye).normalize();
+                let right = up.normalize().cross(&direction).normalize();
+                let up = direction.cross(&right);
+                let camera_to_world = Mat4f::new(right.x, up.x, direction.x, eye.x,
+                                                 right.y, up.y, direction.y, eye.y,
+                                                 right.z, up.z, direction.z, eye.z,
+                                                 0.0, 0.0, 0.0, 1.0);
+                let world_to_camera = camera_to_world.try_inverse().ok_or_else(|| DirectiveError::Invalid("degenerate LookAt".to_string()))?;
+                self.state.transform *= world_to_camera;
+            }
+            // pbrt matrices are given column by column.
+            "Transform" | "ConcatTransform" => {
+                expect(16)?;
+                let matrix = Mat4f::from_column_slice(&numbers);
+                if directive.name == "Transform" {
+                    self.state.transform = matrix;
+                } else {
+                    self.state.transform *= matrix;
+                }
+            }
+            "AttributeBegin" | "TransformBegin" => self.stack.push(self.state.clone()),
+            "AttributeEnd" | "TransformEnd" => {
+                let state = self.stack.pop().ok_or_else(|| DirectiveError::Invalid(format!("{} without a matching begin", directive.name)))?;
+                if directive.name == "TransformEnd" {
+                    self.state.transform = state.transform;
+                } else {
+                    self.state = state;
+                }
+            }
+            "WorldBegin" => self.state.transform = Mat4f::identity(),
+            "WorldEnd" => {}
+            "Camera" => {
+                let camera_to_world = self.state.transform.try_inverse().ok_or_else(|| DirectiveError::Invalid("the camera transform is not invertible".to_string()))?;
+                self.camera = Some((camera_to_world, directive.float("fov", 90.0).to_radians()));
+                if directive.argument(0) != Some("perspective") {
+                    return Ok(Some(format!("unsupported camera {:?}, using a perspective camera", directive.argument(0).unwrap_or(""))));
+                }
+            }
+            "Film" => {
+                self.resolution = (directive.float("xresolution", 1280.0), directive.float("yresolution", 720.0));
+            }
+            "Sampler" | "Integrator" | "PixelFilter" | "Accelerator" | "ColorSpace" | "Option" => {}
+            "Include" | "Import" => {
+                let file = directive.argument(0).ok_or_else(|| DirectiveError::Invalid(format!("{} expects a file name", directive.name)))?;
+                if depth >= MAX_INCLUDE_DEPTH {
+                    return Err(DirectiveError::Invalid(format!("too many nested includes at {file}")));
+                }
+                self.parse_file(&self.directory.join(file), depth + 1).map_err(DirectiveError::Import)?;
+            }
+            "Material" | "MakeNamedMaterial" => {
+                let (kind, name) = match directive.name.as_str() {
+                    "Material" => (directive.argument(0).unwrap_or(""), format!("material{}", self.import.materials.len())),
+                    _ => (directive.string("type").unwrap_or(""), directive.argument(0).unwrap_or("").to_string()),
+                };
+                let (material, albedo, warning) = translate_material(kind, directive);
+                self.import.materials.push((name.clone(), material));
+                let material = (self.import.materials.len() - 1, albedo);
+                if directive.name == "Material" {
+                    self.state.material = Some(material);
+                } else {
+                    self.named_materials.insert(name, material);
+                }
+                return Ok(warning);
+            }
+            "NamedMaterial" => {
+                let name = directive.argument(0).unwrap_or("");
+                match self.named_materials.get(name) {
+                    Some(&material) => self.state.material = Some(material),
+                    None => return Err(DirectiveError::Invalid(format!("unknown material {name:?}"))),
+                }
+            }
+            "LightSource" => {
+                let kind = directive.argument(0).unwrap_or("");
+                if kind != "infinite" {
+                    return Ok(Some(format!("unsupported light {kind:?}")));
+                }
+                let (color, warning) = color(directive, "L", Vec3f::repeat(1.0));
+                self.import.environment = Some(Box::new(ConstantEnvironment { color: color * directive.float("scale", 1.0) }));
+                if directive.string("filename").is_some() {
+                    return Ok(Some("environment maps are not supported, using a constant environment".to_string()));
+                }
+                return Ok(warning);
+            }
+            "AreaLightSource" => {
+                let kind = directive.argument(0).unwrap_or("");
+                if kind != "diffuse" {
+                    return Ok(Some(format!("unsupported area light {kind:?}")));
+                }
+                let (color, warning) = color(directive, "L", Vec3f::repeat(1.0));
+                self.state.area_light = Some(color * directive.float("scale", 1.0));
+                return Ok(warning);
+            }
+            "Shape" => return self.add_shape(directive),
+            name => return Ok(Some(format!("unsupported directive {name}"))),
+        }
+        Ok(None)
+    }
+
+    fn add_shape(&mut self, directive: &Directive) -> Result<Option<String>, DirectiveError> {
+        let invalid = |message: String| DirectiveError::Invalid(message);
+        let kind = directive.argument(0).unwrap_or("");
+        let mesh = match kind {
+            "trianglemesh" => {
+                let positions = directive.numbers("P").ok_or_else(|| invalid("trianglemesh without \"point3 P\"".to_string()))?;
+                let normals = directive.numbers("N").unwrap_or_default();
+                let uvs = directive.numbers("uv").or_else(|| directive.numbers("st")).unwrap_or_default();
+                let vertex_count = positions.len() / 3;
+                let indices = match directive.numbers("indices") {
+                    // Checked before the cast, which would turn a negative or fractional index into another vertex.
+                    Some(indices) => indices.iter().map(|&i| if i >= 0.0 && i.fract() == 0.0 {
+                        Ok(i as usize)
+                    } else {
+                        Err(invalid(format!("trianglemesh index {i} is not a vertex index")))
+                    }).collect::<Result<_, _>>()?,
+                    None if vertex_count == 3 => vec![0, 1, 2],
+                    None => return Err(invalid("trianglemesh without \"integer indices\"".to_string())),
+                };
+                if indices.len() % 3 != 0 || indices.iter().any(|&i| i >= vertex_count) {
+                    return Err(invalid("trianglemesh indices do not match its points".to_string()));
+                }
+
+                let vertices: Vec<Vertex> = (0..vertex_count).map(|i| Vertex {
+                    position: Vec3f::new(positions[3 * i], positions[3 * i + 1], positions[3 * i + 2]),
+                    normal: normals.get(3 * i..3 * i + 3).map(|n| Vec3f::new(n[0], n[1], n[2])).unwrap_or_default(),
+                    uv_coord: uvs.get(2 * i..2 * i + 2).map(|uv| Vec2f::new(uv[0], uv[1])).unwrap_or_default(),
+                }).collect();
+                let triangles: Vec<[usize;3]> = indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+                if normals.len() == positions.len() {
+                    Mesh::new(vertices, triangles)
+                } else {
+                    let (vertices, triangles) = compute_normals(&vertices, &triangles, Normals::default());
+                    Mesh::new(vertices, triangles)
+                }
+            }
+            "plymesh" => {
+                let file = directive.string("filename").ok_or_else(|| invalid("plymesh without \"string filename\"".to_string()))?;
+                Mesh::load_ply(self.directory.join(file))
+                    .map_err(|error| DirectiveError::Import(ImportError::Mesh { name: file.to_string(), error }))?
+            }
+            "sphere" => sphere(directive.float("radius", 1.0)),
+            _ => return Ok(Some(format!("unsupported shape {kind:?}"))),
+        };
+
+        let material = match (self.state.area_light, self.state.material) {
+            (Some(emission), material) => {
+                let color = material.map(|(_, albedo)| albedo).unwrap_or(Vec3f::repeat(0.5));
+                self.import.materials.push((format!("light{}", self.import.materials.len()), Box::new(Lambertian { color, emission })));
+                self.import.materials.len() - 1
+            }
+            (None, Some((material, _))) => material,
+            (None, None) => {
+                let import = &mut self.import;
+                self.default_material.get_or_insert_with(|| {
+                    import.materials.push(("default".to_string(), Box::new(Lambertian { color: Vec3f::repeat(0.5), emission: Vec3f::zeros() })));
+                    (import.materials.len() - 1, Vec3f::repeat(0.5))
+                }).0
+            }
+        };
+
+        let name = format!("{kind}{}", self.import.objects.len());
+        self.import.meshes.push((name.clone(), mesh));
+        self.import.objects.push(ImportedObject {
+            name,
+            mesh: self.import.meshes.len() - 1,
+            material,
+            transform: handedness() * self.state.transform,
+        });
+        Ok(None)
+    }
+}
+
+enum DirectiveError {
+    Import(ImportError),
+    Invalid(String),
+}
+
+// Reads an RGB parameter, other spectra are replaced by `default` with a warning.
+fn color(directive: &Directive, name: &str, default: Vec3f) -> (Vec3f, Option<String>) {
+    let Some(parameter) = directive.parameter(name) else { return (default, None) };
+    match (parameter.kind.as_str(), directive.numbers(name).as_deref()) {
+        ("rgb" | "color", Some(&[r, g, b])) => (Vec3f::new(r, g, b), None),
+        ("float", Some(&[x])) => (Vec3f::repeat(x), None),
+        (kind, _) => (default, Some(format!("unsupported {kind} value for {name:?}"))),
+    }
+}
+
+// Returns the material with the reflectance area lights attached to it should keep.
+fn translate_material(kind: &str, directive: &Directive) -> (Box<dyn Material>, Vec3f, Option<String>) {
+    match kind {
+        "diffuse" => {
+            let (reflectance, warning) = color(directive, "reflectance", Vec3f::repeat(0.5));
+            (Box::new(Lambertian { color: reflectance, emission: Vec3f::zeros() }), reflectance, warning)
+        }
+        "conductor" => {
+            // Without spectral data, conductors use their reflectance or look like copper, pbrt's default.
+            let (reflectance, warning) = color(directive, "reflectance", Vec3f::new(0.95, 0.64, 0.54));
+            let warning = warning.or_else(|| directive.parameter("eta").map(|_| "conductor eta and k are not supported, using a reflectance".to_string()));
+            let roughness = directive.float("roughness", directive.float("uroughness", 0.0));
+            let roughness = if directive.parameter("remaproughness").is_some_and(|parameter| parameter.values == [Value::Bool(false)]) {
+                roughness
+            } else {
+                roughness.sqrt()
+            };
+            (Box::new(Metal { color: reflectance, roughness }), reflectance, warning)
+        }
+        "dielectric" | "thindielectric" => {
+            let refraction_index = directive.float("eta", 1.5);
+            (Box::new(Dialectric { refraction_index }), Vec3f::zeros(), None)
+        }
+        _ => {
+            let (reflectance, _) = color(directive, "reflectance", Vec3f::repeat(0.5));
+            let material = Box::new(Lambertian { color: reflectance, emission: Vec3f::zeros() });
+            (material, reflectance, Some(format!("unsupported material {kind:?}, using a diffuse material")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_import_pbrt() {
+        let directory = TempDir::new("import_pbrt");
+        std::fs::write(directory.join("geometry.pbrt"), r#"
+            AttributeBegin
+                AreaLightSource "diffuse" "rgb L" [ 4 4 4 ]
+                Translate 0 5 0
+                Shape "sphere" "float radius" 0.5
+            AttributeEnd
+            Shape "trianglemesh" "point3 P" [ 0 0 0  1 0 0  0 1 0 ] "integer indices" [ 0 1 2 ]
+            Shape "disk"
+        "#).unwrap();
+        std::fs::write(directory.join("scene.pbrt"), r#"
+            # A camera at z = -5 looking at the origin.
+            LookAt 0 0 -5  0 0 0  0 1 0
+            Camera "perspective" "float fov" [ 45 ]
+            Film "rgb" "integer xresolution" 400 "integer yresolution" 200 "string filename" "scene.exr"
+            Sampler "zsobol" "integer pixelsamples" 64
+            WorldBegin
+            LightSource "infinite" "rgb L" [ 0.2 0.3 0.4 ]
+            Material "conductor" "rgb reflectance" [ 0.9 0.8 0.1 ] "float roughness" 0.04 "bool remaproughness" false
+            Include "geometry.pbrt"
+        "#).unwrap();
+
+        let import = Import::load(directory.join("scene.pbrt")).unwrap();
+        assert_eq!(import.objects.len(), 2);
+        assert_eq!(import.warnings.len(), 1);
+        assert!(import.warnings[0].contains("geometry.pbrt:8: unsupported shape \"disk\""), "{}", import.warnings[0]);
+
+        let light = serde_json::to_value(&import.materials[import.objects[0].material].1).unwrap();
+        assert_eq!(light["emission"], serde_json::json!([4.0, 4.0, 4.0]));
+        let center = import.objects[0].transform * Vec4f::new(0.0, 0.0, 0.0, 1.0);
+        assert_eq!(center, Vec4f::new(0.0, 5.0, 0.0, 1.0));
+
+        let metal = serde_json::to_value(&import.materials[import.objects[1].material].1).unwrap();
+        assert_eq!(metal["type"], "Metal");
+        assert_eq!(metal["roughness"], serde_json::to_value(0.04f32).unwrap());
+        // The x axis is mirrored to go from pbrt's left-handed coordinates to ours.
+        let corner = import.objects[1].transform * Vec4f::new(1.0, 0.0, 0.0, 1.0);
+        assert_eq!(corner, Vec4f::new(-1.0, 0.0, 0.0, 1.0));
+
+        let camera = import.camera.unwrap();
+        assert!((camera.origin - Vec3f::new(0.0, 0.0, -5.0)).norm() < 1e-5);
+        assert!((camera.direction - Vec3f::z()).norm() < 1e-5);
+        assert!((camera.vertical_fov(2.0) - 45f32.to_radians()).abs() < 1e-5);
+
+        std::fs::write(directory.join("broken.pbrt"), "WorldBegin\nAttributeEnd\n").unwrap();
+        let error = Import::load(directory.join("broken.pbrt")).unwrap_err();
+        assert!(matches!(error, ImportError::Pbrt { line: 2, .. }));
+
+        for indices in ["0 -1 2", "0 1.5 2"] {
+            std::fs::write(directory.join("broken.pbrt"), format!("WorldBegin\nShape \"trianglemesh\" \"point3 P\" [ 0 0 0  1 0 0  0 1 0 ] \"integer indices\" [ {indices} ]\n")).unwrap();
+            let error = Import::load(directory.join("broken.pbrt")).unwrap_err();
+            assert!(matches!(error, ImportError::Pbrt { line: 2, .. }), "{error}");
+        }
+    }
+}
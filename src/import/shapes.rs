@@ -0,0 +1,65 @@
+use std::f32::consts::PI;
+
+use crate::{Mesh, Vec2f, Vec3f, Vertex};
+
+// Meshes for the analytic shapes of other renderers' scene formats.
+
+// A uv sphere around the z axis, as pbrt and Mitsuba define them.
+pub(super) fn sphere(radius: f32) -> Mesh {
+    const RINGS: usize = 24;
+    const SEGMENTS: usize = 48;
+    let mut vertices = Vec::new();
+    for ring in 0..=RINGS {
+        let v = ring as f32 / RINGS as f32;
+        let theta = PI * v;
+        for segment in 0..=SEGMENTS {
+            let u = segment as f32 / SEGMENTS as f32;
+            let phi = 2.0 * PI * u;
+            let normal = Vec3f::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), -theta.cos());
+            vertices.push(Vertex { position: radius * normal, normal, uv_coord: Vec2f::new(u, v) });
+        }
+    }
+
+    let index = |ring: usize, segment: usize| ring * (SEGMENTS + 1) + segment;
+    let mut triangles = Vec::new();
+    for ring in 0..RINGS {
+        for segment in 0..SEGMENTS {
+            let (a, b, c, d) = (index(ring, segment), index(ring, segment + 1), index(ring + 1, segment), index(ring + 1, segment + 1));
+            if ring != 0 {
+                triangles.push([a, b, d]);
+            }
+            if ring != RINGS - 1 {
+                triangles.push([a, d, c]);
+            }
+        }
+    }
+    Mesh::new(vertices, triangles)
+}
+
+// The square [-1, 1]² of the z = 0 plane, facing +z.
+pub(super) fn rectangle() -> Mesh {
+    let vertices = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| Vertex {
+        position: Vec3f::new(x, y, 0.0),
+        normal: Vec3f::z(),
+        uv_coord: Vec2f::new((x + 1.0) / 2.0, (y + 1.0) / 2.0),
+    });
+    Mesh::new(vertices.to_vec(), vec![[0, 1, 2], [0, 2, 3]])
+}
+
+// The cube [-1, 1]³ with flat faces.
+pub(super) fn cube() -> Mesh {
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    for normal in [Vec3f::x(), -Vec3f::x(), Vec3f::y(), -Vec3f::y(), Vec3f::z(), -Vec3f::z()] {
+        // Two axes spanning the face, with u × v = normal so that faces are counterclockwise seen from outside.
+        let u = Vec3f::new(normal.y, normal.z, normal.x);
+        let v = normal.cross(&u);
+        let first = vertices.len();
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            vertices.push(Vertex { position: normal + x * u + y * v, normal, uv_coord: Vec2f::new((x + 1.0) / 2.0, (y + 1.0) / 2.0) });
+        }
+        triangles.push([first, first + 1, first + 2]);
+        triangles.push([first, first + 2, first + 3]);
+    }
+    Mesh::new(vertices, triangles)
+}
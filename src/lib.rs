@@ -9,6 +9,23 @@ mod material;
 mod colliders;
 mod bvhs;
 mod parallel;
+mod animation;
+mod scene_graph;
+mod arena;
+mod validation;
+mod migration;
+mod loaders;
+mod texture;
+mod import;
+mod export;
+mod authoring;
+mod statistics;
+mod control;
+mod observer;
+mod tile_order;
+mod distributed;
+#[cfg(test)]
+mod test_util;
 
 pub use image::*;
 pub use ray_tracer::*;
@@ -19,3 +36,15 @@ pub use hitables::*;
 pub use commun_types::*;
 pub use material::*;
 pub use colliders::*;
+pub use animation::*;
+pub use scene_graph::*;
+pub use arena::*;
+pub use validation::*;
+pub use loaders::*;
+pub use texture::*;
+pub use import::*;
+pub use statistics::*;
+pub use control::*;
+pub use observer::*;
+pub use tile_order::*;
+pub use distributed::*;
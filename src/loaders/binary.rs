@@ -0,0 +1,301 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::bvhs::{BVHNode, NodeContent, AABB, BVH};
+use crate::loaders::{MeshLoadError, MeshSource};
+use crate::{Mesh, Vec2f, Vec3f, Vertex};
+
+// Layout of a binary mesh, every value is little endian:
+//   magic "RTMESH\0\0", version: u32, flags: u32,
+//   vertex count: u64, triangle count: u64, BVH node count: u64,
+//   vertices: [position xyz, normal xyz, uv; f32],
+//   vertex colors: [rgb; f32], only when the `HAS_COLORS` flag is set,
+//   triangles: [u32; 3],
+//   BVH nodes: [aabb min xyz, aabb max xyz; f32] then [kind, first, second; u32],
+//              where kind 0 has children indices and kind 1 has a triangle range.
+// Every section is a multiple of 4 bytes, so a memory mapped file can be read as `f32`/`u32` slices directly.
+const MAGIC: &[u8; 8] = b"RTMESH\0\0";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 40;
+const VERTEX_SIZE: usize = 8;
+const NODE_SIZE: usize = 9;
+const HAS_COLORS: u32 = 1;
+
+// Vertices and colors are copied from the file as they are, see `Words::floats`.
+const _: () = assert!(std::mem::size_of::<Vertex>() == 4 * VERTEX_SIZE && std::mem::align_of::<Vertex>() == 4);
+const _: () = assert!(std::mem::size_of::<Vec3f>() == 12 && std::mem::align_of::<Vec3f>() == 4);
+
+impl Mesh {
+    pub fn write_binary(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        let flags = if self.colors.is_empty() { 0 } else { HAS_COLORS };
+        writer.write_all(&flags.to_le_bytes())?;
+        let nodes = self.bvh.get_nodes();
+        for count in [self.vertices.len(), self.triangles.len(), nodes.len()] {
+            writer.write_all(&(count as u64).to_le_bytes())?;
+        }
+
+        for vertex in &self.vertices {
+            for x in vertex.position.iter().chain(&vertex.normal).chain(&vertex.uv_coord) {
+                writer.write_all(&x.to_le_bytes())?;
+            }
+        }
+        for x in self.colors.iter().flatten() {
+            writer.write_all(&x.to_le_bytes())?;
+        }
+        for triangle in &self.triangles {
+            for &index in triangle {
+                writer.write_all(&(index as u32).to_le_bytes())?;
+            }
+        }
+        for node in nodes {
+            for x in node.aabb.min.iter().chain(&node.aabb.max) {
+                writer.write_all(&x.to_le_bytes())?;
+            }
+            let (kind, (first, second)) = match node.content {
+                NodeContent::Children(children) => (0u32, children),
+                NodeContent::Triangles(range) => (1u32, range),
+            };
+            for x in [kind, first as u32, second as u32] {
+                writer.write_all(&x.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn save_binary(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_binary(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<Mesh, MeshLoadError> {
+        let invalid = |message: &str| MeshLoadError::InvalidBinary(message.to_string());
+
+        if bytes.len() < HEADER_SIZE || &bytes[0..8] != MAGIC {
+            return Err(invalid("not a binary mesh"));
+        }
+        let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        if version != VERSION {
+            return Err(MeshLoadError::InvalidBinary(format!("unsupported binary mesh version {version}")));
+        }
+        let flags = u32::from_le_bytes(bytes[12..16].try_into().unwrap());
+        let count = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize;
+        let (vertex_count, triangle_count, node_count) = (count(16), count(24), count(32));
+
+        let color_words = if flags & HAS_COLORS != 0 { vertex_count.checked_mul(3) } else { Some(0) };
+        let words = vertex_count.checked_mul(VERTEX_SIZE)
+            .and_then(|vertices| vertices.checked_add(color_words?))
+            .zip(triangle_count.checked_mul(3))
+            .zip(node_count.checked_mul(NODE_SIZE))
+            .and_then(|((vertices, triangles), nodes)| vertices.checked_add(triangles)?.checked_add(nodes));
+        if words.and_then(|words| words.checked_mul(4)) != Some(bytes.len() - HEADER_SIZE) {
+            return Err(invalid("the size of the file does not match its header"));
+        }
+
+        let words = Words::new(&bytes[HEADER_SIZE..]);
+        let color_words = color_words.unwrap();
+        let vertex_words = vertex_count * VERTEX_SIZE + color_words;
+        let triangle_words = triangle_count * 3;
+
+        // Safety: `Vertex` and `Vec3f` are `repr(C)` structs of f32, checked above.
+        let vertices = unsafe {
+            words.floats(0, vertex_count, |x| Vertex {
+                position: Vec3f::new(x(0), x(1), x(2)),
+                normal: Vec3f::new(x(3), x(4), x(5)),
+                uv_coord: Vec2f::new(x(6), x(7)),
+            })
+        };
+        let colors = unsafe { words.floats(vertex_count * VERTEX_SIZE, color_words / 3, |x| Vec3f::new(x(0), x(1), x(2))) };
+
+        let triangles = words.with_u32s(vertex_words, triangle_words, |indices| {
+            if indices.iter().max().is_some_and(|&index| index as usize >= vertex_count) {
+                return Err(invalid("a triangle index is out of range"));
+            }
+            Ok(indices.chunks_exact(3).map(|triangle| [0, 1, 2].map(|j| triangle[j] as usize)).collect())
+        })?;
+
+        let nodes = words.with_u32s(vertex_words + triangle_words, node_count * NODE_SIZE, |nodes| {
+            if nodes.chunks_exact(NODE_SIZE).any(|node| node[6] > 1) {
+                return Err(invalid("invalid BVH node"));
+            }
+            Ok(nodes.chunks_exact(NODE_SIZE).map(|node| {
+                let x = |j: usize| f32::from_bits(node[j]);
+                let children = (node[7] as usize, node[8] as usize);
+                BVHNode {
+                    aabb: AABB { min: Vec3f::new(x(0), x(1), x(2)), max: Vec3f::new(x(3), x(4), x(5)) },
+                    content: if node[6] == 0 { NodeContent::Children(children) } else { NodeContent::Triangles(children) },
+                }
+            }).collect())
+        })?;
+
+        Ok(Mesh {
+            vertices,
+            triangles,
+            bvh: BVH::from_nodes(nodes, triangle_count).map_err(MeshLoadError::InvalidBinary)?,
+            colors,
+            source: None,
+        })
+    }
+
+    pub fn load_binary(path: impl AsRef<Path>) -> Result<Mesh, MeshLoadError> {
+        let file = File::open(&path)?;
+        // Safety: the mapping is only read while the file is open, like the other loaders we don't
+        // protect against another process truncating the file in the meantime.
+        let bytes = unsafe { memmap2::MmapOptions::new().populate().map(&file)? };
+        let mut mesh = Mesh::from_binary(&bytes)?;
+        mesh.source = Some(MeshSource { path: path.as_ref().to_path_buf() });
+        Ok(mesh)
+    }
+
+    // Loads a mesh through a binary cache next to it (`lucy.obj` is cached in `lucy.obj.rtmesh`).
+    // The cache is used when it is newer than the mesh, otherwise the mesh is loaded and the cache rewritten.
+    pub fn load_cached(path: impl AsRef<Path>) -> Result<Mesh, MeshLoadError> {
+        let path = path.as_ref();
+        if path.extension().is_some_and(|extension| extension == "rtmesh") {
+            return Mesh::load_binary(path);
+        }
+
+        let cache = cache_path(path);
+        let modified = |path: &Path| path.metadata().and_then(|metadata| metadata.modified()).ok();
+        if let (Some(source_time), Some(cache_time)) = (modified(path), modified(&cache)) {
+            if cache_time >= source_time {
+                if let Ok(mut mesh) = Mesh::load_binary(&cache) {
+                    mesh.source = Some(MeshSource { path: path.to_path_buf() });
+                    return Ok(mesh);
+                }
+            }
+        }
+
+        let mesh = Mesh::load(path)?;
+        // The cache is only an optimisation, a read-only directory shouldn't prevent loading.
+        let _ = mesh.save_binary(&cache);
+        Ok(mesh)
+    }
+}
+
+// The full file name is kept so that `lucy.obj` and `lucy.ply` don't share a cache.
+pub fn cache_path(path: &Path) -> PathBuf {
+    let mut cache = path.as_os_str().to_owned();
+    cache.push(".rtmesh");
+    PathBuf::from(cache)
+}
+
+// Reads the little endian words of a binary mesh, straight from the buffer when it is suitably aligned.
+enum Words<'a> {
+    Aligned(&'a [u32]),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> Words<'a> {
+    fn new(bytes: &'a [u8]) -> Words<'a> {
+        // Safety: every bit pattern is a valid u32.
+        let (prefix, words, _) = unsafe { bytes.align_to::<u32>() };
+        if cfg!(target_endian = "little") && prefix.is_empty() {
+            Words::Aligned(words)
+        } else {
+            Words::Bytes(bytes)
+        }
+    }
+
+    #[inline]
+    fn u32(&self, index: usize) -> u32 {
+        match self {
+            Words::Aligned(words) => words[index],
+            Words::Bytes(bytes) => u32::from_le_bytes(bytes[4 * index..4 * index + 4].try_into().unwrap()),
+        }
+    }
+
+    #[inline]
+    fn f32(&self, index: usize) -> f32 {
+        f32::from_bits(self.u32(index))
+    }
+
+    // Calls `f` with `count` words starting at the word `offset`, in place when they are aligned.
+    fn with_u32s<R>(&self, offset: usize, count: usize, f: impl FnOnce(&[u32]) -> R) -> R {
+        match self {
+            Words::Aligned(words) => f(&words[offset..offset + count]),
+            Words::Bytes(_) => f(&(offset..offset + count).map(|i| self.u32(i)).collect::<Vec<_>>()),
+        }
+    }
+
+    // Reads `count` values of `T` starting at the word `offset`, with a single copy when the words are aligned.
+    // Safety: `T` must be a `repr(C)` struct made of f32 only, for which every bit pattern is valid.
+    unsafe fn floats<T: Copy>(&self, offset: usize, count: usize, from_floats: impl Fn(&dyn Fn(usize) -> f32) -> T) -> Vec<T> {
+        let size = std::mem::size_of::<T>() / 4;
+        match self {
+            Words::Aligned(words) => std::slice::from_raw_parts(words[offset..offset + count * size].as_ptr().cast::<T>(), count).to_vec(),
+            Words::Bytes(_) => (0..count).map(|i| from_floats(&|j| self.f32(offset + i * size + j))).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let mut mesh = Mesh::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/assets/monkey.obj")).unwrap();
+        mesh.colors = mesh.vertices.iter().map(|vertex| vertex.normal).collect();
+        let mut bytes = Vec::new();
+        mesh.write_binary(&mut bytes).unwrap();
+
+        let loaded = Mesh::from_binary(&bytes).unwrap();
+        assert_eq!(loaded.triangles, mesh.triangles);
+        assert_eq!(loaded.vertices.len(), mesh.vertices.len());
+        assert_eq!(loaded.vertices[10].position, mesh.vertices[10].position);
+        assert_eq!(loaded.colors, mesh.colors);
+        assert_eq!(loaded.bvh.get_nodes().len(), mesh.bvh.get_nodes().len());
+
+        assert!(Mesh::from_binary(&bytes[..bytes.len() - 4]).is_err());
+
+        // Files with the right size but indices out of range, or nodes that don't form a tree, are rejected too.
+        let triangles = HEADER_SIZE + 4 * mesh.vertices.len() * (VERTEX_SIZE + 3);
+        let nodes = triangles + 12 * mesh.triangles.len();
+        let leaf = mesh.bvh.get_nodes().iter().position(|node| matches!(node.content, NodeContent::Triangles(_))).unwrap();
+        let tampered = |offset: usize, value: u32| {
+            let mut bytes = bytes.clone();
+            bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            Mesh::from_binary(&bytes)
+        };
+        assert!(tampered(triangles + 4, mesh.vertices.len() as u32).is_err());
+        assert!(tampered(nodes + 28, mesh.bvh.get_nodes().len() as u32).is_err());
+        // The root is its own child.
+        assert!(tampered(nodes + 28, 0).is_err());
+        assert!(tampered(nodes + 36 * leaf + 32, mesh.triangles.len() as u32 + 1).is_err());
+
+        // Each level has a leaf and the next level, down to `depth`.
+        let chain = |depth: usize| (0..2 * depth - 1).map(|i| BVHNode {
+            aabb: AABB::EMPTY,
+            content: if i % 2 == 0 && i < 2 * depth - 2 { NodeContent::Children((i + 2, i + 1)) } else { NodeContent::Triangles((0, 0)) },
+        }).collect();
+        assert!(BVH::from_nodes(chain(BVH::MAX_DEPTH), 0).is_ok());
+        assert!(BVH::from_nodes(chain(BVH::MAX_DEPTH + 1), 0).is_err());
+    }
+
+    #[test]
+    fn test_mesh_cache() {
+        let directory = TempDir::new("mesh_cache");
+        let path = directory.join("triangle.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+        let mesh = Mesh::load_cached(&path).unwrap();
+        assert_eq!(cache_path(&path), directory.join("triangle.obj.rtmesh"));
+        assert!(cache_path(&path).exists());
+
+        let cached = Mesh::load_cached(&path).unwrap();
+        assert_eq!(cached.triangles, mesh.triangles);
+        assert_eq!(cached.source.unwrap().path, path);
+
+        // A mesh edited after its cache was written is parsed again.
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\nf 2 4 3\n").unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(10);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        assert_eq!(Mesh::load_cached(&path).unwrap().triangles.len(), 2);
+    }
+}
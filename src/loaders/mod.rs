@@ -0,0 +1,145 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Deserialize};
+
+use crate::Mesh;
+
+mod binary;
+mod normals;
+mod obj;
+mod ply;
+mod stl;
+pub use binary::*;
+pub use normals::*;
+pub use obj::*;
+
+// Where a mesh was loaded from, so that scenes can refer to the file instead of embedding the geometry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeshSource {
+    pub path: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum MeshLoadError {
+    Io(std::io::Error),
+    Obj(tobj::LoadError),
+    InvalidObj(String),
+    InvalidPly(String),
+    InvalidStl(String),
+    InvalidGltf(String),
+    UnsupportedFormat(PathBuf),
+    InvalidBinary(String),
+}
+
+impl Display for MeshLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshLoadError::Io(error) => write!(f, "{error}"),
+            MeshLoadError::Obj(error) => write!(f, "invalid OBJ file: {error}"),
+            MeshLoadError::InvalidObj(message) => write!(f, "invalid OBJ file: {message}"),
+            MeshLoadError::InvalidPly(message) => write!(f, "invalid PLY file: {message}"),
+            MeshLoadError::InvalidStl(message) => write!(f, "invalid STL file: {message}"),
+            MeshLoadError::InvalidGltf(message) => write!(f, "invalid glTF file: {message}"),
+            MeshLoadError::UnsupportedFormat(path) => write!(f, "unsupported mesh format: {}", path.display()),
+            MeshLoadError::InvalidBinary(message) => write!(f, "invalid binary mesh: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for MeshLoadError {}
+
+impl From<std::io::Error> for MeshLoadError {
+    fn from(error: std::io::Error) -> Self {
+        MeshLoadError::Io(error)
+    }
+}
+
+impl From<tobj::LoadError> for MeshLoadError {
+    fn from(error: tobj::LoadError) -> Self {
+        MeshLoadError::Obj(error)
+    }
+}
+
+impl Mesh {
+    // Loads a mesh file, picking the format from its extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Mesh, MeshLoadError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("obj") => Mesh::load_obj(path),
+            Some("ply") => Mesh::load_ply(path),
+            Some("stl") => Mesh::load_stl(path),
+            Some("rtmesh") => Mesh::load_binary(path),
+            _ => Err(MeshLoadError::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+}
+
+// Expresses `path` relative to the directory `base`, falling back to the absolute path when they share no root.
+// Paths that don't exist, e.g. files removed since, are relative to the current directory rather than to `base`.
+pub(crate) fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let resolve = |path: &Path| path.canonicalize().or_else(|_| std::path::absolute(path));
+    let (Ok(path), Ok(base)) = (resolve(path), resolve(base)) else {
+        return path.to_path_buf();
+    };
+
+    let common = path.components().zip(base.components()).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return path;
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in base.components().skip(common) {
+        relative.push("..");
+    }
+    for component in path.components().skip(common) {
+        relative.push(component);
+    }
+    relative
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::{Camera, ConstantEnvironment, Lambertian, Mat4f, Object, SaveOptions, Scene, Vec3f};
+
+    #[test]
+    fn test_scene_references_mesh_files() {
+        let mut scene = Scene::new(Camera::new(Vec3f::zeros(), Vec3f::z(), Vec3f::y(), 1.0),
+            Box::new(ConstantEnvironment { color: Vec3f::zeros() }));
+        // Copied so that loading the scene writes the cache of the mesh in the temporary directory.
+        let directory = TempDir::new("scene_references_mesh_files");
+        std::fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/assets/cube.obj"), directory.join("cube.obj")).unwrap();
+        let cube = Mesh::load(directory.join("cube.obj")).unwrap();
+        let triangle_count = cube.triangles.len();
+        let mesh = scene.add_mesh(cube);
+        let material = scene.add_material(Box::new(Lambertian { color: Vec3f::zeros(), emission: Vec3f::zeros() }));
+        scene.add_object(Object::new(mesh, Mat4f::identity(), material).unwrap());
+
+        let path = directory.join("scene.json");
+
+        scene.save(&path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let source = json["meshes"][0]["value"]["source"]["path"].as_str().unwrap();
+        assert!(Path::new(source).is_relative());
+        assert!(source.ends_with("cube.obj"));
+
+        let loaded = Scene::load(&path).unwrap();
+        assert_eq!(loaded.meshes()[mesh].triangles.len(), triangle_count);
+        assert!(directory.join("cube.obj.rtmesh").exists());
+
+        scene.save_with_options(&path, &SaveOptions { embed_meshes: true }).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(json["meshes"][0]["value"]["vertices"].is_array());
+    }
+
+    #[test]
+    fn test_relative_path_of_missing_file() {
+        // Tests run from the directory of the crate.
+        assert_eq!(relative_path(Path::new("src/missing.obj"), Path::new("src")), Path::new("missing.obj"));
+        assert_eq!(relative_path(Path::new("src/missing.obj"), Path::new("examples")), Path::new("../src/missing.obj"));
+    }
+}
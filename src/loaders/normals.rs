@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_3;
+
+use crate::{Vec3f, Vertex};
+
+// How vertex normals are computed for meshes whose file doesn't provide them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normals {
+    // Averages the normals of the triangles around each vertex, weighted by their area and by the angle of their corner.
+    // Triangles meeting at more than `crease_angle` radians keep separate normals, which keeps hard edges sharp.
+    Smooth { crease_angle: f32 },
+    // Every triangle uses its own normal.
+    Flat,
+}
+
+impl Default for Normals {
+    fn default() -> Self {
+        Normals::Smooth { crease_angle: FRAC_PI_3 }
+    }
+}
+
+// Returns the vertices with their normals replaced, and the triangles indexing them.
+// Vertices are duplicated where triangles sharing them need different normals.
+pub fn compute_normals(vertices: &[Vertex], triangles: &[[usize;3]], normals: Normals) -> (Vec<Vertex>, Vec<[usize;3]>) {
+    let face_normals: Vec<Vec3f> = triangles.iter().map(|&[i,j,k]| {
+        let (a, b, c) = (vertices[i].position, vertices[j].position, vertices[k].position);
+        (b - a).cross(&(c - a))
+    }).collect();
+    let unit = |normal: &Vec3f| normal.try_normalize(0.0).unwrap_or(Vec3f::zeros());
+
+    let mut new_vertices = Vec::with_capacity(vertices.len());
+    let mut new_triangles = Vec::with_capacity(triangles.len());
+
+    let Normals::Smooth { crease_angle } = normals else {
+        for (triangle, normal) in triangles.iter().zip(&face_normals) {
+            let normal = unit(normal);
+            new_triangles.push(triangle.map(|i| {
+                new_vertices.push(Vertex { normal, ..vertices[i] });
+                new_vertices.len() - 1
+            }));
+        }
+        return (new_vertices, new_triangles);
+    };
+
+    // Vertices split on uv seams are still smoothed together, so corners are grouped by position.
+    let mut positions = HashMap::new();
+    let position_ids: Vec<usize> = vertices.iter().map(|vertex| {
+        let bits = vertex.position.map(f32::to_bits);
+        let next_id = positions.len();
+        *positions.entry((bits.x, bits.y, bits.z)).or_insert(next_id)
+    }).collect();
+
+    let mut corners = vec![Vec::new(); positions.len()];
+    for (t, triangle) in triangles.iter().enumerate() {
+        for (c, &i) in triangle.iter().enumerate() {
+            corners[position_ids[i]].push((t, c));
+        }
+    }
+
+    let corner_angle = |t: usize, c: usize| {
+        let triangle = triangles[t];
+        let origin = vertices[triangle[c]].position;
+        let (a, b) = (vertices[triangle[(c + 1) % 3]].position - origin, vertices[triangle[(c + 2) % 3]].position - origin);
+        let angle = a.angle(&b);
+        if angle.is_finite() { angle } else { 0.0 }
+    };
+
+    let cos_crease = crease_angle.cos();
+    let mut indices = HashMap::new();
+    for (t, triangle) in triangles.iter().enumerate() {
+        let face_normal = unit(&face_normals[t]);
+        let mut new_triangle = [0; 3];
+        for (c, &i) in triangle.iter().enumerate() {
+            let normal: Vec3f = corners[position_ids[i]].iter()
+                .filter(|&&(s, _)| unit(&face_normals[s]).dot(&face_normal) >= cos_crease)
+                .map(|&(s, corner)| corner_angle(s, corner) * face_normals[s])
+                .sum();
+            let normal = normal.try_normalize(0.0).unwrap_or(face_normal);
+
+            let bits = normal.map(f32::to_bits);
+            new_triangle[c] = *indices.entry((i, bits.x, bits.y, bits.z)).or_insert_with(|| {
+                new_vertices.push(Vertex { normal, ..vertices[i] });
+                new_vertices.len() - 1
+            });
+        }
+        new_triangles.push(new_triangle);
+    }
+
+    (new_vertices, new_triangles)
+}
+
+// Carries per-vertex colors over to the vertices returned by `compute_normals`.
+pub(crate) fn remap_colors(colors: &[Vec3f], triangles: &[[usize;3]], new_triangles: &[[usize;3]], new_vertex_count: usize) -> Vec<Vec3f> {
+    let mut new_colors = vec![Vec3f::zeros(); new_vertex_count];
+    for (old, new) in triangles.iter().zip(new_triangles) {
+        for (&i, &j) in old.iter().zip(new) {
+            new_colors[j] = colors[i];
+        }
+    }
+    new_colors
+}
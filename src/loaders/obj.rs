@@ -0,0 +1,112 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::loaders::{compute_normals, MeshLoadError, MeshSource, Normals};
+use crate::{Mesh, Vec2f, Vec3f, Vertex};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ObjOptions {
+    // Used when the file has no normals.
+    pub normals: Normals,
+}
+
+pub(crate) fn tobj_load_options() -> tobj::LoadOptions {
+    tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
+        ..tobj::LoadOptions::default()
+    }
+}
+
+impl Mesh {
+    pub fn load_obj(path: &(impl AsRef<OsStr> + ?Sized)) -> Result<Mesh, MeshLoadError> {
+        Mesh::load_obj_with_options(path, &ObjOptions::default())
+    }
+
+    // Loads a file holding a single object, use `Import::load_obj` for files with several objects.
+    pub fn load_obj_with_options(path: &(impl AsRef<OsStr> + ?Sized), options: &ObjOptions) -> Result<Mesh, MeshLoadError> {
+        let path = Path::new(path);
+        let (models, _) = tobj::load_obj(path, &tobj_load_options())?;
+        let [model] = &models[..] else {
+            return Err(MeshLoadError::InvalidObj(format!("expected a single object, found {}", models.len())));
+        };
+
+        let mut mesh = Mesh::from_tobj(&model.mesh, options.normals)?;
+        mesh.source = Some(MeshSource { path: path.to_path_buf() });
+        Ok(mesh)
+    }
+
+    // Builds a mesh from a model loaded with `single_index`, normals are computed when the model has none
+    // and uvs default to zero.
+    pub(crate) fn from_tobj(mesh: &tobj::Mesh, normals: Normals) -> Result<Mesh, MeshLoadError> {
+        let tobj::Mesh {positions, indices, texcoords, normals: file_normals, ..} = mesh;
+        let invalid = |message: String| Err(MeshLoadError::InvalidObj(message));
+
+        if positions.len() % 3 != 0 {
+            return invalid(format!("the position array's length is not a multiple of 3 ({})", positions.len()));
+        }
+        if indices.len() % 3 != 0 {
+            return invalid(format!("the index array's length is not a multiple of 3 ({})", indices.len()));
+        }
+        let vertex_count = positions.len() / 3;
+        if !file_normals.is_empty() && file_normals.len() != 3 * vertex_count {
+            return invalid(format!("{} normal values for {vertex_count} vertices", file_normals.len()));
+        }
+        if !texcoords.is_empty() && texcoords.len() != 2 * vertex_count {
+            return invalid(format!("{} uv values for {vertex_count} vertices", texcoords.len()));
+        }
+        if let Some(&index) = indices.iter().find(|&&index| index as usize >= vertex_count) {
+            return invalid(format!("vertex index {index} is out of range, there are {vertex_count} vertices"));
+        }
+
+        let vertices: Vec<_> = (0..vertex_count).map(|i| Vertex {
+            position: Vec3f::new(positions[3*i], positions[3*i+1], positions[3*i+2]),
+            normal: file_normals.get(3*i..3*i+3).map(|n| Vec3f::new(n[0], n[1], n[2])).unwrap_or(Vec3f::zeros()),
+            uv_coord: texcoords.get(2*i..2*i+2).map(|uv| Vec2f::new(uv[0], uv[1])).unwrap_or(Vec2f::zeros()),
+        }).collect();
+
+        let triangles: Vec<_> = indices.chunks_exact(3)
+                                       .map(|triangle| [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize])
+                                       .collect();
+
+        let (vertices, triangles) = if file_normals.is_empty() {
+            compute_normals(&vertices, &triangles, normals)
+        } else {
+            (vertices, triangles)
+        };
+        Ok(Mesh::new(vertices, triangles))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_generated_normals() {
+        let directory = TempDir::new("generated_normals");
+        let path = directory.join("cube.obj");
+        std::fs::write(&path, "\
+v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4 1 5 8\n").unwrap();
+
+        // The cube's edges are sharper than the crease angle, so every face keeps its own normal.
+        let mesh = Mesh::load_obj(&path).unwrap();
+        assert_eq!(mesh.triangles.len(), 12);
+        assert_eq!(mesh.vertices.len(), 24);
+        assert!(mesh.vertices.iter().all(|vertex| [0.0, 1.0].contains(&vertex.normal.abs().max())));
+
+        let options = ObjOptions { normals: Normals::Smooth { crease_angle: std::f32::consts::PI } };
+        let mesh = Mesh::load_obj_with_options(&path, &options).unwrap();
+        assert_eq!(mesh.vertices.len(), 8);
+        let corner = mesh.vertices.iter().find(|vertex| vertex.position == Vec3f::zeros()).unwrap();
+        assert!((corner.normal - Vec3f::repeat(-1.0).normalize()).norm() < 1e-5);
+
+        let flat = ObjOptions { normals: Normals::Flat };
+        assert_eq!(Mesh::load_obj_with_options(&path, &flat).unwrap().vertices.len(), 36);
+
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nf 1 2 3\n").unwrap();
+        assert!(matches!(Mesh::load_obj(&path), Err(MeshLoadError::InvalidObj(_)) | Err(MeshLoadError::Obj(_))));
+    }
+}
@@ -0,0 +1,343 @@
+use std::path::Path;
+use std::str::SplitAsciiWhitespace;
+
+use crate::loaders::{compute_normals, remap_colors, MeshLoadError, MeshSource, Normals};
+use crate::{Mesh, Vec2f, Vec3f, Vertex};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    I8, U8, I16, U16, I32, U32, F32, F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // Colors stored as integers are normalized to [0, 1].
+    fn max(self) -> f64 {
+        match self {
+            Scalar::U8 => u8::MAX as f64,
+            Scalar::U16 => u16::MAX as f64,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar { name: String, scalar: Scalar },
+    List { name: String, count: Scalar, item: Scalar },
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// Reads the values of the body one after the other, whatever the format.
+enum Values<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl Values<'_> {
+    fn next(&mut self, scalar: Scalar) -> Result<f64, MeshLoadError> {
+        let truncated = || MeshLoadError::InvalidPly("the file ends before its last element".to_string());
+        match self {
+            Values::Ascii(tokens) => {
+                let token = tokens.next().ok_or_else(truncated)?;
+                token.parse().map_err(|_| MeshLoadError::InvalidPly(format!("invalid number: {token}")))
+            }
+            Values::Binary { bytes, big_endian } => {
+                let size = scalar.size();
+                if bytes.len() < size {
+                    return Err(truncated());
+                }
+                let (value, rest) = bytes.split_at(size);
+                *bytes = rest;
+
+                let mut buffer = [0; 8];
+                buffer[..size].copy_from_slice(value);
+                if *big_endian {
+                    buffer[..size].reverse();
+                }
+                let value = match scalar {
+                    Scalar::I8 => buffer[0] as i8 as f64,
+                    Scalar::U8 => buffer[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    Scalar::U32 => u32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    Scalar::F32 => f32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    Scalar::F64 => f64::from_le_bytes(buffer),
+                };
+                Ok(value)
+            }
+        }
+    }
+}
+
+// List lengths and vertex indices, which would otherwise be cast to the wrong count or vertex.
+fn to_index(value: f64) -> Result<usize, MeshLoadError> {
+    if value >= 0.0 && value.fract() == 0.0 {
+        Ok(value as usize)
+    } else {
+        Err(MeshLoadError::InvalidPly(format!("invalid count or index: {value}")))
+    }
+}
+
+// Reads one instance of `element`, scalar properties go to `row` and the list at index `list` to `polygon`.
+fn read_element(values: &mut Values, element: &Element, list: Option<usize>, row: &mut Vec<f64>, polygon: &mut Vec<usize>) -> Result<(), MeshLoadError> {
+    row.clear();
+    polygon.clear();
+    for (i, property) in element.properties.iter().enumerate() {
+        match property {
+            Property::Scalar { scalar, .. } => row.push(values.next(*scalar)?),
+            Property::List { count, item, .. } => {
+                row.push(0.0);
+                for _ in 0..to_index(values.next(*count)?)? {
+                    let value = values.next(*item)?;
+                    if list == Some(i) {
+                        polygon.push(to_index(value)?);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_header(header: &str) -> Result<(Format, Vec<Element>), MeshLoadError> {
+    let invalid = |message: String| MeshLoadError::InvalidPly(message);
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err(invalid("missing `ply` magic number".to_string()));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<_> = line.split_ascii_whitespace().collect();
+        match words[..] {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(Format::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid(format!("invalid element count: {line}")))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let (Some(count), Some(item)) = (Scalar::parse(count), Scalar::parse(item)) else {
+                    return Err(invalid(format!("invalid property: {line}")));
+                };
+                let element = elements.last_mut().ok_or_else(|| invalid(format!("property outside of an element: {line}")))?;
+                element.properties.push(Property::List { name: name.to_string(), count, item });
+            }
+            ["property", scalar, name] => {
+                let scalar = Scalar::parse(scalar).ok_or_else(|| invalid(format!("invalid property: {line}")))?;
+                let element = elements.last_mut().ok_or_else(|| invalid(format!("property outside of an element: {line}")))?;
+                element.properties.push(Property::Scalar { name: name.to_string(), scalar });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(invalid(format!("unexpected header line: {line}"))),
+        }
+    }
+
+    let format = format.ok_or_else(|| invalid("missing format".to_string()))?;
+    Ok((format, elements))
+}
+
+impl Mesh {
+    pub fn load_ply(path: impl AsRef<Path>) -> Result<Mesh, MeshLoadError> {
+        let path = path.as_ref();
+        let mut mesh = Mesh::from_ply(&std::fs::read(path)?)?;
+        mesh.source = Some(MeshSource { path: path.to_path_buf() });
+        Ok(mesh)
+    }
+
+    // Reads the `vertex` and `face` elements of an ASCII or binary PLY file, other elements are skipped.
+    // Vertices can have normals (`nx`, `ny`, `nz`), uvs (`u`/`v`, `s`/`t` or `texture_u`/`texture_v`) and colors
+    // (`red`, `green`, `blue`). Polygons are split in triangle fans and missing normals are computed.
+    pub fn from_ply(bytes: &[u8]) -> Result<Mesh, MeshLoadError> {
+        // The header stops at the first line that is `end_header`, comments can contain the words, and the body
+        // starts on the next line.
+        let mut start = 0;
+        let (end, body) = loop {
+            let line_end = bytes[start..].iter().position(|&byte| byte == b'\n').map(|i| start + i + 1).unwrap_or(bytes.len());
+            if bytes[start..line_end].trim_ascii() == b"end_header" {
+                break (start, &bytes[line_end..]);
+            }
+            if line_end == bytes.len() {
+                return Err(MeshLoadError::InvalidPly("missing `end_header`".to_string()));
+            }
+            start = line_end;
+        };
+        let header = std::str::from_utf8(&bytes[..end]).map_err(|_| MeshLoadError::InvalidPly("the header is not valid text".to_string()))?;
+        let (format, elements) = parse_header(header)?;
+
+        let mut values = match format {
+            Format::Ascii => {
+                let text = std::str::from_utf8(body).map_err(|_| MeshLoadError::InvalidPly("the body is not valid text".to_string()))?;
+                Values::Ascii(text.split_ascii_whitespace())
+            }
+            Format::BinaryLittleEndian => Values::Binary { bytes: body, big_endian: false },
+            Format::BinaryBigEndian => Values::Binary { bytes: body, big_endian: true },
+        };
+
+        let mut vertices = Vec::new();
+        let mut colors = Vec::new();
+        let mut triangles = Vec::new();
+        let (mut has_normals, mut has_colors) = (false, false);
+
+        let mut row = Vec::new();
+        let mut polygon = Vec::new();
+        for element in &elements {
+            let scalar_names: Vec<_> = element.properties.iter().map(|property| match property {
+                Property::Scalar { name, .. } => Some(name.as_str()),
+                Property::List { .. } => None,
+            }).collect();
+            let find = |names: &[&str]| names.iter().find_map(|name| scalar_names.iter().position(|property| *property == Some(*name)));
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+                    let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+                    let uv = [find(&["u", "s", "texture_u"]), find(&["v", "t", "texture_v"])];
+                    let color = [find(&["red"]), find(&["green"]), find(&["blue"])];
+                    if position.contains(&None) {
+                        return Err(MeshLoadError::InvalidPly("vertices have no position".to_string()));
+                    }
+                    has_normals = !normal.contains(&None);
+                    has_colors = !color.contains(&None);
+
+                    for _ in 0..element.count {
+                        read_element(&mut values, element, None, &mut row, &mut polygon)?;
+                        let get = |index: Option<usize>| index.map(|i| row[i] as f32).unwrap_or(0.0);
+                        vertices.push(Vertex {
+                            position: Vec3f::new(get(position[0]), get(position[1]), get(position[2])),
+                            normal: Vec3f::new(get(normal[0]), get(normal[1]), get(normal[2])),
+                            uv_coord: Vec2f::new(get(uv[0]), get(uv[1])),
+                        });
+                        if has_colors {
+                            let channel = |index: Option<usize>| {
+                                let i = index.unwrap();
+                                let Property::Scalar { scalar, .. } = element.properties[i] else { unreachable!() };
+                                (row[i] / scalar.max()) as f32
+                            };
+                            colors.push(Vec3f::new(channel(color[0]), channel(color[1]), channel(color[2])));
+                        }
+                    }
+                }
+                "face" => {
+                    let indices = element.properties.iter().position(|property| matches!(property,
+                        Property::List { name, .. } if name == "vertex_indices" || name == "vertex_index"));
+                    if indices.is_none() {
+                        return Err(MeshLoadError::InvalidPly("faces have no `vertex_indices`".to_string()));
+                    }
+
+                    for _ in 0..element.count {
+                        read_element(&mut values, element, indices, &mut row, &mut polygon)?;
+                        for k in 2..polygon.len() {
+                            triangles.push([polygon[0], polygon[k - 1], polygon[k]]);
+                        }
+                    }
+                }
+                _ => {
+                    for _ in 0..element.count {
+                        read_element(&mut values, element, None, &mut row, &mut polygon)?;
+                    }
+                }
+            }
+        }
+
+        if let Some(index) = triangles.iter().flatten().find(|&&index| index >= vertices.len()) {
+            return Err(MeshLoadError::InvalidPly(format!("vertex index {index} is out of range, there are {} vertices", vertices.len())));
+        }
+
+        let mut mesh = if has_normals {
+            Mesh::new(vertices, triangles)
+        } else {
+            // Computing normals may duplicate vertices, colors follow the vertex they were copied from.
+            let (new_vertices, new_triangles) = compute_normals(&vertices, &triangles, Normals::default());
+            if has_colors {
+                colors = remap_colors(&colors, &triangles, &new_triangles, new_vertices.len());
+            }
+            Mesh::new(new_vertices, new_triangles)
+        };
+        mesh.colors = colors;
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TETRAHEDRON: [[f32;3];4] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+    #[test]
+    fn test_ply_formats() {
+        let header = |format: &str| format!("ply\nformat {format} 1.0\ncomment test\nobj_info written before end_header\n\
+element vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+property float s\nproperty float t\nproperty uchar red\nproperty uchar green\nproperty uchar blue\n\
+element face 1\nproperty list uchar int vertex_indices\nend_header\n");
+
+        let mut ascii = header("ascii");
+        let mut binary = header("binary_big_endian").into_bytes();
+        for (i, [x, y, z]) in TETRAHEDRON.into_iter().enumerate() {
+            ascii += &format!("{x} {y} {z} 0.5 {i} 255 0 {}\n", 51 * i);
+            binary.extend([x, y, z, 0.5, i as f32].iter().flat_map(|x| x.to_be_bytes()));
+            binary.extend([255, 0, 51 * i as u8]);
+        }
+        ascii += "4 0 1 2 3\n";
+        binary.push(4);
+        binary.extend([0i32, 1, 2, 3].iter().flat_map(|x| x.to_be_bytes()));
+
+        for mesh in [Mesh::from_ply(ascii.as_bytes()).unwrap(), Mesh::from_ply(&binary).unwrap()] {
+            // The quad is split in two triangles.
+            assert_eq!(mesh.triangles.len(), 2);
+            assert_eq!(mesh.colors.len(), mesh.vertices.len());
+            let (i, vertex) = mesh.vertices.iter().enumerate().find(|(_, vertex)| vertex.position == Vec3f::z()).unwrap();
+            assert_eq!(vertex.uv_coord, Vec2f::new(0.5, 3.0));
+            assert!((mesh.colors[i] - Vec3f::new(1.0, 0.0, 0.6)).norm() < 1e-6);
+        }
+
+        assert!(Mesh::from_ply(&binary[..binary.len() - 1]).is_err());
+        assert!(Mesh::from_ply(b"ply\nformat ascii 1.0\ncomment no end_header\n").is_err());
+
+        // Negative or fractional counts and indices are refused rather than cast to another vertex.
+        for face in ["3 0 -1 2\n", "3 0 1.5 2\n", "-3 0 1 2\n"] {
+            assert!(Mesh::from_ply(ascii.replace("4 0 1 2 3\n", face).as_bytes()).is_err(), "{face}");
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::loaders::{compute_normals, MeshLoadError, MeshSource, Normals};
+use crate::{Mesh, Vec2f, Vec3f, Vertex};
+
+impl Mesh {
+    pub fn load_stl(path: impl AsRef<Path>) -> Result<Mesh, MeshLoadError> {
+        let path = path.as_ref();
+        let mut mesh = Mesh::from_stl(&std::fs::read(path)?)?;
+        mesh.source = Some(MeshSource { path: path.to_path_buf() });
+        Ok(mesh)
+    }
+
+    // Reads an ASCII or binary STL file. STL stores every triangle separately, so identical corners are merged
+    // and normals are recomputed with the default crease angle instead of using the often unreliable facet normals.
+    pub fn from_stl(bytes: &[u8]) -> Result<Mesh, MeshLoadError> {
+        // Some binary files also start with `solid`, their size is what tells them apart.
+        let binary_size = bytes.get(80..84).map(|count| 84 + 50 * u32::from_le_bytes(count.try_into().unwrap()) as usize);
+        let positions = if bytes.starts_with(b"solid") && binary_size != Some(bytes.len()) {
+            parse_ascii(bytes)?
+        } else {
+            parse_binary(bytes)?
+        };
+
+        let mut indices = HashMap::new();
+        let mut vertices = Vec::new();
+        let triangles: Vec<[usize;3]> = positions.chunks_exact(3).map(|corners| {
+            [0, 1, 2].map(|c| {
+                let position = corners[c];
+                let bits = position.map(f32::to_bits);
+                *indices.entry((bits.x, bits.y, bits.z)).or_insert_with(|| {
+                    vertices.push(Vertex { position, normal: Vec3f::zeros(), uv_coord: Vec2f::zeros() });
+                    vertices.len() - 1
+                })
+            })
+        }).collect();
+
+        let (vertices, triangles) = compute_normals(&vertices, &triangles, Normals::default());
+        Ok(Mesh::new(vertices, triangles))
+    }
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<Vec<Vec3f>, MeshLoadError> {
+    let invalid = |message: &str| MeshLoadError::InvalidStl(message.to_string());
+    let count = bytes.get(80..84).ok_or_else(|| invalid("the file is too short"))?;
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    if bytes.len() < 84 + 50 * count {
+        return Err(invalid("the file ends before its last triangle"));
+    }
+
+    // Each triangle is a normal, three corners and a 2 bytes attribute.
+    let float = |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let positions = (0..count).flat_map(|t| {
+        let triangle = 84 + 50 * t;
+        (0..3).map(move |c| {
+            let corner = triangle + 12 + 12 * c;
+            Vec3f::new(float(corner), float(corner + 4), float(corner + 8))
+        })
+    }).collect();
+    Ok(positions)
+}
+
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<Vec3f>, MeshLoadError> {
+    let invalid = |message: String| MeshLoadError::InvalidStl(message);
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid("the file is not valid text".to_string()))?;
+
+    let mut positions = Vec::new();
+    let mut corners = 0;
+    for line in text.lines() {
+        let mut words = line.split_ascii_whitespace();
+        match words.next() {
+            Some("vertex") => {
+                let coordinates = words.map(str::parse::<f32>).collect::<Result<Vec<_>, _>>();
+                let Ok(&[x, y, z]) = coordinates.as_deref() else {
+                    return Err(invalid(format!("invalid vertex: {}", line.trim())));
+                };
+                positions.push(Vec3f::new(x, y, z));
+                corners += 1;
+            }
+            Some("endloop") => {
+                if corners != 3 {
+                    return Err(invalid(format!("facets should have 3 vertices, found {corners}")));
+                }
+                corners = 0;
+            }
+            _ => {}
+        }
+    }
+
+    if positions.len() % 3 != 0 {
+        return Err(invalid("the last facet is incomplete".to_string()));
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TETRAHEDRON: [[f32;3];4] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    const FACES: [[usize;3];4] = [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]];
+
+    #[test]
+    fn test_stl_formats() {
+        let mut ascii = String::from("solid tetrahedron\n");
+        let mut binary = vec![0u8; 80];
+        binary.extend((FACES.len() as u32).to_le_bytes());
+        for face in FACES {
+            ascii += "facet normal 0 0 0\nouter loop\n";
+            binary.extend([0u8; 12]);
+            for i in face {
+                let [x, y, z] = TETRAHEDRON[i];
+                ascii += &format!("vertex {x} {y} {z}\n");
+                binary.extend([x, y, z].iter().flat_map(|x| x.to_le_bytes()));
+            }
+            ascii += "endloop\nendfacet\n";
+            binary.extend([0u8; 2]);
+        }
+        ascii += "endsolid tetrahedron\n";
+
+        for mesh in [Mesh::from_stl(ascii.as_bytes()).unwrap(), Mesh::from_stl(&binary).unwrap()] {
+            assert_eq!(mesh.triangles.len(), 4);
+            // Every edge of the tetrahedron is sharper than the crease angle.
+            assert_eq!(mesh.vertices.len(), 12);
+            let bottom = mesh.vertices.iter().find(|vertex| vertex.normal == -Vec3f::z()).unwrap();
+            assert_eq!(bottom.position.z, 0.0);
+        }
+
+        assert!(Mesh::from_stl(&binary[..binary.len() - 1]).is_err());
+    }
+}
@@ -4,6 +4,7 @@ use serde::Serialize;
 use crate::math::*;
 use crate::HitInfo;
 use crate::Ray;
+use crate::Texture;
 
 pub struct ScaterInfo {
     pub ray: Ray,
@@ -12,11 +13,28 @@ pub struct ScaterInfo {
 } 
 
 #[typetag::serde(tag="type")]
-pub trait Material : core::fmt::Debug + Sync {
+pub trait Material : core::fmt::Debug + Sync + CloneMaterial {
     fn scater(&self, in_direction: Vec3f, hit_info: &HitInfo) -> ScaterInfo;
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// Implemented for every material that is `Clone`, so that boxed materials can be cloned too.
+pub trait CloneMaterial {
+    fn clone_material(&self) -> Box<dyn Material>;
+}
+
+impl<M: Material + Clone + 'static> CloneMaterial for M {
+    fn clone_material(&self) -> Box<dyn Material> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Material> {
+    fn clone(&self) -> Self {
+        self.clone_material()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Lambertian {
     pub color : Vec3f,
     pub emission: Vec3f
@@ -35,7 +53,7 @@ impl Material for Lambertian {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dialectric {
     pub refraction_index: f32,
 }
@@ -53,7 +71,7 @@ impl Material for Dialectric {
         let out_tangential = (in_direction + cos * normal) / n;
         let a = 1.0 - out_tangential.norm_squared();
 
-        let direction = if  a < 0.0 || Dialectric::reflectance(cos,n) > rand::random() {
+        let direction = if  a < 0.0 || Dialectric::reflectance(cos,n) > random() {
             reflect(&in_direction, &normal)
         }
         else {
@@ -78,7 +96,7 @@ impl Dialectric {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Metal {
     pub color: Vec3f,
     pub roughness: f32,
@@ -96,3 +114,91 @@ impl Material for Metal {
         }    
     }
 }
+
+// Diffuse, glossy and transmitted lobes mixed together, close to what MTL materials describe.
+// Textures multiply the matching constant, like `map_Kd` multiplies `Kd`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Standard {
+    pub diffuse: Vec3f,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diffuse_texture: Option<Texture>,
+    pub specular: Vec3f,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub specular_texture: Option<Texture>,
+    pub roughness: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roughness_texture: Option<Texture>,
+    // Lowers the roughness where it is bright, like an MTL `map_Ns`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossiness_texture: Option<Texture>,
+    pub opacity: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opacity_texture: Option<Texture>,
+    pub refraction_index: f32,
+    pub emission: Vec3f,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emission_texture: Option<Texture>,
+}
+
+impl Default for Standard {
+    fn default() -> Self {
+        Standard {
+            diffuse: Vec3f::new(0.8, 0.8, 0.8),
+            diffuse_texture: None,
+            specular: Vec3f::zeros(),
+            specular_texture: None,
+            roughness: 1.0,
+            roughness_texture: None,
+            glossiness_texture: None,
+            opacity: 1.0,
+            opacity_texture: None,
+            refraction_index: 1.0,
+            emission: Vec3f::zeros(),
+            emission_texture: None,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Material for Standard {
+    fn scater(&self, in_direction: Vec3f, hit_info: &HitInfo) -> ScaterInfo {
+        let sample = |value: Vec3f, texture: &Option<Texture>| match texture {
+            Some(texture) => value.component_mul(&texture.sample(&hit_info.uv)),
+            None => value,
+        };
+        let emission = sample(self.emission, &self.emission_texture);
+
+        let opacity = sample(Vec3f::repeat(self.opacity), &self.opacity_texture).x;
+        if random::<f32>() >= opacity {
+            let transmitted = Dialectric { refraction_index: self.refraction_index }.scater(in_direction, hit_info);
+            return ScaterInfo { emission, ..transmitted };
+        }
+
+        // Picks the glossy or the diffuse lobe proportionally to their strength.
+        let diffuse = sample(self.diffuse, &self.diffuse_texture);
+        let specular = sample(self.specular, &self.specular_texture);
+        let total = diffuse.max() + specular.max();
+        let specular_probability = if total > 0.0 { specular.max() / total } else { 0.0 };
+
+        if random::<f32>() < specular_probability {
+            let mut roughness = sample(Vec3f::repeat(self.roughness), &self.roughness_texture).x;
+            if let Some(texture) = &self.glossiness_texture {
+                roughness *= 1.0 - texture.sample(&hit_info.uv).x;
+            }
+            let direction = reflect(&in_direction.normalize(), &hit_info.normal) + roughness*random_uniform_unit();
+            ScaterInfo {
+                ray: Ray {direction, origin: hit_info.point},
+                attenuation: specular / specular_probability,
+                emission,
+            }
+        }
+        else {
+            let direction = (random_uniform_unit() + hit_info.normal).normalize();
+            ScaterInfo {
+                ray: Ray {direction, origin: hit_info.point},
+                attenuation: diffuse / (1.0 - specular_probability),
+                emission,
+            }
+        }
+    }
+}
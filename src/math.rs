@@ -1,7 +1,11 @@
 use core::f32;
+use std::cell::RefCell;
 
 pub use nalgebra as na;
-use rand::Rng;
+use rand::distributions::{Distribution, Standard};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 pub type Vec2f = na::Vector2<f32>;
 pub type Vec3f = na::Vector3<f32>;
@@ -25,12 +29,32 @@ pub fn mul_element_wise(v1: Vec3f, v2: Vec3f) -> Vec3f {
     Vec3f::new( v1.x*v2.x,v1.y*v2.y,v1.z*v2.z )
 }
 
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+// Every random number of a render comes from this thread local generator, seeding it makes renders reproducible.
+pub fn seed_random(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
 #[inline]
-pub fn random_uniform_unit() -> Vec3f {
-    let mut rng = rand::thread_rng();
+pub fn random<T>() -> T where Standard: Distribution<T> {
+    RNG.with(|rng| rng.borrow_mut().gen())
+}
+
+// Makes `rng` the generator of the thread while `f` runs, so that workers keep theirs whatever thread they run on.
+pub(crate) fn with_random_generator<R>(rng: &mut StdRng, f: impl FnOnce() -> R) -> R {
+    RNG.with(|current| std::mem::swap(&mut *current.borrow_mut(), rng));
+    let result = f();
+    RNG.with(|current| std::mem::swap(&mut *current.borrow_mut(), rng));
+    result
+}
 
-    let u : f32= rng.gen::<f32>() * 2.0 - 1.0;
-    let theta :f32 = rng.gen::<f32>() * 2.0 * f32::consts::PI;
+#[inline]
+pub fn random_uniform_unit() -> Vec3f {
+    let u : f32= random::<f32>() * 2.0 - 1.0;
+    let theta :f32 = random::<f32>() * 2.0 * f32::consts::PI;
 
     let ring_radius = (1.0-u*u).sqrt();
 
@@ -52,4 +76,34 @@ pub fn translate(translation: &Vec3f) -> Mat4f {
 #[inline] 
 pub fn scale(x:f32, y:f32, z:f32) -> Mat4f {
     Mat4f::from_diagonal(&Vec4f::new(x,y,z,1.0))
-}
\ No newline at end of file
+}
+pub type Quatf = na::UnitQuaternion<f32>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub translation: Vec3f,
+    pub rotation: Quatf,
+    pub scale: Vec3f,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        translation: Vec3f::new(0.0, 0.0, 0.0),
+        rotation: Quatf::new_unchecked(na::Quaternion::new(1.0, 0.0, 0.0, 0.0)),
+        scale: Vec3f::new(1.0, 1.0, 1.0),
+    };
+
+    pub fn new(translation: Vec3f, rotation: Quatf, scale: Vec3f) -> Transform {
+        Transform { translation, rotation, scale }
+    }
+
+    pub fn to_matrix(&self) -> Mat4f {
+        translate(&self.translation) * self.rotation.to_homogeneous() * scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
@@ -0,0 +1,132 @@
+use serde_json::{json, Map, Value};
+
+use crate::texture::for_each_texture_path;
+use crate::{Scene, SceneError};
+
+// `MIGRATIONS[i]` upgrades the layout of a scene from version `i` to version `i + 1`.
+// Scenes saved before versioning was introduced have no `version` field and are version 0.
+const MIGRATIONS: [fn(Value) -> Result<Value, String>; 3] = [
+    migrate_v0_to_v1,
+    // Version 2 lets meshes be `{"source": {"path": ...}}` references, version 1 files are valid as they are.
+    Ok,
+    migrate_v2_to_v3,
+];
+
+pub fn scene_version(scene: &Value) -> u32 {
+    scene.get("version").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+// Upgrades a serialized scene to `Scene::VERSION`, one version at a time.
+pub fn migrate(mut scene: Value) -> Result<Value, SceneError> {
+    let version = scene_version(&scene);
+    if version > Scene::VERSION {
+        return Err(SceneError::UnsupportedVersion(version));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        scene = migration(scene).map_err(|message| SceneError::Migration { from: from as u32, message })?;
+        scene["version"] = json!(from + 1);
+    }
+
+    Ok(scene)
+}
+
+// Version 1 stores meshes, materials and objects in arenas of `{generation, name, value}` slots,
+// and handles as `{index, generation}` instead of plain indices.
+fn migrate_v0_to_v1(mut scene: Value) -> Result<Value, String> {
+    fn handle(value: &mut Value) {
+        if let Some(index) = value.as_u64() {
+            *value = json!({"index": index, "generation": 0});
+        }
+    }
+
+    fn object(value: &mut Value) {
+        handle(&mut value["mesh"]);
+        handle(&mut value["material"]);
+    }
+
+    fn node(value: &mut Value) {
+        if let Some(value) = value.get_mut("Object") {
+            object(value);
+        }
+        if let Some(Value::Array(children)) = value.pointer_mut("/Group/children") {
+            children.iter_mut().for_each(node);
+        }
+    }
+
+    fn arena(scene: &mut Map<String, Value>, key: &str, mut migrate_value: impl FnMut(&mut Value)) -> Result<(), String> {
+        let Some(values) = scene.get_mut(key) else { return Ok(()) };
+        let Value::Array(values) = values else { return Err(format!("`{key}` should be an array")) };
+
+        for value in values.iter_mut() {
+            migrate_value(value);
+            *value = json!({"generation": 0, "value": value.take()});
+        }
+        Ok(())
+    }
+
+    let Value::Object(map) = &mut scene else { return Err("the scene should be a JSON object".to_string()) };
+
+    arena(map, "meshes", |_| {})?;
+    arena(map, "materials", |_| {})?;
+    arena(map, "objects", object)?;
+
+    if let Some(Value::Array(nodes)) = map.get_mut("nodes") {
+        nodes.iter_mut().for_each(node);
+    }
+    if let Some(Value::Object(groups)) = map.get_mut("groups") {
+        for group in groups.values_mut() {
+            if let Some(Value::Array(children)) = group.get_mut("children") {
+                children.iter_mut().for_each(node);
+            }
+        }
+    }
+    for key in ["/animation/objects", "/animation/materials"] {
+        if let Some(Value::Array(tracks)) = scene.pointer_mut(key) {
+            for track in tracks {
+                handle(&mut track[0]);
+            }
+        }
+    }
+
+    Ok(scene)
+}
+
+// Version 3 stores texture paths relative to the scene file instead of the working directory. The paths of older
+// scenes are made absolute, so that they keep pointing to the files they were loaded from.
+fn migrate_v2_to_v3(mut scene: Value) -> Result<Value, String> {
+    let directory = std::env::current_dir().map_err(|error| format!("could not resolve texture paths: {error}"))?;
+    for key in ["materials", "animation"] {
+        if let Some(value) = scene.get_mut(key) {
+            for_each_texture_path(value, &mut |path| *path = directory.join(&*path).to_string_lossy().into_owned());
+        }
+    }
+    Ok(scene)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_migrate_v0() {
+        let scene = json!({
+            "meshes": [{"vertices": [], "triangles": [], "bvh": {"nodes": []}}],
+            "materials": [{"type": "Dialectric", "refraction_index": 1.5}],
+            "objects": [{"transform": [1,0,0,0, 0,1,0,0, 0,0,1,0, 0,0,0,1], "mesh": 0, "material": 0}],
+            "camera": {"origin": [0,0,0], "direction": [0,0,1], "up": [0,1,0], "fov": 1.0},
+            "environment": {"type": "ConstantEnvironment", "color": [0,0,0]}
+        });
+
+        let scene = migrate(scene).unwrap();
+        assert_eq!(scene_version(&scene), Scene::VERSION);
+        assert_eq!(scene["objects"][0]["value"]["mesh"], json!({"index": 0, "generation": 0}));
+        assert_eq!(scene["materials"][0]["value"]["refraction_index"], json!(1.5));
+        assert!(serde_json::from_value::<Scene>(scene).is_ok());
+
+        // Texture paths of version 2 were relative to the working directory.
+        let scene = migrate(json!({"version": 2, "materials": [{"generation": 0, "value": {"type": "Standard", "diffuse_texture": {"path": "red.png"}}}]})).unwrap();
+        let path = scene.pointer("/materials/0/value/diffuse_texture/path").unwrap().as_str().unwrap();
+        assert_eq!(std::path::Path::new(path), std::env::current_dir().unwrap().join("red.png"));
+    }
+}
@@ -0,0 +1,61 @@
+use std::io::Write;
+use std::time::Duration;
+
+use crate::{RenderReport, Vec3f};
+
+// Follows a render as it goes, e.g. to show a progress bar or a preview. The methods are called from the worker
+// threads and do nothing by default, `()` is the observer that ignores everything.
+pub trait RenderObserver: Sync {
+    fn tile_started(&self, _tile: &TileInfo) {}
+    // `pixels` is the tile as rendered so far, row by row, with the samples of this pass and the previous ones.
+    fn tile_finished(&self, _tile: &TileInfo, _pixels: &[Vec3f]) {}
+    fn progress(&self, _progress: &Progress) {}
+    fn pass_finished(&self, _pass: &PassInfo) {}
+    fn render_finished(&self, _report: &RenderReport) {}
+}
+
+impl RenderObserver for () {}
+
+// A tile of a pass, in pixels from the top left corner of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileInfo {
+    pub pass: u32,
+    pub index: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    // Between 0 and 1, estimated from the samples left and the time limit.
+    pub fraction: f32,
+    pub elapsed: Duration,
+    // Time left at the current pace, unknown until something is done.
+    pub eta: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PassInfo {
+    pub pass: u32,
+    // Samples per pixel of this pass and the previous ones.
+    pub samples_per_pixel: u32,
+    // Only estimated with a noise target.
+    pub noise: Option<f32>,
+}
+
+// Prints a progress bar with the time left on the current line of the standard output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProgressBar;
+
+impl RenderObserver for ProgressBar {
+    fn progress(&self, progress: &Progress) {
+        const PROGRESS_BAR_LENGHT : usize = 100;
+        let progress_bar : String = (0..PROGRESS_BAR_LENGHT).map(|i| if i as f32 <= (progress.fraction * PROGRESS_BAR_LENGHT as f32) {'█'} else {'-'})
+                                                            .collect();
+        let eta = progress.eta.map_or_else(|| "?".to_string(), |eta| format!("{}s", eta.as_secs()));
+        print!("\rRendering: [{progress_bar}] {:.2}% ETA {eta}   ", progress.fraction*100.);
+        let _ = std::io::stdout().flush();
+    }
+}
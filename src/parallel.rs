@@ -1,23 +1,173 @@
-use std::sync::Mutex;
-
-pub type Task = Box<dyn FnOnce() -> () + Send>;
-
-pub fn parallel_execute(tasks: impl Iterator<Item=Task> + Send, worker_count: usize) {
-    
-    let tasks = Mutex::new(tasks);
-    
-    std::thread::scope(|s| {
-        for _ in 0..worker_count {
-            s.spawn(|| {
-                loop {
-                    let Some(task) = tasks.lock().unwrap().next() else {break};
-                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(task));
-                    
-                    if let Err(_) = result {
-                        println!("Warning: a task panicked during execution");
+use std::any::Any;
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+
+// Tasks may borrow from the caller, `Workers::execute` only returns once they are all done.
+pub type Task<'a, S> = Box<dyn FnOnce(&mut S) + Send + 'a>;
+
+// What a thread of the pool is sent for a run. It borrows from `Workers::execute`, which waits for it to be dropped.
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// A fixed number of worker threads, started with the pool and stopped when it is dropped, each with its own state
+// kept from one run to the next. Each thread is sent its part of a run through a channel of its own.
+pub struct ThreadPool<S> {
+    workers: Mutex<Vec<S>>,
+    senders: Vec<Sender<Job>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+// The workers of a pool, lent to one user at a time.
+pub struct Workers<'p, S> {
+    states: MutexGuard<'p, Vec<S>>,
+    senders: &'p [Sender<Job>],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskPanicked {
+    pub message: String,
+}
+
+impl<S: Send> ThreadPool<S> {
+    pub fn new(worker_count: usize, new_worker: impl Fn() -> S) -> ThreadPool<S> {
+        let worker_count = worker_count.max(1);
+        let (senders, threads) = (0..worker_count).map(|_| {
+            let (sender, receiver) = mpsc::channel::<Job>();
+            (sender, std::thread::spawn(move || receiver.into_iter().for_each(|job| job())))
+        }).unzip();
+        ThreadPool { workers: Mutex::new((0..worker_count).map(|_| new_worker()).collect()), senders, threads }
+    }
+
+    // Doesn't wait for the workers to be free.
+    pub fn worker_count(&self) -> usize {
+        self.senders.len()
+    }
+
+    // Waits for the workers to be free, users of the same pool take turns.
+    pub fn lock(&self) -> Workers<'_, S> {
+        // Panics are caught in the workers, the states are still usable after one.
+        Workers { states: self.workers.lock().unwrap_or_else(PoisonError::into_inner), senders: &self.senders }
+    }
+}
+
+impl<S> Drop for ThreadPool<S> {
+    fn drop(&mut self) {
+        // The threads stop once their channel is closed.
+        self.senders.clear();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<S: Send> Workers<'_, S> {
+    pub fn states(&mut self) -> &mut [S] {
+        &mut self.states
+    }
+
+    // Runs the tasks, each worker starting with the first ones it was dealt and then taking the last ones of the others.
+    // Tasks are dealt in turn so that they start roughly in order. After a panic the tasks not started yet are dropped.
+    pub fn execute<'a>(&mut self, tasks: impl IntoIterator<Item=Task<'a, S>>) -> Result<(), TaskPanicked> {
+        let worker_count = self.states.len();
+        let mut queues: Vec<_> = (0..worker_count).map(|_| VecDeque::new()).collect();
+        for (index, task) in tasks.into_iter().enumerate() {
+            queues[index % worker_count].push_back(task);
+        }
+        let queues: Vec<_> = queues.into_iter().map(Mutex::new).collect();
+        let stopped = AtomicBool::new(false);
+        let panic = Mutex::new(None);
+        // Each job holds a sender, the receiver hears from the channel once they have all been dropped.
+        let (running, finished) = mpsc::channel::<()>();
+
+        for (index, state) in self.states.iter_mut().enumerate() {
+            let (queues, stopped, panic, running) = (&queues, &stopped, &panic, running.clone());
+            let job: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    // One queue locked at a time, two workers stealing from each other would deadlock otherwise.
+                    let own = queues[index].lock().unwrap().pop_front();
+                    let Some(task) = own.or_else(|| (1..worker_count).find_map(|offset| queues[(index + offset) % worker_count].lock().unwrap().pop_back())) else {
+                        break;
+                    };
+                    if let Err(payload) = catch_unwind(AssertUnwindSafe(|| task(state))) {
+                        stopped.store(true, Ordering::Relaxed);
+                        panic.lock().unwrap().get_or_insert(TaskPanicked { message: panic_message(payload.as_ref()) });
                     }
                 }
+                drop(running);
             });
+            // SAFETY: the job borrows from this function and from the tasks, which outlive it: the function doesn't return
+            // before the job has run to the end or been dropped unstarted, and it touches nothing borrowed after that.
+            let job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + '_>, Job>(job) };
+            // A thread that is gone drops the job, the other workers take its tasks.
+            let _ = self.senders[index].send(job);
+        }
+        drop(running);
+        let _ = finished.recv();
+
+        match panic.into_inner().unwrap() {
+            Some(panic) => Err(panic),
+            None => Ok(()),
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_thread_pool() {
+        let pool = ThreadPool::new(3, || 0usize);
+        let mut workers = pool.lock();
+        // Known without waiting for the workers.
+        assert_eq!(pool.worker_count(), 3);
+        let done = Mutex::new(Vec::new());
+        let tasks = (0..20).map(|i| Box::new({
+            let done = &done;
+            move |count: &mut usize| {
+                *count += 1;
+                done.lock().unwrap().push(i);
+            }
+        }) as Task<usize>);
+        workers.execute(tasks).unwrap();
+        done.lock().unwrap().sort();
+        assert_eq!(*done.lock().unwrap(), (0..20).collect::<Vec<_>>());
+        assert_eq!(workers.states().iter().sum::<usize>(), 20);
+
+        // A panic stops the run and comes back as an error, the workers can still be used afterwards.
+        let panicking = (0..6).map(|i| Box::new(move |_: &mut usize| if i == 0 { panic!("tile {i} failed") }) as Task<usize>);
+        assert_eq!(workers.execute(panicking), Err(TaskPanicked { message: "tile 0 failed".to_string() }));
+        workers.execute((0..3).map(|_| Box::new(|count: &mut usize| *count += 1) as Task<usize>)).unwrap();
+        assert_eq!(workers.states().iter().sum::<usize>(), 23);
+    }
+
+    #[test]
+    fn test_thread_pool_keeps_its_threads() {
+        let pool = ThreadPool::new(2, Vec::new);
+        let mut workers = pool.lock();
+        for _ in 0..3 {
+            workers.execute((0..8).map(|_| Box::new(|threads: &mut Vec<_>| threads.push(std::thread::current().id())) as Task<Vec<_>>)).unwrap();
+        }
+        // Each worker ran its tasks of every run on the same thread, which isn't the caller's.
+        let threads: Vec<_> = workers.states().iter().flatten().copied().unique().collect();
+        assert!(threads.len() <= 2);
+        assert!(!threads.contains(&std::thread::current().id()));
+        for worker_threads in workers.states() {
+            assert!(worker_threads.iter().all_equal());
         }
-    });
-}
\ No newline at end of file
+    }
+}
@@ -1,214 +1,305 @@
 use itertools::Itertools;
 
-use crate::parallel;
-use crate::{Image, image::RenderTraget};
+use crate::bvhs::TraversalStack;
+use crate::parallel::{self, ThreadPool, Workers};
+use crate::Image;
 
-use crate::{CollisionReport, ImageView, RenderReport, ScaterInfo, Vec3f};
+use crate::{ImageView, PassInfo, Progress, RenderControl, RenderObserver, RenderReport, ScaterInfo, TileInfo, TileOrder, Vec3f};
 use crate::scene::Scene;
 use crate::camera::Camera;
-use crate::hitables::*;
 use crate::commun_types::Ray;
 use crate::math::*;
 use core::sync;
-use std::mem;
 use std::num::{NonZeroI16, NonZeroUsize};
 use std::ops::DerefMut;
 use std::thread;
 use std::sync::atomic::AtomicU32;
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// A rectangle of the image, in pixels from the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Crop {
+    // The part of the crop inside of a `width` x `height` image, renders only fill that part.
+    pub fn clamp(&self, width: u32, height: u32) -> Crop {
+        let (x, y) = (self.x.min(width), self.y.min(height));
+        Crop { x, y, width: self.width.min(width - x), height: self.height.min(height - y) }
+    }
+}
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct RenderOptions {
     pub max_depth: u32,
-    pub rays_per_pixel: u32
+    pub rays_per_pixel: u32,
+    pub tile_order: TileOrder,
+    // Renders with the same seed give the same image, whatever the number of threads and the tile order.
+    pub seed: Option<u64>,
+    // Only the pixels inside the crop are rendered, the others are left untouched.
+    pub crop: Option<Crop>,
+    // With a time limit or a noise target, the image is rendered in passes of a few samples per pixel until the time
+    // is up or the estimated noise is below the target. `rays_per_pixel` is then the most samples a pixel can get.
+    pub time_limit: Option<Duration>,
+    // Standard error of the pixels relative to the average brightness of the image, e.g. 0.01 for 1%.
+    pub noise_target: Option<f32>,
+    // Cancels or pauses the render from another thread.
+    pub control: Option<RenderControl>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions { max_depth: 10, rays_per_pixel: 100, tile_order: TileOrder::Scanline, seed: None, crop: None, time_limit: None, noise_target: None, control: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderError {
+    // A tile panicked, e.g. on a NaN in a material. The render stops there and the target is left untouched.
+    Panicked { message: String },
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Panicked { message } => write!(f, "a tile panicked while rendering: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+// Renders with its own workers, renders sharing a `RayTracer` take turns. The worker threads are started with the
+// `RayTracer` and kept, with what they keep from one tile to the next, until it is dropped: it is meant to be reused
+// from one render to the next.
+pub struct RayTracer {
+    pool: ThreadPool<Worker>,
 }
 
-pub struct RayTracer;
+// What a worker keeps from one tile to the next.
+struct Worker {
+    rng: StdRng,
+    counters: RenderReport,
+    // Pixels of the tile given to the observer.
+    pixels: Vec<Vec3f>,
+    stack: TraversalStack,
+}
+
+impl Default for RayTracer {
+    fn default() -> Self {
+        RayTracer::new()
+    }
+}
 
 impl RayTracer {
-    pub fn render(&self, scene: &Scene, target: &mut Image, options: &RenderOptions) -> RenderReport {
-        const TILE_SIZE : u32 = 32;
-        
-        let render_report = RenderReport::default();
-        let camera = &scene.camera;
+    const TILE_SIZE: u32 = 32;
+    const MAX_PASS_SAMPLES: u32 = 64;
 
-        let scene_ref = Arc::new(scene);
+    // One worker per CPU.
+    pub fn new() -> RayTracer {
+        RayTracer::with_threads(num_cpus::get())
+    }
 
-        let tiles = target.split_tiles(TILE_SIZE, TILE_SIZE);
+    pub fn with_threads(threads: usize) -> RayTracer {
+        let new_worker = || Worker { rng: StdRng::from_entropy(), counters: RenderReport::default(), pixels: Vec::new(), stack: TraversalStack::default() };
+        RayTracer { pool: ThreadPool::new(threads, new_worker) }
+    }
 
-        let tasks = tiles.map(|tile| (tile, scene_ref.clone()))
-            .map(|(mut tile, scene)| { 
-                let task = Box::new(move || {
-                    for (ray, pixel) in Self::shoot_at_tile(camera.clone(), &tile, options.rays_per_pixel) {
-                        
-                        let (c, _) = self.trace(&ray, &scene,0, options.max_depth);
-                        
-                        tile[pixel] += c;
-            
-                        //TODO: Render report
-                    }
+    pub fn threads(&self) -> usize {
+        self.pool.worker_count()
+    }
 
-                    for i in 0..tile.height as usize {
-                        for j in 0..tile.width as usize {
-                            tile[[i,j]] /= options.rays_per_pixel as f32;
-                        }
-                    }
-            }) as Box<dyn FnOnce()->()>;
-        
-            let task : parallel::Task = unsafe {mem::transmute(task)};
-            task
+    // Renders the image in passes until `options` says to stop, telling `observer` how it goes.
+    pub fn render(&self, scene: &Scene, target: &mut Image, options: &RenderOptions, observer: &dyn RenderObserver) -> Result<RenderReport, RenderError> {
+        let start = Instant::now();
+        let crop = options.crop.unwrap_or(Crop { x: 0, y: 0, width: target.width, height: target.height }).clamp(target.width, target.height);
+        // Sum and number of the samples of each pixel, and the sum of their squared luminance, only kept to estimate
+        // the noise when there is a target.
+        let mut sums = Image::new(Vec3f::zeros(), target.width, target.height);
+        let mut counts = Image::new(0, target.width, target.height);
+        let mut moments = options.noise_target.map(|_| Image::new(0.0, target.width, target.height));
+
+        let counters = self.accumulate(scene, &mut sums, &mut counts, moments.as_mut(), &crop, options, observer, start)?;
+        let report = resolve(target, &sums, counts, moments.as_ref(), &crop, RenderReport {
+            elapsed: start.elapsed(),
+            cancelled: options.control.as_ref().is_some_and(RenderControl::is_cancelled),
+            ..counters
         });
+        observer.render_finished(&report);
+        Ok(report)
+    }
 
-        // let worker_count = thread::available_parallelism().map(|i| i.get()).unwrap_or(16);
-        let worker_count = num_cpus::get();
-        parallel::parallel_execute(tasks,worker_count);
+    // Adds samples to the pixels of the crop in `sums`, `counts` and `moments`, in passes until `options` says to stop.
+    // Returns the counters of the workers.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn accumulate(&self, scene: &Scene, sums: &mut Image, counts: &mut Image<u32>, mut moments: Option<&mut Image<f32>>, crop: &Crop,
+                             options: &RenderOptions, observer: &dyn RenderObserver, start: Instant) -> Result<RenderReport, RenderError> {
+        let deadline = options.time_limit.map(|limit| start + limit);
+        // Pauses don't count, checked only once the deadline is reached to keep the control's lock out of the way.
+        let time_is_up = || deadline.is_some_and(|deadline| Instant::now() > deadline
+                             && options.control.as_ref().is_none_or(|control| Instant::now() > deadline + control.paused_for()));
+        let proceed = || options.control.as_ref().is_none_or(RenderControl::proceed);
+        let progressive = options.time_limit.is_some() || options.noise_target.is_some();
+
+        let mut workers = self.pool.lock();
+        // Counters of the workers, merged after each pass.
+        let mut counters = RenderReport::default();
+        for worker in workers.states() {
+            worker.counters = RenderReport::default();
+        }
 
-        render_report
-    }
+        let mut samples = 0;
+        let mut pass = 0;
+        while samples < options.rays_per_pixel && proceed() {
+            // Passes double in size so that the noise is checked often early on, without a check every sample later.
+            let pass_samples = if progressive { samples.clamp(1, Self::MAX_PASS_SAMPLES) } else { options.rays_per_pixel };
+            let pass_samples = pass_samples.min(options.rays_per_pixel - samples);
+
+            let result = self.render_pass(&mut workers, scene, sums, counts, moments.as_deref_mut(), crop, options, pass, pass_samples, &time_is_up, &proceed, observer, |tiles_done| {
+                let elapsed = start.elapsed();
+                let fraction = (samples as f32 + pass_samples as f32 * tiles_done) / options.rays_per_pixel as f32;
+                let time_fraction = options.time_limit.map_or(0.0, |limit| elapsed.as_secs_f32() / limit.as_secs_f32());
+                let fraction = fraction.max(time_fraction).min(1.0);
+                let eta = (fraction > 0.0).then(|| elapsed.mul_f32((1.0 - fraction) / fraction));
+                observer.progress(&Progress { fraction, elapsed, eta });
+            });
+            result.map_err(|panic| RenderError::Panicked { message: panic.message })?;
+            for worker in workers.states() {
+                counters.merge(&worker.counters);
+                worker.counters = RenderReport::default();
+            }
+            samples += pass_samples;
+            let noise = moments.as_deref().map(|moments| estimate_noise(sums, counts, moments, crop));
+            observer.pass_finished(&PassInfo { pass, samples_per_pixel: samples, noise });
+            pass += 1;
 
-    pub fn render_with_print(&self, scene: &Scene, target: &mut Image, options: &RenderOptions) -> RenderReport {
-        const TILE_SIZE : u32 = 32;
+            if time_is_up() {
+                break;
+            }
+            if options.noise_target.zip(noise).is_some_and(|(noise_target, noise)| noise <= noise_target) {
+                break;
+            }
+        }
+        Ok(counters)
+    }
 
-        let render_report = RenderReport::default();
+    // Adds `samples` samples to every pixel of the crop, tile by tile in `options.tile_order` on the workers.
+    // Tiles are skipped once `proceed` says so, and pixels once `time_is_up`. `on_tile_done` is given the fraction of the tiles done.
+    #[allow(clippy::too_many_arguments)]
+    fn render_pass(&self, workers: &mut Workers<Worker>, scene: &Scene, sums: &mut Image, counts: &mut Image<u32>, moments: Option<&mut Image<f32>>,
+                   crop: &Crop, options: &RenderOptions, pass: u32, samples: u32, time_is_up: &(impl Fn() -> bool + Sync), proceed: &(impl Fn() -> bool + Sync),
+                   observer: &dyn RenderObserver, on_tile_done: impl Fn(f32) + Sync) -> Result<(), parallel::TaskPanicked> {
         let camera = &scene.camera;
 
-        let scene_ref = Arc::new(scene);
-
-        let tile_count = target.split_tiles(TILE_SIZE, TILE_SIZE).count();
-        let tiles = target.split_tiles(TILE_SIZE, TILE_SIZE);
+        // Tiles keep their index in scanline order, which seeds their random numbers, whatever the order they are rendered in.
+        let order = options.tile_order.order(sums.width.div_ceil(Self::TILE_SIZE), sums.height.div_ceil(Self::TILE_SIZE));
+        let mut rank = vec![0; order.len()];
+        for (position, &index) in order.iter().enumerate() {
+            rank[index] = position;
+        }
 
+        let Crop { x, y, width, height } = *crop;
+        let mut moment_tiles = moments.map(|moments| moments.split_tiles(Self::TILE_SIZE, Self::TILE_SIZE));
+        let mut tiles: Vec<_> = sums.split_tiles(Self::TILE_SIZE, Self::TILE_SIZE).zip(counts.split_tiles(Self::TILE_SIZE, Self::TILE_SIZE)).enumerate()
+                                    .map(|(index, (tile, counts))| {
+                                        let moments = moment_tiles.as_mut().and_then(Iterator::next).and_then(|moments| moments.clip(x, y, width, height));
+                                        (index, tile.clip(x, y, width, height), counts.clip(x, y, width, height), moments)
+                                    })
+                                    .collect();
+        tiles.sort_by_key(|(index, _, _, _)| rank[*index]);
+        let tiles: Vec<_> = tiles.into_iter().filter_map(|(index, tile, counts, moments)| Some((index, tile?, counts?, moments))).collect();
+        let tile_count = tiles.len();
         let tiles_done = Mutex::new(0usize);
+        let on_tile_done = &on_tile_done;
 
-        let tasks = tiles.map(|tile| (tile, scene_ref.clone()))
-            .map(|(mut tile, scene)| { 
+        let tasks = tiles.into_iter()
+            .map(|(index, mut tile, mut counts, mut moments)| {
                 let tiles_done = &tiles_done;
-                let task = Box::new(move || {
-                    for (ray, pixel) in Self::shoot_at_tile(camera.clone(), &tile, options.rays_per_pixel) {
-                        
-                        let (c, _) = self.trace(&ray, &scene,0, options.max_depth);
-                        
-                        tile[pixel] += c;
-            
-                        //TODO: Render report
+                Box::new(move |worker: &mut Worker| {
+                    if !proceed() {
+                        return;
                     }
+                    let info = TileInfo { pass, index, x: tile.offset_x, y: tile.offset_y, width: tile.width, height: tile.height };
+                    observer.tile_started(&info);
+                    let tile_start = Instant::now();
+                    let Worker { rng, counters, pixels, stack } = worker;
+                    with_random_generator(rng, || {
+                        // Each tile of each pass has its own sequence of random numbers so that seeded renders don't depend on scheduling.
+                        if let Some(seed) = options.seed {
+                            seed_random(seed.wrapping_add((pass as u64) << 32 | index as u64));
+                        }
 
-                    for i in 0..tile.height as usize {
-                        for j in 0..tile.width as usize {
-                            tile[[i,j]] /= options.rays_per_pixel as f32;
+                        let mut current_pixel = None;
+                        for (ray, pixel) in Self::shoot_at_tile(camera.clone(), &tile, samples) {
+                            if current_pixel != Some(pixel) {
+                                if time_is_up() {
+                                    break;
+                                }
+                                current_pixel = Some(pixel);
+                            }
+
+                            let c = self.trace(&ray, scene, 0, options.max_depth, counters, stack);
+
+                            tile[pixel] += c;
+                            counts[pixel] += 1;
+                            if let Some(moments) = &mut moments {
+                                moments[pixel] += luminance(&c).powi(2);
+                            }
                         }
-                    }
+                    });
+                    counters.tile_times.push((info, tile_start.elapsed()));
+
+                    pixels.clear();
+                    pixels.extend((0..tile.height as usize).cartesian_product(0..tile.width as usize)
+                        .map(|(i, j)| {
+                            let count = counts[[i,j]];
+                            if count > 0 { tile[[i,j]] / count as f32 } else { Vec3f::zeros() }
+                        }));
+                    observer.tile_finished(&info, pixels);
 
                     let mut tiles_done = tiles_done.lock().unwrap();
                     *tiles_done += 1;
-                    
-                    const PROGRESS_BAR_LENGHT : usize = 100;
-                    let percentage = *tiles_done as f32 / tile_count as f32;
-                    let progress_bar : String = (0..PROGRESS_BAR_LENGHT).map(|i| if i as f32 <= (percentage * PROGRESS_BAR_LENGHT as f32) {'█'} else {'-'})
-                                                                        .collect();
-                    print!("\rRendering: [{progress_bar}] {:.2}", percentage*100.);
-            }) as Box<dyn FnOnce()->()>;
-        
-            let task : parallel::Task = unsafe {mem::transmute(task)};
-            task
-        });
-
+                    on_tile_done(*tiles_done as f32 / tile_count as f32);
+                }) as parallel::Task<Worker>
+            });
 
-        // let worker_count = thread::available_parallelism().map(|i| i.get()).unwrap_or(16);
-        let worker_count = num_cpus::get();
-        println!("Worker thread count: {worker_count}");
-        parallel::parallel_execute(tasks,worker_count);
-
-        render_report
-    }
-    
-    pub fn render_with_print_single_thread(&self, scene: &Scene, target: &mut Image, options: &RenderOptions) -> RenderReport {
-        let mut render_report = RenderReport::default();
-        let camera = &scene.camera;
-        let resolution = target.get_resolution();
-
-        let mut current_sample_index = 0;
-        let total_sample_count = resolution.0*resolution.1*options.rays_per_pixel;
-        let hundredth_of_persantile = total_sample_count / 100000;
-
-        for (ray, pixel) in Self::shoot_at(camera.clone(), resolution, options.rays_per_pixel) {
-            
-            let (c, report) = self.trace(&ray, scene,0, options.max_depth);
-            
-            target[pixel] += c;
-            
-            render_report.aabb_tests += report.aabb_tests;
-            render_report.triangle_tests += report.triangle_tests;
-            current_sample_index += 1;
-            
-            if current_sample_index % hundredth_of_persantile == 0 {
-                const PROGRESS_BAR_LENGHT : usize = 100;
-                let percentage = current_sample_index as f32 / total_sample_count as f32;
-                let progress_bar : String = (0..PROGRESS_BAR_LENGHT).map(|i| if i as f32 <= (percentage * PROGRESS_BAR_LENGHT as f32) {'█'} else {'-'})
-                                                                    .collect();
-                print!("\rRendering: [{progress_bar}] {:.2}", percentage*100.);
-            }
-        }
-
-        for i in 0..target.pixels.len() {
-            target.pixels[i] /= options.rays_per_pixel as f32;
-        }
-
-        render_report
+        workers.execute(tasks)
     }
 
-    fn trace(&self, ray: &Ray, scene: &Scene, depth: u32, max_depth: u32) -> (Vec3f, CollisionReport) {
+    // Follows a path from `ray`, counting its rays and intersection tests in `report`.
+    fn trace(&self, ray: &Ray, scene: &Scene, depth: u32, max_depth: u32, report: &mut RenderReport, stack: &mut TraversalStack) -> Vec3f {
         if depth >= max_depth {
-            return (Vec3f::zeros(), CollisionReport::default());
+            report.record_path(depth);
+            return Vec3f::zeros();
         }
 
-        let (hit, report1) = scene.hit(&ray, 0.01, f32::INFINITY);
+        let (hit, collisions) = scene.hit_with(ray, 0.01, f32::INFINITY, stack);
+        if depth == 0 {
+            report.primary_rays += 1;
+        } else {
+            report.secondary_rays += 1;
+        }
+        report.aabb_tests += collisions.aabb_tests;
+        report.triangle_tests += collisions.triangle_tests;
 
         if let Some(info) = hit {
             let ScaterInfo { ray: new_ray, attenuation, emission} = info.material.scater(ray.direction, &info);
 
-            let (scatered, report2) = self.trace(&new_ray, scene, depth + 1, max_depth);
-            (
-                mul_element_wise(scatered, attenuation) + emission,
-                CollisionReport {
-                    aabb_tests: report1.aabb_tests + report2.aabb_tests,
-                    triangle_tests: report1.triangle_tests + report2.triangle_tests
-                }
-            )
+            let scatered = self.trace(&new_ray, scene, depth + 1, max_depth, report, stack);
+            mul_element_wise(scatered, attenuation) + emission
         } else {
-            (scene.environment.sample(&ray.direction), report1)
+            report.record_path(depth + 1);
+            scene.sample_environment(&ray.direction)
         }
     }
 
-    fn shoot_at(camera: Camera, (width, height): (u32, u32), rays_per_pixel: u32) -> impl Iterator<Item = (Ray, [usize;2])> +'static {
-
-        // let right = camera.direction.cross(&camera.up);
-        // let up  = right.cross(&camera.direction);
-        // let tan_fov = (camera.fov/2.0).tan();
-        // let aspect_ratio = height as f32/ width as f32;
-        // let direction = camera.direction;
-        // let origin = camera.origin;
-
-        // (0..height as usize).cartesian_product(0..width as usize).cartesian_product(0..rays_per_pixel as usize)
-        // .map(move |((i,j), _)| {
-        //     let relative_x = ( (j as f32 + rand::random::<f32>()) / width as f32  - 0.5) * tan_fov;
-        //     let relative_y = (-(i as f32 + rand::random::<f32>()) / height as f32 + 0.5) * tan_fov * aspect_ratio;
-
-        //     let pixel_in_plane = relative_x * right + relative_y * up;
-        //     let direction = direction + pixel_in_plane;
-
-        //     (
-        //         Ray {
-        //             origin: origin,
-        //             direction,
-        //         }, 
-        //         [i,j]
-        //     )
-        // })
-
-        RayIterator::new(camera, 0, 0, width as usize, height as usize,
-            width as usize, height as usize, rays_per_pixel)
-    }
-
     fn shoot_at_tile(camera: Camera, tile: &ImageView, rays_per_pixel: u32) -> impl Iterator<Item = (Ray, [usize;2])> +'static {
 
         // let right = camera.direction.cross(&camera.up);
@@ -303,8 +394,8 @@ impl<'a> Iterator for RayIterator {
         let right = self.camera.direction.cross(&self.camera.up);
         let up  = right.cross(&self.camera.direction);
 
-        let relative_x = ((self.current_pixel_j as f32 + rand::random::<f32>()) / self.source_width as f32  - 0.5) * self.tan_fov;
-        let relative_y = (-(self.current_pixel_i as f32 + rand::random::<f32>()) / self.source_height as f32 + 0.5) * self.tan_fov * self.aspect_ratio;
+        let relative_x = ((self.current_pixel_j as f32 + random::<f32>()) / self.source_width as f32  - 0.5) * self.tan_fov;
+        let relative_y = (-(self.current_pixel_i as f32 + random::<f32>()) / self.source_height as f32 + 0.5) * self.tan_fov * self.aspect_ratio;
 
         let pixel_in_plane = relative_x * right + relative_y * up;
         let direction = self.camera.direction + pixel_in_plane;
@@ -320,3 +411,156 @@ impl<'a> Iterator for RayIterator {
         ))
     }
 }
+
+// Writes the average of the samples of each pixel of the crop to `target`, and adds the samples per pixel and the noise,
+// if the moments were kept, to `report`. Pixels the time limit or a cancellation didn't leave any sample to are left untouched.
+pub(crate) fn resolve(target: &mut Image, sums: &Image, counts: Image<u32>, moments: Option<&Image<f32>>, crop: &Crop, report: RenderReport) -> RenderReport {
+    let mut samples_per_pixel = u32::MAX;
+    for i in crop.y as usize..(crop.y + crop.height) as usize {
+        for j in crop.x as usize..(crop.x + crop.width) as usize {
+            let count = counts[[i,j]];
+            samples_per_pixel = samples_per_pixel.min(count);
+            if count > 0 {
+                target[[i,j]] = sums[[i,j]] / count as f32;
+            }
+        }
+    }
+
+    RenderReport {
+        samples_per_pixel: if crop.width == 0 || crop.height == 0 { 0 } else { samples_per_pixel },
+        noise: moments.map(|moments| estimate_noise(sums, &counts, moments, crop)),
+        // Only the pixels of the crop get samples.
+        sample_counts: counts.pixels,
+        ..report
+    }
+}
+
+pub(crate) fn luminance(color: &Vec3f) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+// Root mean square of the standard error of the pixels, relative to the average luminance of the crop.
+pub(crate) fn estimate_noise(sums: &Image, counts: &Image<u32>, moments: &Image<f32>, crop: &Crop) -> f32 {
+    let mut variance = 0.0;
+    let mut luminance_sum = 0.0;
+    let mut pixel_count = 0;
+    for i in crop.y as usize..(crop.y + crop.height) as usize {
+        for j in crop.x as usize..(crop.x + crop.width) as usize {
+            let count = counts[[i,j]] as f32;
+            // The variance of a pixel can't be estimated from less than two samples.
+            if count < 2.0 {
+                return f32::INFINITY;
+            }
+            let mean = luminance(&sums[[i,j]]) / count;
+            let sample_variance = (moments[[i,j]] / count - mean * mean).max(0.0) * count / (count - 1.0);
+            variance += sample_variance / count;
+            luminance_sum += mean;
+            pixel_count += 1;
+        }
+    }
+
+    if luminance_sum <= 0.0 {
+        return if pixel_count == 0 || variance <= 0.0 { 0.0 } else { f32::INFINITY };
+    }
+    (variance / pixel_count as f32).sqrt() / (luminance_sum / pixel_count as f32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::sky_scene;
+
+    #[test]
+    fn test_noise_target() {
+        // Nothing but a uniform sky: the noise is zero as soon as it can be estimated.
+        let color = Vec3f::new(0.2, 0.4, 0.8);
+        let scene = sky_scene(color);
+        let mut target = Image::new(Vec3f::zeros(), 40, 30);
+        let options = RenderOptions { rays_per_pixel: 1000, noise_target: Some(0.01), crop: Some(Crop { x: 5, y: 5, width: 20, height: 10 }), ..Default::default() };
+
+        let report = RayTracer::new().render(&scene, &mut target, &options, &()).unwrap();
+        assert_eq!(report.samples_per_pixel, 2);
+        assert_eq!(report.noise, Some(0.0));
+        assert!((target[[10, 10]] - color).norm() < 1e-5);
+        assert_eq!(target[[0, 0]], Vec3f::zeros());
+
+        // Crops going past the edges only render what is inside of the image.
+        for crop in [Crop { x: 30, y: 5, width: 20, height: 10 }, Crop { x: 5, y: 25, width: 10, height: 10 }] {
+            let mut target = Image::new(Vec3f::zeros(), 40, 30);
+            let report = RayTracer::new().render(&scene, &mut target, &RenderOptions { crop: Some(crop), ..options.clone() }, &()).unwrap();
+            assert_eq!(report.noise, Some(0.0));
+            let inside = crop.clamp(40, 30);
+            for (i, j) in (0..30).flat_map(|i| (0..40).map(move |j| (i, j))) {
+                let in_crop = (inside.y..inside.y + inside.height).contains(&i) && (inside.x..inside.x + inside.width).contains(&j);
+                assert_eq!((target[[i as usize, j as usize]] - color).norm() < 1e-5, in_crop, "{i},{j}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_cancel_paused_render() {
+        let scene = sky_scene(Vec3f::repeat(1.0));
+        let mut target = Image::new(Vec3f::zeros(), 8, 8);
+        let control = RenderControl::new();
+        control.pause();
+        let options = RenderOptions { control: Some(control.clone()), ..Default::default() };
+
+        // The render waits for the pause to end, and gives up when cancelled instead.
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            control.cancel();
+        });
+        let report = RayTracer::new().render(&scene, &mut target, &options, &()).unwrap();
+        canceller.join().unwrap();
+
+        assert!(report.cancelled);
+        assert_eq!(report.samples_per_pixel, 0);
+        assert!(report.sample_counts.iter().all(|&count| count == 0));
+        // Without a noise target the moments aren't kept.
+        assert_eq!(report.noise, None);
+        assert_eq!(target[[4, 4]], Vec3f::zeros());
+    }
+
+    #[test]
+    fn test_render_observer() {
+        #[derive(Default)]
+        struct Recorder {
+            tiles: Mutex<Vec<(TileInfo, Vec<Vec3f>)>>,
+            passes: Mutex<Vec<PassInfo>>,
+            finished: Mutex<bool>,
+        }
+        impl RenderObserver for Recorder {
+            fn tile_finished(&self, tile: &TileInfo, pixels: &[Vec3f]) {
+                self.tiles.lock().unwrap().push((*tile, pixels.to_vec()));
+            }
+            fn pass_finished(&self, pass: &PassInfo) {
+                self.passes.lock().unwrap().push(*pass);
+            }
+            fn render_finished(&self, _report: &RenderReport) {
+                *self.finished.lock().unwrap() = true;
+            }
+        }
+
+        let color = Vec3f::new(0.5, 0.25, 1.0);
+        let scene = sky_scene(color);
+        let mut target = Image::new(Vec3f::zeros(), 40, 20);
+        let recorder = Recorder::default();
+        let report = RayTracer::new().render(&scene, &mut target, &RenderOptions { rays_per_pixel: 4, time_limit: Some(Duration::from_secs(60)), ..Default::default() }, &recorder).unwrap();
+
+        // Passes of 1, 1 and 2 samples over two tiles, the second one 8 pixels wide.
+        let passes = recorder.passes.into_inner().unwrap();
+        assert_eq!(passes.iter().map(|pass| pass.samples_per_pixel).collect::<Vec<_>>(), vec![1, 2, 4]);
+        let tiles = recorder.tiles.into_inner().unwrap();
+        assert_eq!(tiles.len(), 6);
+        let (tile, pixels) = tiles.iter().find(|(tile, _)| tile.index == 1).unwrap();
+        assert_eq!((tile.x, tile.y, tile.width, tile.height), (32, 0, 8, 20));
+        assert_eq!(pixels.len(), 8 * 20);
+        assert!(pixels.iter().all(|pixel| (pixel - color).norm() < 1e-5));
+        assert!(recorder.finished.into_inner().unwrap());
+
+        // Every camera ray escapes to the sky, the counters of all the tiles add up.
+        assert_eq!((report.primary_rays, report.secondary_rays), (40 * 20 * 4, 0));
+        assert_eq!(report.path_lengths, vec![0, 40 * 20 * 4]);
+        assert_eq!(report.tile_times.len(), 6);
+    }
+}
@@ -1,17 +1,55 @@
 use core::f32;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::io::{BufReader, Write};
 use std::path::Path;
 
-use crate::{hitables::*, vec3_to_vec4, Camera, Collider, CollisionReport, Mat3f, Mat4f, Material, Mesh, Vec3f};
+use crate::arena::{Arena, Handle, RawHandle};
+use crate::bvhs::{BVH, TraversalStack};
+use crate::loaders::relative_path;
+use crate::texture::for_each_texture_path;
+use crate::{migration, MeshLoadError, MeshSource, SceneError};
+use crate::{hitables::*, vec3_to_vec4, Animation, InterpolationError, Group, Node, Camera, CollisionReport, Mat3f, Mat4f, Material, Mesh, Vec3f};
 use crate::commun_types::Ray;
+use crate::math::na;
 use std::fs::File;
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct MeshHandle(usize);
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct MatearialHandle(usize);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MeshHandle(RawHandle);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MatearialHandle(RawHandle);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ObjectHandle(RawHandle);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CameraHandle(RawHandle);
+
+macro_rules! impl_handle {
+    ($($handle:ident),*) => {$(
+        impl Handle for $handle {
+            fn from_raw(raw: RawHandle) -> Self { $handle(raw) }
+            fn raw(&self) -> RawHandle { self.0 }
+        }
+    )*};
+}
+impl_handle!(MeshHandle, MatearialHandle, ObjectHandle, CameraHandle);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneEditError {
+    InvalidHandle,
+    StillReferenced { references: usize },
+}
+
+impl std::fmt::Display for SceneEditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneEditError::InvalidHandle => write!(f, "the handle does not refer to anything in the scene"),
+            SceneEditError::StillReferenced { references } => write!(f, "still referenced by {references} object(s)"),
+        }
+    }
+}
+
+impl std::error::Error for SceneEditError {}
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(from = "MinimalObject", into = "MinimalObject")]
@@ -23,9 +61,24 @@ pub struct Object {
     material: MatearialHandle,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonInvertibleTransform;
+
+impl std::fmt::Display for NonInvertibleTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the transform is not invertible")
+    }
+}
+
+impl std::error::Error for NonInvertibleTransform {}
+
 impl Object {
-    pub fn new(mesh: MeshHandle, transform: Mat4f, material: MatearialHandle) -> Object {
-        let inv_transform = transform.try_inverse().unwrap();
+    pub fn new(mesh: MeshHandle, transform: Mat4f, material: MatearialHandle) -> Result<Object, NonInvertibleTransform> {
+        let inv_transform = transform.try_inverse().ok_or(NonInvertibleTransform)?;
+        Ok(Object::from_parts(mesh, transform, inv_transform, material))
+    }
+
+    fn from_parts(mesh: MeshHandle, transform: Mat4f, inv_transform: Mat4f, material: MatearialHandle) -> Object {
         let normal_mat = Mat3f::new(
             inv_transform.m11,inv_transform.m21, inv_transform.m31,
             inv_transform.m12,inv_transform.m22, inv_transform.m32,
@@ -45,17 +98,18 @@ impl Object {
         &self.transform
     }
 
-    pub fn set_transform(&mut self, new_transform: Mat4f) {
-        let inv_transform = new_transform.try_inverse().unwrap();
-        let normal_mat = Mat3f::new(
-            inv_transform.m11,inv_transform.m21, inv_transform.m31,
-            inv_transform.m12,inv_transform.m22, inv_transform.m32,
-            inv_transform.m13,inv_transform.m23, inv_transform.m33
-        );
+    pub fn get_mesh(&self) -> MeshHandle {
+        self.mesh
+    }
 
-        self.transform = new_transform;
-        self.inv_transform = inv_transform;
-        self.normal_mat = normal_mat;
+    pub fn get_material(&self) -> MatearialHandle {
+        self.material
+    }
+
+    pub fn set_transform(&mut self, new_transform: Mat4f) -> Result<(), NonInvertibleTransform> {
+        let inv_transform = new_transform.try_inverse().ok_or(NonInvertibleTransform)?;
+        *self = Object::from_parts(self.mesh, new_transform, inv_transform, self.material);
+        Ok(())
     }
 }
 
@@ -66,21 +120,11 @@ struct MinimalObject {
     material: MatearialHandle,
 }
 
+// Singular transforms are let through so that `Scene::validate` can report them along with every other problem.
 impl From<MinimalObject> for Object {
     fn from(value: MinimalObject) -> Self {
-        let inv_transform = value.transform.try_inverse().unwrap();
-        let normal_mat = Mat3f::new(
-            inv_transform.m11,inv_transform.m21, inv_transform.m31,
-            inv_transform.m12,inv_transform.m22, inv_transform.m32,
-            inv_transform.m13,inv_transform.m23, inv_transform.m33
-        );
-        Object {
-            transform: value.transform,
-            mesh: value.mesh,
-            material: value.material,
-            inv_transform,
-            normal_mat
-        }
+        let inv_transform = value.transform.try_inverse().unwrap_or(Mat4f::from_element(f32::NAN));
+        Object::from_parts(value.mesh, value.transform, inv_transform, value.material)
     }
 }
 
@@ -137,58 +181,296 @@ impl Environment for SkyEnvironment {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Scene {
-    meshes: Vec<Mesh>,
-    materials: Vec<Box<dyn Material>>,
-    objects: Vec<Object>,
+    #[serde(default)]
+    version: u32,
+    pub(crate) meshes: Arena<MeshHandle, Mesh>,
+    pub(crate) materials: Arena<MatearialHandle, Box<dyn Material>>,
+    pub(crate) objects: Arena<ObjectHandle, Object>,
+    #[serde(default)]
+    pub(crate) cameras: Arena<CameraHandle, Camera>,
+    #[serde(default)]
+    pub(crate) nodes: Vec<Node>,
+    #[serde(default)]
+    pub(crate) groups: BTreeMap<String, Group>,
+    // Objects of `nodes` with their world transforms.
+    #[serde(skip)]
+    pub(crate) world_objects: Vec<Object>,
     pub camera: Camera,
-    pub environment: Box<dyn Environment>
+    pub environment: Box<dyn Environment>,
+    // Rotation of the environment around the y axis, in radians.
+    #[serde(default)]
+    pub environment_rotation: f32,
+    #[serde(default, skip_serializing_if = "Animation::is_empty")]
+    pub animation: Animation,
 } 
 
 impl Scene {
+    pub const VERSION: u32 = 3;
+
     pub fn new(camera: Camera, environment: Box<dyn Environment>) -> Scene {
         Scene { 
-            objects: Vec::new(), 
-            meshes: Vec::new(), 
-            materials: Vec::new(), 
+            version: Scene::VERSION,
+            objects: Arena::new(), 
+            nodes: Vec::new(),
+            groups: BTreeMap::new(),
+            world_objects: Vec::new(),
+            meshes: Arena::new(), 
+            materials: Arena::new(), 
+            cameras: Arena::new(),
             camera,
-            environment
+            environment,
+            environment_rotation: 0.0,
+            animation: Animation::default(),
         }
     }
 
-    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Scene> {
+    // Loads a scene saved with `Scene::save`, or written in the TOML authoring format when the file ends in `.toml`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+        Scene::load_with_options(path, &LoadOptions::default())
+    }
+
+    pub fn load_with_options(path: impl AsRef<Path>, options: &LoadOptions) -> Result<Scene, SceneError> {
+        let path = path.as_ref();
+        if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("toml")) {
+            return Scene::load_toml(path, options);
+        }
+
         let file = File::open(path)?;
         let reader = BufReader::new(file);
-        let scene = serde_json::from_reader(reader)?;
+        let scene: serde_json::Value = serde_json::from_reader(reader)?;
+        Scene::from_value(scene, path.parent().unwrap_or(Path::new("")), options)
+    }
+
+    // Reads a scene saved with `Scene::save` from parsed JSON, e.g. received over the network.
+    // Meshes and textures it refers to are loaded relative to `base`.
+    pub fn from_value(scene: serde_json::Value, base: impl AsRef<Path>, options: &LoadOptions) -> Result<Scene, SceneError> {
+        let base = base.as_ref();
+        let mut scene = migration::migrate(scene)?;
+        for key in ["materials", "animation"] {
+            if let Some(value) = scene.get_mut(key) {
+                for_each_texture_path(value, &mut |path| *path = base.join(&*path).to_string_lossy().into_owned());
+            }
+        }
+
+        // Referenced meshes are deserialized as empty placeholders and loaded once the scene is parsed.
+        let mut references = Vec::new();
+        if let Some(serde_json::Value::Array(slots)) = scene.get_mut("meshes") {
+            for (index, slot) in slots.iter_mut().enumerate() {
+                let Some(source) = slot.pointer_mut("/value/source").map(serde_json::Value::take) else { continue };
+                let source: MeshSource = serde_json::from_value(source)?;
+                let generation = slot["generation"].as_u64().unwrap_or(0) as u32;
+                slot["value"] = serde_json::to_value(Mesh { vertices: Vec::new(), triangles: Vec::new(), bvh: BVH::default(), colors: Vec::new(), source: None })?;
+                references.push((MeshHandle(RawHandle { index: index as u32, generation }), source));
+            }
+        }
+
+        let mut scene: Scene = serde_json::from_value(scene)?;
+
+        for (handle, source) in references {
+            let path = base.join(&source.path);
+            let mesh = options.load_mesh(&path).map_err(|error| SceneError::MeshLoad { path, error })?;
+            let _ = scene.meshes.replace(handle, mesh);
+        }
+
+        let issues = scene.validate();
+        if issues.iter().any(|issue| !issue.is_warning()) {
+            return Err(SceneError::Invalid(issues));
+        }
+        scene.update_world_transforms();
         Ok(scene)
     }
 
-    pub fn add_object(&mut self, object: Object) {
-        self.objects.push(object);
+    pub fn add_object(&mut self, object: Object) -> ObjectHandle {
+        self.objects.insert(object, None)
+    }
+
+    pub fn add_named_object(&mut self, name: impl Into<String>, object: Object) -> ObjectHandle {
+        self.objects.insert(object, Some(name.into()))
+    }
+
+    pub fn objects(&self) -> &Arena<ObjectHandle, Object> {
+        &self.objects
+    }
+
+    pub fn object_mut(&mut self, handle: ObjectHandle) -> Option<&mut Object> {
+        self.objects.get_mut(handle)
+    }
+
+    pub fn replace_object(&mut self, handle: ObjectHandle, object: Object) -> Result<Object, SceneEditError> {
+        self.objects.replace(handle, object).map_err(|_| SceneEditError::InvalidHandle)
+    }
+
+    pub fn remove_object(&mut self, handle: ObjectHandle) -> Result<Object, SceneEditError> {
+        let object = self.objects.remove(handle).ok_or(SceneEditError::InvalidHandle)?;
+        self.animation.objects.retain(|(animated, _)| *animated != handle);
+        Ok(object)
     }
+
     pub fn object_count(&self) -> usize {
-        self.objects.len()
+        self.objects.len() + self.world_objects.len()
+    }
+
+    pub fn add_node(&mut self, node: Node) {
+        node.flatten(&Mat4f::identity(), &self.groups, &mut self.world_objects);
+        self.nodes.push(node);
+    }
+
+    // Defines a group that can be placed any number of times with `instantiate`.
+    pub fn define_group(&mut self, name: impl Into<String>, group: Group) {
+        self.groups.insert(name.into(), group);
+        self.update_world_transforms();
+    }
+
+    pub fn instantiate(&mut self, group: impl Into<String>, transform: Mat4f) {
+        self.add_node(Node::Instance { group: group.into(), transform });
+    }
+
+    pub fn update_world_transforms(&mut self) {
+        self.world_objects.clear();
+        for node in &self.nodes {
+            node.flatten(&Mat4f::identity(), &self.groups, &mut self.world_objects);
+        }
+    }
+
+    // Calls `f` on every object of the scene, including the ones inside groups.
+    fn for_each_object(&self, mut f: impl FnMut(&Object)) {
+        self.objects.values().for_each(&mut f);
+        for node in &self.nodes {
+            node.for_each_object(&mut f);
+        }
+        for group in self.groups.values() {
+            for node in &group.children {
+                node.for_each_object(&mut f);
+            }
+        }
     }
 
     pub fn add_mesh(&mut self, mesh: Mesh) -> MeshHandle {
-        self.meshes.push(mesh);
-        MeshHandle(self.meshes.len() - 1)
+        self.meshes.insert(mesh, None)
+    }
+
+    pub fn add_named_mesh(&mut self, name: impl Into<String>, mesh: Mesh) -> MeshHandle {
+        self.meshes.insert(mesh, Some(name.into()))
+    }
+
+    pub fn meshes(&self) -> &Arena<MeshHandle, Mesh> {
+        &self.meshes
+    }
+
+    pub fn replace_mesh(&mut self, handle: MeshHandle, mesh: Mesh) -> Result<Mesh, SceneEditError> {
+        self.meshes.replace(handle, mesh).map_err(|_| SceneEditError::InvalidHandle)
+    }
+
+    pub fn remove_mesh(&mut self, handle: MeshHandle) -> Result<Mesh, SceneEditError> {
+        if !self.meshes.contains(handle) {
+            return Err(SceneEditError::InvalidHandle);
+        }
+
+        let mut references = 0;
+        self.for_each_object(|object| if object.mesh == handle { references += 1 });
+        if references > 0 {
+            return Err(SceneEditError::StillReferenced { references });
+        }
+
+        Ok(self.meshes.remove(handle).unwrap())
     }
 
     pub fn add_material(&mut self, material: Box<dyn Material>) -> MatearialHandle {
-        self.materials.push(material);
-        MatearialHandle(self.materials.len() - 1)
+        self.materials.insert(material, None)
+    }
+
+    pub fn add_named_material(&mut self, name: impl Into<String>, material: Box<dyn Material>) -> MatearialHandle {
+        self.materials.insert(material, Some(name.into()))
+    }
+
+    pub fn materials(&self) -> &Arena<MatearialHandle, Box<dyn Material>> {
+        &self.materials
+    }
+
+    pub fn replace_material(&mut self, handle: MatearialHandle, material: Box<dyn Material>) -> Result<Box<dyn Material>, SceneEditError> {
+        self.materials.replace(handle, material).map_err(|_| SceneEditError::InvalidHandle)
+    }
+
+    pub fn remove_material(&mut self, handle: MatearialHandle) -> Result<Box<dyn Material>, SceneEditError> {
+        if !self.materials.contains(handle) {
+            return Err(SceneEditError::InvalidHandle);
+        }
+
+        let mut references = 0;
+        self.for_each_object(|object| if object.material == handle { references += 1 });
+        if references > 0 {
+            return Err(SceneEditError::StillReferenced { references });
+        }
+
+        self.animation.materials.retain(|(animated, _)| *animated != handle);
+        Ok(self.materials.remove(handle).unwrap())
+    }
+
+    pub fn add_camera(&mut self, camera: Camera) -> CameraHandle {
+        self.cameras.insert(camera, None)
+    }
+
+    pub fn add_named_camera(&mut self, name: impl Into<String>, camera: Camera) -> CameraHandle {
+        self.cameras.insert(camera, Some(name.into()))
+    }
+
+    pub fn cameras(&self) -> &Arena<CameraHandle, Camera> {
+        &self.cameras
+    }
+
+    pub fn replace_camera(&mut self, handle: CameraHandle, camera: Camera) -> Result<Camera, SceneEditError> {
+        self.cameras.replace(handle, camera).map_err(|_| SceneEditError::InvalidHandle)
+    }
+
+    pub fn remove_camera(&mut self, handle: CameraHandle) -> Result<Camera, SceneEditError> {
+        self.cameras.remove(handle).ok_or(SceneEditError::InvalidHandle)
+    }
+
+    // Makes a copy of one of the stored cameras the one used for rendering.
+    pub fn use_camera(&mut self, handle: CameraHandle) -> Result<(), SceneEditError> {
+        self.camera = self.cameras.get(handle).ok_or(SceneEditError::InvalidHandle)?.clone();
+        Ok(())
+    }
+
+
+    pub fn set_time(&mut self, time: f32) -> Result<(), InterpolationError> {
+        for (handle, track) in &self.animation.objects {
+            if let (Some(transform), Some(object)) = (track.sample(time)?, self.objects.get_mut(*handle)) {
+                // A keyframe scaled down to zero leaves the object where it was.
+                let _ = object.set_transform(transform.to_matrix());
+            }
+        }
+        for (handle, track) in &self.animation.materials {
+            if let Some(material) = track.sample(time)? {
+                let _ = self.materials.replace(*handle, material);
+            }
+        }
+        if let Some(camera) = self.animation.camera.sample(time)? {
+            self.camera = camera;
+        }
+        if let Some(rotation) = self.animation.environment_rotation.sample(time)? {
+            self.environment_rotation = rotation;
+        }
+        Ok(())
     }
 
+    pub fn sample_environment(&self, direction: &Vec3f) -> Vec3f {
+        if self.environment_rotation == 0.0 {
+            return self.environment.sample(direction);
+        }
+        let rotation = na::Rotation3::from_axis_angle(&Vec3f::y_axis(), -self.environment_rotation);
+        self.environment.sample(&(rotation * direction))
+    }
 
-    fn hit_object(&self, object: &Object, ray: &Ray, min_t: f32, max_t: f32) -> (Option<HitInfo>, CollisionReport) {
-        let mesh = &self.meshes[object.mesh.0];
-        let material = &self.materials[object.material.0];
+    fn hit_object(&self, object: &Object, ray: &Ray, min_t: f32, max_t: f32, stack: &mut TraversalStack) -> (Option<HitInfo>, CollisionReport) {
+        let mesh = &self.meshes[object.mesh];
+        let material = &self.materials[object.material];
 
         let local_ray = Ray {
             origin: (object.inv_transform * vec3_to_vec4(&ray.origin,1.0)).xyz(),
             direction: (object.inv_transform * vec3_to_vec4(&ray.direction,0.0)).xyz(),
         };
-        let (collision, report) = mesh.collide(&local_ray, min_t, max_t);
+        let (collision, report) = mesh.collide_with(&local_ray, min_t, max_t, stack);
         
         let collision = collision.map(|info| HitInfo {
             point: (object.transform * vec3_to_vec4(&info.point,1.0)).xyz(),
@@ -201,22 +483,137 @@ impl Scene {
 
         (collision, report)
     }
+    // Rewrites a scene file saved by an older version in the current layout.
+    // Returns whether the file needed an upgrade.
+    pub fn upgrade_file(path: impl AsRef<Path>) -> Result<bool, SceneError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let scene: serde_json::Value = serde_json::from_reader(BufReader::new(file))?;
+        if migration::scene_version(&scene) == Scene::VERSION {
+            return Ok(false);
+        }
+
+        let scene = Scene::from_value(scene, path.parent().unwrap_or(Path::new("")), &LoadOptions::default())?;
+        scene.save(path)?;
+        Ok(true)
+    }
+
+    // Saves the scene as JSON, meshes and textures loaded from a file are saved as a path relative to the scene file.
     pub fn save(&self, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
-        let serialized = serde_json::to_string_pretty(self)?;
+        self.save_with_options(path, &SaveOptions::default())
+    }
+
+    pub fn save_with_options(&self, path: impl AsRef<Path>, options: &SaveOptions) -> Result<(), std::io::Error> {
+        let path = path.as_ref();
+        let base = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let mut materials = serde_json::to_value(&self.materials)?;
+        let mut animation = serde_json::to_value(&self.animation)?;
+        for value in [&mut materials, &mut animation] {
+            for_each_texture_path(value, &mut |path| *path = relative_path(Path::new(path), base).to_string_lossy().into_owned());
+        }
+
+        let scene = SceneFile {
+            version: self.version,
+            meshes: MeshesFile { meshes: &self.meshes, base: (!options.embed_meshes).then_some(base) },
+            materials,
+            objects: &self.objects,
+            cameras: &self.cameras,
+            nodes: &self.nodes,
+            groups: &self.groups,
+            camera: &self.camera,
+            environment: self.environment.as_ref(),
+            environment_rotation: self.environment_rotation,
+            animation: (!self.animation.is_empty()).then_some(animation),
+        };
+
+        let serialized = serde_json::to_string_pretty(&scene)?;
         let mut file = File::create(path)?;
-        file.write(serialized.as_bytes())?;
+        file.write_all(serialized.as_bytes())?;
         Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    // The meshes a scene refers to are cached next to their file in the binary format, see `Mesh::load_cached`,
+    // unless this is set. Meshes are then parsed from their file every time and nothing is written.
+    pub no_mesh_cache: bool,
+}
+
+impl LoadOptions {
+    pub(crate) fn load_mesh(&self, path: &Path) -> Result<Mesh, MeshLoadError> {
+        if self.no_mesh_cache {
+            Mesh::load(path)
+        } else {
+            Mesh::load_cached(path)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SaveOptions {
+    // Write the geometry of every mesh into the scene file, even the ones loaded from a file.
+    pub embed_meshes: bool,
+}
 
+// Borrowed view of a `Scene` with the same layout, used to save meshes as references and texture paths relative to
+// the scene file.
+#[derive(Serialize)]
+struct SceneFile<'a> {
+    version: u32,
+    meshes: MeshesFile<'a>,
+    materials: serde_json::Value,
+    objects: &'a Arena<ObjectHandle, Object>,
+    cameras: &'a Arena<CameraHandle, Camera>,
+    nodes: &'a Vec<Node>,
+    groups: &'a BTreeMap<String, Group>,
+    camera: &'a Camera,
+    environment: &'a dyn Environment,
+    environment_rotation: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    animation: Option<serde_json::Value>,
+}
+
+struct MeshesFile<'a> {
+    meshes: &'a Arena<MeshHandle, Mesh>,
+    // Directory that referenced meshes are relative to, or `None` to embed every mesh.
+    base: Option<&'a Path>,
+}
+
+impl Serialize for MeshesFile<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum StoredMesh<'a> {
+            Reference { source: MeshSource },
+            Inline(&'a Mesh),
+        }
+
+        self.meshes.serialize_map(serializer, |mesh| match (&mesh.source, self.base) {
+            (Some(source), Some(base)) => StoredMesh::Reference { source: MeshSource { path: relative_path(&source.path, base) } },
+            _ => StoredMesh::Inline(mesh),
+        })
     }
 }
 
 impl Optical for Scene {
     fn hit(&self, ray: &Ray, min_t: f32, max_t: f32) -> (Option<HitInfo>, CollisionReport) {
+        self.hit_with(ray, min_t, max_t, &mut TraversalStack::default())
+    }
+}
+
+impl Scene {
+    // Like `hit`, with a traversal stack kept by the caller, e.g. a worker of the `RayTracer`.
+    pub(crate) fn hit_with(&self, ray: &Ray, min_t: f32, max_t: f32, stack: &mut TraversalStack) -> (Option<HitInfo>, CollisionReport) {
         let mut hit_info: Option<HitInfo> = None;
         let mut report = CollisionReport::default();
-        for object in &self.objects {
+        for object in self.objects.values().chain(&self.world_objects) {
             let old_t = hit_info.as_ref().map(|hit| hit.t).unwrap_or(max_t);
-            let (new_hit, new_report) = self.hit_object(object, ray, min_t, old_t);
+            let (new_hit, new_report) = self.hit_object(object, ray, min_t, old_t, stack);
             let new_t = new_hit.as_ref().map(|hit| hit.t).unwrap_or(f32::INFINITY);
             report.aabb_tests += new_report.aabb_tests;
             report.triangle_tests += new_report.triangle_tests;
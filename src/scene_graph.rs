@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{Mat4f, Object};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Node {
+    Object(Object),
+    Group(Group),
+    Instance { group: String, transform: Mat4f },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Group {
+    pub transform: Mat4f,
+    pub children: Vec<Node>,
+}
+
+impl Group {
+    pub fn new(transform: Mat4f) -> Group {
+        Group {
+            transform,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn add_node(&mut self, node: Node) {
+        self.children.push(node);
+    }
+
+    pub fn add_object(&mut self, object: Object) {
+        self.children.push(Node::Object(object));
+    }
+
+    pub fn add_group(&mut self, group: Group) {
+        self.children.push(Node::Group(group));
+    }
+
+    pub fn add_instance(&mut self, group: impl Into<String>, transform: Mat4f) {
+        self.children.push(Node::Instance { group: group.into(), transform });
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Group::new(Mat4f::identity())
+    }
+}
+
+impl Node {
+    pub fn for_each_object(&self, f: &mut impl FnMut(&Object)) {
+        match self {
+            Node::Object(object) => f(object),
+            Node::Group(group) => group.children.iter().for_each(|child| child.for_each_object(f)),
+            Node::Instance { .. } => {}
+        }
+    }
+
+    // Appends the objects under this node to `objects` with their world transforms.
+    // Instances of unknown groups and instances that would recurse into themselves are skipped.
+    pub fn flatten(&self, parent: &Mat4f, groups: &BTreeMap<String, Group>, objects: &mut Vec<Object>) {
+        Self::flatten_node(self, parent, groups, &mut Vec::new(), objects);
+    }
+
+    fn flatten_node<'a>(node: &'a Node, parent: &Mat4f, groups: &'a BTreeMap<String, Group>,
+                        instanced: &mut Vec<&'a str>, objects: &mut Vec<Object>) {
+        match node {
+            Node::Object(object) => {
+                let mut object = *object;
+                if object.set_transform(parent * object.get_transform()).is_ok() {
+                    objects.push(object);
+                }
+            }
+            Node::Group(group) => {
+                let transform = parent * group.transform;
+                for child in &group.children {
+                    Self::flatten_node(child, &transform, groups, instanced, objects);
+                }
+            }
+            Node::Instance { group: name, transform } => {
+                let Some(group) = groups.get(name) else { return };
+                if instanced.contains(&name.as_str()) {
+                    return;
+                }
+
+                instanced.push(name);
+                let transform = parent * transform * group.transform;
+                for child in &group.children {
+                    Self::flatten_node(child, &transform, groups, instanced, objects);
+                }
+                instanced.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{grey, quad, sky_scene};
+    use crate::{translate, Vec3f};
+
+    #[test]
+    fn test_instances_compose_transforms() {
+        let mut scene = sky_scene(Vec3f::zeros());
+        let mesh = scene.add_mesh(quad(Vec3f::zeros(), 1.0));
+        let material = scene.add_material(grey());
+
+        let mut group = Group::new(translate(&Vec3f::new(0.0, 1.0, 0.0)));
+        group.add_object(Object::new(mesh, translate(&Vec3f::new(1.0, 0.0, 0.0)), material).unwrap());
+        group.add_instance("squad", Mat4f::identity());
+        scene.define_group("squad", group);
+
+        scene.instantiate("squad", translate(&Vec3f::new(0.0, 0.0, 1.0)));
+        scene.instantiate("squad", translate(&Vec3f::new(0.0, 0.0, 2.0)));
+
+        assert_eq!(scene.object_count(), 2);
+        let mut objects = Vec::new();
+        Node::Instance { group: "squad".into(), transform: Mat4f::identity() }
+            .flatten(&translate(&Vec3f::new(0.0, 0.0, 3.0)), &scene.groups, &mut objects);
+        assert_eq!(objects[0].get_transform(), &translate(&Vec3f::new(1.0, 1.0, 3.0)));
+    }
+}
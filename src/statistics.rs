@@ -0,0 +1,133 @@
+use std::fmt::Display;
+use std::mem::size_of;
+
+use crate::bvhs::BVHNode;
+use crate::{Mesh, Scene, Vec3f, Vertex};
+
+// Sizes of a scene, to check what will be rendered without rendering it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SceneStatistics {
+    // Objects placed directly in the scene and through the scene graph.
+    pub objects: usize,
+    pub meshes: usize,
+    pub materials: usize,
+    // Cameras besides the main one.
+    pub cameras: usize,
+    // Triangles of every object, meshes used several times count several times.
+    pub instanced_triangles: usize,
+    // Totals over the meshes, BVH depth and leaf sizes are the worst and average over the meshes.
+    pub geometry: MeshStatistics,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MeshStatistics {
+    pub triangles: usize,
+    pub vertices: usize,
+    pub bvh_nodes: usize,
+    pub bvh_leaves: usize,
+    pub bvh_depth: u32,
+    pub max_leaf_triangles: u32,
+    pub avg_leaf_triangles: f32,
+    // Bytes used by the vertices, triangles, colors and BVH.
+    pub memory: usize,
+}
+
+impl Mesh {
+    pub fn statistics(&self) -> MeshStatistics {
+        let bvh_nodes = self.bvh.get_nodes().len();
+        // Empty meshes have no BVH.
+        let has_bvh = !self.triangles.is_empty() && bvh_nodes > 0;
+        MeshStatistics {
+            triangles: self.triangles.len(),
+            vertices: self.vertices.len(),
+            bvh_nodes,
+            // Every node of the BVH has either two children or none.
+            bvh_leaves: bvh_nodes.div_ceil(2),
+            bvh_depth: if has_bvh { self.bvh.depth() } else { 0 },
+            max_leaf_triangles: if has_bvh { self.bvh.max_triangle_count() } else { 0 },
+            avg_leaf_triangles: if has_bvh { self.bvh.avg_triangle_count() } else { 0.0 },
+            memory: self.vertices.len() * size_of::<Vertex>() + self.triangles.len() * size_of::<[usize;3]>()
+                    + self.colors.len() * size_of::<Vec3f>() + bvh_nodes * size_of::<BVHNode>(),
+        }
+    }
+}
+
+impl Scene {
+    pub fn statistics(&self) -> SceneStatistics {
+        let instanced_triangles = self.objects.values().chain(&self.world_objects)
+                                      .filter_map(|object| self.meshes.get(object.get_mesh()))
+                                      .map(|mesh| mesh.triangles.len())
+                                      .sum();
+
+        let mut geometry = MeshStatistics::default();
+        let mut leaf_triangles = 0.0;
+        for mesh in self.meshes.values().map(Mesh::statistics) {
+            geometry.triangles += mesh.triangles;
+            geometry.vertices += mesh.vertices;
+            geometry.bvh_nodes += mesh.bvh_nodes;
+            geometry.bvh_leaves += mesh.bvh_leaves;
+            geometry.bvh_depth = geometry.bvh_depth.max(mesh.bvh_depth);
+            geometry.max_leaf_triangles = geometry.max_leaf_triangles.max(mesh.max_leaf_triangles);
+            leaf_triangles += mesh.avg_leaf_triangles * mesh.bvh_leaves as f32;
+            geometry.memory += mesh.memory;
+        }
+        if geometry.bvh_leaves > 0 {
+            geometry.avg_leaf_triangles = leaf_triangles / geometry.bvh_leaves as f32;
+        }
+
+        SceneStatistics {
+            objects: self.object_count(),
+            meshes: self.meshes.len(),
+            materials: self.materials.len(),
+            cameras: self.cameras.len(),
+            instanced_triangles,
+            geometry,
+        }
+    }
+}
+
+impl Display for SceneStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "objects:             {}", self.objects)?;
+        writeln!(f, "meshes:              {}", self.meshes)?;
+        writeln!(f, "materials:           {}", self.materials)?;
+        writeln!(f, "named cameras:       {}", self.cameras)?;
+        writeln!(f, "instanced triangles: {}", self.instanced_triangles)?;
+        write!(f, "{}", self.geometry)
+    }
+}
+
+impl Display for MeshStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "triangles:           {}", self.triangles)?;
+        writeln!(f, "vertices:            {}", self.vertices)?;
+        writeln!(f, "BVH nodes:           {} ({} leaves)", self.bvh_nodes, self.bvh_leaves)?;
+        writeln!(f, "BVH depth:           {}", self.bvh_depth)?;
+        writeln!(f, "triangles per leaf:  {:.2} on average, {} at most", self.avg_leaf_triangles, self.max_leaf_triangles)?;
+        write!(f, "memory:              {:.2} MiB", self.memory as f64 / (1024.0 * 1024.0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{grey, quad, sky_scene};
+    use crate::{Mat4f, Object};
+
+    #[test]
+    fn test_scene_statistics() {
+        let mut scene = sky_scene(Vec3f::zeros());
+        let quad = scene.add_mesh(quad(Vec3f::zeros(), 1.0));
+        let material = scene.add_material(grey());
+        for x in 0..3 {
+            scene.add_object(Object::new(quad, Mat4f::new_translation(&Vec3f::new(x as f32, 0.0, 0.0)), material).unwrap());
+        }
+
+        let statistics = scene.statistics();
+        assert_eq!((statistics.objects, statistics.meshes, statistics.materials), (3, 1, 1));
+        assert_eq!(statistics.instanced_triangles, 6);
+        assert_eq!(statistics.geometry, scene.meshes().get(quad).unwrap().statistics());
+        assert_eq!((statistics.geometry.triangles, statistics.geometry.vertices), (2, 4));
+        assert_eq!(statistics.geometry.bvh_leaves as f32 * statistics.geometry.avg_leaf_triangles, 2.0);
+    }
+}
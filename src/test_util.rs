@@ -0,0 +1,55 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Camera, ConstantEnvironment, Lambertian, Material, Mesh, Scene, Vec2f, Vec3f, Vertex};
+
+// An empty scene under a uniform sky, seen from the origin looking down -z.
+pub(crate) fn sky_scene(color: Vec3f) -> Scene {
+    Scene::new(Camera::new(Vec3f::zeros(), -Vec3f::z(), Vec3f::y(), 0.5), Box::new(ConstantEnvironment { color }))
+}
+
+// A square facing +z with its lower left corner at `corner`, made of two triangles.
+pub(crate) fn quad(corner: Vec3f, size: f32) -> Mesh {
+    let vertex = |x: f32, y: f32| Vertex { position: corner + Vec3f::new(x, y, 0.0) * size, normal: Vec3f::z(), uv_coord: Vec2f::new(x, y) };
+    Mesh::new(vec![vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(1.0, 1.0), vertex(0.0, 1.0)], vec![[0, 1, 2], [2, 3, 0]])
+}
+
+pub(crate) fn grey() -> Box<dyn Material> {
+    Box::new(Lambertian { color: Vec3f::repeat(0.5), emission: Vec3f::zeros() })
+}
+
+// A directory of its own in the temporary directory, removed with everything in it when dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> TempDir {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("ray_tracer_test_{name}_{}_{count}", std::process::id()));
+        // Left over by a process that had the same id and did not finish.
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
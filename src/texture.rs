@@ -0,0 +1,199 @@
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Serialize, Deserialize};
+
+use crate::math::*;
+
+// Where the pixels of a texture come from. Only the source is serialized, the pixels are decoded again when a scene is loaded.
+#[derive(Debug, Clone)]
+pub enum TextureSource {
+    File(PathBuf),
+    // An encoded image (PNG, JPEG...) stored inside the scene, as glTF files do.
+    Embedded(Vec<u8>),
+}
+
+// An image sampled with uv coordinates.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "TextureFile", into = "TextureFile")]
+pub struct Texture {
+    source: TextureSource,
+    srgb: bool,
+    channel: Option<usize>,
+    pixels: Vec<Vec3f>,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TextureFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    // Base64 of the encoded image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+    #[serde(default = "default_srgb")]
+    srgb: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    channel: Option<usize>,
+}
+
+impl TextureFile {
+    const FIELDS: [&'static str; 4] = ["path", "data", "srgb", "channel"];
+}
+
+fn default_srgb() -> bool {
+    true
+}
+
+impl TryFrom<TextureFile> for Texture {
+    type Error = String;
+    fn try_from(file: TextureFile) -> Result<Self, Self::Error> {
+        let texture = match (file.path, file.data) {
+            (Some(path), _) => Texture::load(&path, file.srgb).map_err(|error| format!("could not load texture {}: {error}", path.display()))?,
+            (None, Some(data)) => {
+                let bytes = BASE64.decode(data).map_err(|error| format!("invalid embedded texture: {error}"))?;
+                Texture::decode(bytes, file.srgb).map_err(|error| format!("invalid embedded texture: {error}"))?
+            }
+            (None, None) => return Err("a texture needs either a `path` or `data`".to_string()),
+        };
+        Ok(match file.channel {
+            Some(channel) => texture.with_channel(channel),
+            None => texture,
+        })
+    }
+}
+
+impl From<Texture> for TextureFile {
+    fn from(texture: Texture) -> Self {
+        let (path, data) = match texture.source {
+            TextureSource::File(path) => (Some(path), None),
+            TextureSource::Embedded(bytes) => (None, Some(BASE64.encode(bytes))),
+        };
+        TextureFile { path, data, srgb: texture.srgb, channel: texture.channel }
+    }
+}
+
+// Calls `f` on the path of every texture in serialized materials, or anything holding materials. Textures are the
+// objects with nothing but the fields of a `TextureFile`, other fields named `path` are left alone.
+pub(crate) fn for_each_texture_path(value: &mut serde_json::Value, f: &mut impl FnMut(&mut String)) {
+    match value {
+        serde_json::Value::Object(fields) if fields.keys().all(|key| TextureFile::FIELDS.contains(&key.as_str())) => {
+            if let Some(serde_json::Value::String(path)) = fields.get_mut("path") {
+                f(path);
+            }
+        }
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(|value| for_each_texture_path(value, f)),
+        serde_json::Value::Array(values) => values.iter_mut().for_each(|value| for_each_texture_path(value, f)),
+        _ => {}
+    }
+}
+
+impl Debug for Texture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("Texture");
+        match &self.source {
+            TextureSource::File(path) => debug.field("path", path),
+            TextureSource::Embedded(bytes) => debug.field("embedded_bytes", &bytes.len()),
+        };
+        debug.field("width", &self.width).field("height", &self.height).finish()
+    }
+}
+
+impl Texture {
+    // Color textures are stored with gamma correction (`srgb`), data such as roughness or opacity is linear.
+    pub fn load(path: impl AsRef<Path>, srgb: bool) -> image::ImageResult<Texture> {
+        let path = path.as_ref();
+        Ok(Texture::from_image(image::open(path)?, TextureSource::File(path.to_path_buf()), srgb))
+    }
+
+    pub fn decode(bytes: Vec<u8>, srgb: bool) -> image::ImageResult<Texture> {
+        let image = image::load_from_memory(&bytes)?;
+        Ok(Texture::from_image(image, TextureSource::Embedded(bytes), srgb))
+    }
+
+    fn from_image(image: image::DynamicImage, source: TextureSource, srgb: bool) -> Texture {
+        let image = image.into_rgb32f();
+        let (width, height) = image.dimensions();
+        let pixels = image.pixels()
+                          .map(|pixel| Vec3f::new(pixel[0], pixel[1], pixel[2]))
+                          .map(|color| if srgb { color.map(|x| x.powf(2.2)) } else { color })
+                          .collect();
+
+        Texture { source, srgb, channel: None, pixels, width, height }
+    }
+
+    // Keeps a single channel, repeated in the three components, for data packed in one image
+    // such as glTF's metallic-roughness textures.
+    pub fn with_channel(mut self, channel: usize) -> Texture {
+        for pixel in &mut self.pixels {
+            *pixel = Vec3f::repeat(pixel[channel.min(2)]);
+        }
+        self.channel = Some(channel);
+        self
+    }
+
+    pub fn source(&self) -> &TextureSource {
+        &self.source
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match &self.source {
+            TextureSource::File(path) => Some(path),
+            TextureSource::Embedded(_) => None,
+        }
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
+    // Bilinear lookup, the texture repeats outside of [0, 1] and v goes from the bottom to the top of the image.
+    pub fn sample(&self, uv: &Vec2f) -> Vec3f {
+        if self.pixels.is_empty() {
+            return Vec3f::zeros();
+        }
+
+        let x = uv.x.rem_euclid(1.0) * self.width as f32 - 0.5;
+        let y = (1.0 - uv.y.rem_euclid(1.0)) * self.height as f32 - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+
+        let pixel = |i: f32, j: f32| {
+            let i = (i as i64).rem_euclid(self.width as i64) as usize;
+            let j = (j as i64).rem_euclid(self.height as i64) as usize;
+            self.pixels[j * self.width as usize + i]
+        };
+        let (x, y) = (x.floor(), y.floor());
+        let top = (1.0 - fx) * pixel(x, y) + fx * pixel(x + 1.0, y);
+        let bottom = (1.0 - fx) * pixel(x, y + 1.0) + fx * pixel(x + 1.0, y + 1.0);
+        (1.0 - fy) * top + fy * bottom
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_for_each_texture_path() {
+        let mut material = json!({
+            "type": "Textured",
+            "path": "not/a/texture",
+            "albedo": { "path": "albedo.png", "srgb": true },
+            "layers": [{ "path": "layer.png", "channel": 0 }, { "data": "AAAA" }],
+            "curve": { "path": "curve.csv", "samples": 16 },
+        });
+        let mut paths = Vec::new();
+        for_each_texture_path(&mut material, &mut |path| {
+            paths.push(path.clone());
+            *path = format!("textures/{path}");
+        });
+        assert_eq!(paths, ["albedo.png", "layer.png"]);
+        assert_eq!(material["path"], "not/a/texture");
+        assert_eq!(material["layers"][0]["path"], "textures/layer.png");
+        assert_eq!(material["curve"]["path"], "curve.csv");
+    }
+}
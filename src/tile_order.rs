@@ -0,0 +1,90 @@
+// Order in which the tiles of a pass are started, which is what a preview shows filling up first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    // Row by row from the top left corner.
+    #[default]
+    Scanline,
+    // Ring by ring from the center of the image, where the subject usually is.
+    Spiral,
+    // Along a Hilbert curve, each tile next to the previous one so that the workers share what they have in cache.
+    Hilbert,
+}
+
+impl TileOrder {
+    // Indices of the tiles of a grid, counted row by row, in the order they should be rendered.
+    pub fn order(self, columns: u32, rows: u32) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..columns as usize * rows as usize).collect();
+        let position = |index: usize| ((index % columns as usize) as u32, (index / columns as usize) as u32);
+        match self {
+            TileOrder::Scanline => {}
+            TileOrder::Spiral => {
+                let center_x = (columns as f32 - 1.0) / 2.0;
+                let center_y = (rows as f32 - 1.0) / 2.0;
+                let ring_and_angle = |index: usize| {
+                    let (x, y) = position(index);
+                    let (dx, dy) = (x as f32 - center_x, y as f32 - center_y);
+                    (dx.abs().max(dy.abs()), dy.atan2(dx))
+                };
+                indices.sort_by(|&a, &b| {
+                    let (a, b) = (ring_and_angle(a), ring_and_angle(b));
+                    a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+                });
+            }
+            TileOrder::Hilbert => {
+                let size = columns.max(rows).next_power_of_two();
+                indices.sort_by_key(|&index| {
+                    let (x, y) = position(index);
+                    hilbert_index(size, x, y)
+                });
+            }
+        }
+        indices
+    }
+}
+
+// Distance along the Hilbert curve filling a `size` x `size` grid, `size` being a power of two.
+fn hilbert_index(size: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0;
+    let mut s = size / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        index += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+        // Rotates the quadrant so that the curve of the next level starts where this one arrives.
+        if ry == 0 {
+            if rx == 1 {
+                x = size - 1 - x;
+                y = size - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tile_orders() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let mut indices = order.order(5, 3);
+            indices.sort();
+            assert_eq!(indices, (0..15).collect::<Vec<_>>());
+        }
+        assert_eq!(TileOrder::Scanline.order(3, 2), [0, 1, 2, 3, 4, 5]);
+        // The center first, then the ring around it.
+        let spiral = TileOrder::Spiral.order(3, 3);
+        assert_eq!(spiral[0], 4);
+        assert!(spiral[1..].iter().all(|&index| index != 4));
+
+        // Every tile is next to the previous one.
+        let hilbert = TileOrder::Hilbert.order(4, 4);
+        for pair in hilbert.windows(2) {
+            let (a, b) = ((pair[0] % 4) as i32 + (pair[0] / 4) as i32 * 10, (pair[1] % 4) as i32 + (pair[1] / 4) as i32 * 10);
+            assert!([1, 10].contains(&(a - b).abs()), "{hilbert:?}");
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use crate::bvhs::NodeContent;
+use crate::{MeshLoadError, Group, Handle, MatearialHandle, Mesh, MeshHandle, Node, Object, ObjectHandle, Scene};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneIssueKind {
+    DanglingMesh(MeshHandle),
+    DanglingMaterial(MatearialHandle),
+    DanglingObject(ObjectHandle),
+    NonInvertibleTransform,
+    NanVertex,
+    TriangleIndexOutOfRange { index: usize, vertex_count: usize },
+    DegenerateTriangle,
+    EmptyMesh,
+    InvalidBvh,
+    UnknownGroup(String),
+    RecursiveInstance(String),
+    UnknownMesh(String),
+    UnknownMaterial(String),
+}
+
+impl Display for SceneIssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneIssueKind::DanglingMesh(handle) => write!(f, "mesh {:?} does not exist", handle.raw()),
+            SceneIssueKind::DanglingMaterial(handle) => write!(f, "material {:?} does not exist", handle.raw()),
+            SceneIssueKind::DanglingObject(handle) => write!(f, "object {:?} does not exist", handle.raw()),
+            SceneIssueKind::NonInvertibleTransform => write!(f, "the transform is not invertible"),
+            SceneIssueKind::NanVertex => write!(f, "the vertex has a NaN or infinite component"),
+            SceneIssueKind::TriangleIndexOutOfRange { index, vertex_count } =>
+                write!(f, "vertex index {index} is out of range for a mesh of {vertex_count} vertices"),
+            SceneIssueKind::DegenerateTriangle => write!(f, "the triangle has no area"),
+            SceneIssueKind::EmptyMesh => write!(f, "the mesh has no triangles"),
+            SceneIssueKind::InvalidBvh => write!(f, "the BVH does not match the triangles of the mesh"),
+            SceneIssueKind::UnknownGroup(name) => write!(f, "there is no group named \"{name}\""),
+            SceneIssueKind::RecursiveInstance(name) => write!(f, "the group \"{name}\" ends up instancing itself"),
+            SceneIssueKind::UnknownMesh(name) => write!(f, "there is no mesh named \"{name}\""),
+            SceneIssueKind::UnknownMaterial(name) => write!(f, "there is no material named \"{name}\""),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneIssue {
+    // Location of the problem in the scene's JSON, e.g. `objects[3].value.mesh`.
+    pub path: String,
+    pub kind: SceneIssueKind,
+}
+
+impl SceneIssue {
+    // Degenerate triangles are common in scanned meshes and are simply never hit, so they don't prevent rendering.
+    pub fn is_warning(&self) -> bool {
+        matches!(self.kind, SceneIssueKind::DegenerateTriangle)
+    }
+}
+
+impl Display for SceneIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = if self.is_warning() {"warning"} else {"error"};
+        write!(f, "{severity}: {}: {}", self.path, self.kind)
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    // Errors of the TOML authoring format.
+    Authoring(String),
+    UnsupportedVersion(u32),
+    Migration { from: u32, message: String },
+    MeshLoad { path: PathBuf, error: MeshLoadError },
+    Invalid(Vec<SceneIssue>),
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "could not read the scene: {error}"),
+            SceneError::Parse(error) => write!(f, "could not parse the scene: {error}"),
+            SceneError::Authoring(message) => write!(f, "could not parse the scene: {message}"),
+            SceneError::UnsupportedVersion(version) =>
+                write!(f, "the scene has version {version} but only versions up to {} are supported", Scene::VERSION),
+            SceneError::Migration { from, message } => write!(f, "could not upgrade the scene from version {from}: {message}"),
+            SceneError::MeshLoad { path, error } => write!(f, "could not load the mesh {}: {error}", path.display()),
+            SceneError::Invalid(issues) => {
+                write!(f, "the scene has {} problem(s):", issues.len())?;
+                for issue in issues {
+                    write!(f, "\n    {issue}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io(error) => Some(error),
+            SceneError::Parse(error) => Some(error),
+            SceneError::MeshLoad { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(error: serde_json::Error) -> Self {
+        SceneError::Parse(error)
+    }
+}
+
+impl From<toml::de::Error> for SceneError {
+    fn from(error: toml::de::Error) -> Self {
+        SceneError::Authoring(error.to_string())
+    }
+}
+
+impl Scene {
+    // Lists every problem of the scene, `Scene::load` refuses scenes with any issue that isn't a warning.
+    pub fn validate(&self) -> Vec<SceneIssue> {
+        let mut issues = Vec::new();
+
+        for (handle, _, mesh) in self.meshes.iter() {
+            validate_mesh(mesh, &format!("meshes[{}].value", handle.raw().index), &mut issues);
+        }
+
+        for (handle, _, object) in self.objects.iter() {
+            self.validate_object(object, &format!("objects[{}].value", handle.raw().index), &mut issues);
+        }
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            self.validate_node(node, &format!("nodes[{i}]"), &mut issues);
+        }
+
+        for (name, group) in &self.groups {
+            let path = format!("groups.{name}");
+            if group.transform.try_inverse().is_none() {
+                issues.push(SceneIssue { path: format!("{path}.transform"), kind: SceneIssueKind::NonInvertibleTransform });
+            }
+            for (i, node) in group.children.iter().enumerate() {
+                self.validate_node(node, &format!("{path}.children[{i}]"), &mut issues);
+            }
+        }
+
+        for (i, (handle, _)) in self.animation.objects.iter().enumerate() {
+            if !self.objects.contains(*handle) {
+                issues.push(SceneIssue { path: format!("animation.objects[{i}][0]"), kind: SceneIssueKind::DanglingObject(*handle) });
+            }
+        }
+        for (i, (handle, _)) in self.animation.materials.iter().enumerate() {
+            if !self.materials.contains(*handle) {
+                issues.push(SceneIssue { path: format!("animation.materials[{i}][0]"), kind: SceneIssueKind::DanglingMaterial(*handle) });
+            }
+        }
+
+        issues
+    }
+
+    fn validate_object(&self, object: &Object, path: &str, issues: &mut Vec<SceneIssue>) {
+        if !self.meshes.contains(object.get_mesh()) {
+            issues.push(SceneIssue { path: format!("{path}.mesh"), kind: SceneIssueKind::DanglingMesh(object.get_mesh()) });
+        }
+        if !self.materials.contains(object.get_material()) {
+            issues.push(SceneIssue { path: format!("{path}.material"), kind: SceneIssueKind::DanglingMaterial(object.get_material()) });
+        }
+        if object.get_transform().try_inverse().is_none() {
+            issues.push(SceneIssue { path: format!("{path}.transform"), kind: SceneIssueKind::NonInvertibleTransform });
+        }
+    }
+
+    fn validate_node(&self, node: &Node, path: &str, issues: &mut Vec<SceneIssue>) {
+        match node {
+            Node::Object(object) => self.validate_object(object, &format!("{path}.Object"), issues),
+            Node::Group(group) => {
+                if group.transform.try_inverse().is_none() {
+                    issues.push(SceneIssue { path: format!("{path}.Group.transform"), kind: SceneIssueKind::NonInvertibleTransform });
+                }
+                for (i, child) in group.children.iter().enumerate() {
+                    self.validate_node(child, &format!("{path}.Group.children[{i}]"), issues);
+                }
+            }
+            Node::Instance { group, transform } => {
+                if transform.try_inverse().is_none() {
+                    issues.push(SceneIssue { path: format!("{path}.Instance.transform"), kind: SceneIssueKind::NonInvertibleTransform });
+                }
+                if !self.groups.contains_key(group) {
+                    issues.push(SceneIssue { path: format!("{path}.Instance.group"), kind: SceneIssueKind::UnknownGroup(group.clone()) });
+                } else if instances_group(&self.groups, group, group, &mut Vec::new()) {
+                    issues.push(SceneIssue { path: format!("{path}.Instance.group"), kind: SceneIssueKind::RecursiveInstance(group.clone()) });
+                }
+            }
+        }
+    }
+}
+
+// Whether placing the group `name` ends up placing the group `target`.
+fn instances_group<'a>(groups: &'a BTreeMap<String, Group>, name: &'a str, target: &str, visited: &mut Vec<&'a str>) -> bool {
+    fn visit<'a>(node: &'a Node, groups: &'a BTreeMap<String, Group>, target: &str, visited: &mut Vec<&'a str>) -> bool {
+        match node {
+            Node::Object(_) => false,
+            Node::Group(group) => group.children.iter().any(|child| visit(child, groups, target, visited)),
+            Node::Instance { group, .. } => group == target || instances_group(groups, group, target, visited),
+        }
+    }
+
+    if visited.contains(&name) {
+        return false;
+    }
+    visited.push(name);
+
+    let Some(group) = groups.get(name) else { return false };
+    group.children.iter().any(|child| visit(child, groups, target, visited))
+}
+
+fn validate_mesh(mesh: &Mesh, path: &str, issues: &mut Vec<SceneIssue>) {
+    if mesh.triangles.is_empty() || mesh.vertices.is_empty() {
+        issues.push(SceneIssue { path: path.to_string(), kind: SceneIssueKind::EmptyMesh });
+        return;
+    }
+
+    for (i, vertex) in mesh.vertices.iter().enumerate() {
+        let finite = vertex.position.iter().chain(&vertex.normal).chain(&vertex.uv_coord).all(|x| x.is_finite());
+        if !finite {
+            issues.push(SceneIssue { path: format!("{path}.vertices[{i}]"), kind: SceneIssueKind::NanVertex });
+        }
+    }
+
+    let vertex_count = mesh.vertices.len();
+    for (i, triangle) in mesh.triangles.iter().enumerate() {
+        if let Some(&index) = triangle.iter().find(|&&index| index >= vertex_count) {
+            issues.push(SceneIssue { path: format!("{path}.triangles[{i}]"), kind: SceneIssueKind::TriangleIndexOutOfRange { index, vertex_count } });
+            continue;
+        }
+
+        let [a, b, c] = triangle.map(|index| mesh.vertices[index].position);
+        if (b - a).cross(&(c - a)).norm_squared() == 0.0 {
+            issues.push(SceneIssue { path: format!("{path}.triangles[{i}]"), kind: SceneIssueKind::DegenerateTriangle });
+        }
+    }
+
+    let nodes = mesh.bvh.get_nodes();
+    let valid_bvh = !nodes.is_empty() && nodes.iter().all(|node| match node.content {
+        NodeContent::Children((left, right)) => left < nodes.len() && right < nodes.len(),
+        NodeContent::Triangles((start, end)) => start <= end && end <= mesh.triangles.len(),
+    });
+    if !valid_bvh {
+        issues.push(SceneIssue { path: format!("{path}.bvh"), kind: SceneIssueKind::InvalidBvh });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{grey, quad, sky_scene};
+    use crate::{Mat4f, Vec3f};
+
+    #[test]
+    fn test_reports_every_issue() {
+        let mut scene = sky_scene(Vec3f::zeros());
+        let mesh = scene.add_mesh(quad(Vec3f::zeros(), 1.0));
+        let material = scene.add_material(grey());
+        scene.add_object(Object::new(mesh, Mat4f::identity(), material).unwrap());
+        assert!(scene.validate().is_empty());
+
+        let mut json = serde_json::to_value(&scene).unwrap();
+        json["objects"][0]["value"]["mesh"]["index"] = 7.into();
+        json["objects"][0]["value"]["transform"] = serde_json::to_value(Mat4f::zeros()).unwrap();
+        json["meshes"][0]["value"]["triangles"][0] = serde_json::json!([0, 0, 4]);
+        let scene: Scene = serde_json::from_value(json).unwrap();
+
+        let issues = scene.validate();
+        let paths: Vec<_> = issues.iter().map(|issue| issue.path.as_str()).collect();
+        assert_eq!(paths, ["meshes[0].value.triangles[0]", "objects[0].value.mesh", "objects[0].value.transform"]);
+    }
+}